@@ -1,9 +1,13 @@
 use c64_assembler::{
-    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    builder::{ApplicationBuilder, FunctionBuilder, InstructionBuilder, ModuleBuilder},
     generator::{print_hexdump, DasmGenerator, Generator, ProgramGenerator},
     validator::{AssemblerResult, Validator},
 };
-use c64_encoder::command::modules::EngineBuilder;
+use c64_encoder::{
+    builder::demo::DemoBuilder,
+    command::modules::{EngineBuilder, EngineOptions, IrqPlayerOptions},
+    decoder::reader::Reader,
+};
 
 pub fn intro_application() -> AssemblerResult<Vec<u8>> {
     let data = vec![
@@ -687,6 +691,10 @@ pub fn intro_application() -> AssemblerResult<Vec<u8>> {
         0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
         0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     ];
+    // Played from the raster interrupt, so restart at the first frame instead of running past the last one.
+    let (mut demo, _) = data.read::<DemoBuilder>().expect("intro data should be valid");
+    demo.goto_frame(0);
+    let data = demo.build();
     let engine_data = ModuleBuilder::default()
         .name("engine_data")
         .instructions(InstructionBuilder::default().label("engine_data").raw(&data).build())
@@ -703,21 +711,32 @@ pub fn intro_application() -> AssemblerResult<Vec<u8>> {
                         .add_basic_header()
                         .label("main_entry_point")
                         .jsr_addr("engine__init")
-                        .ldx_imm(100)
-                        .label("next_frame")
-                        .txa()
-                        .pha()
-                        .jsr_addr("engine__frame__process")
-                        .pla()
-                        .tax()
-                        .dex()
-                        .bne_addr("next_frame")
+                        .label("main__wait_key")
+                        .comment("Play the intro until a key is pressed")
+                        .jsr_addr("KERNAL_GETIN")
+                        .beq_addr("main__wait_key")
+                        .jsr_addr("engine__deinit")
                         .rts()
                         .build(),
                 )
+                .function(
+                    FunctionBuilder::default()
+                        .name("main__scan_keyboard")
+                        .doc(&["Fill the keyboard buffer, the KERNAL doesn't while the intro plays."])
+                        .instructions(InstructionBuilder::default().jmp_addr("KERNAL_SCNKEY").build())
+                        .build(),
+                )
                 .build(),
         )
-        .add_engine()
+        .define_address("KERNAL_SCNKEY", 0xFF9F)
+        .define_address("KERNAL_GETIN", 0xFFE4)
+        .add_engine_with_options(EngineOptions {
+            irq_player: Some(IrqPlayerOptions {
+                hook: Some("main__scan_keyboard"),
+                ..IrqPlayerOptions::default()
+            }),
+            ..EngineOptions::default()
+        })
         .module(engine_data)
         .build()
         .unwrap();
@@ -0,0 +1,175 @@
+use c64::image_container::{bit_char::BitCharImage, image_sequence::ImageSequence};
+use c64_colors::colors::Color;
+
+use crate::{
+    animation::{AnimationEncoder, AnimationError, OneCharset, StrategyReport},
+    command::{set_palette4::SetPalette4, Command},
+    evaluator::evaluate,
+    optimizer::{FrameOptimizer, FramePart, FullCharsetUpdate, OptimizerError, UnchangedScreenChars},
+};
+
+/// Sequence of full screen images, the char at each offset is given by `char(image, offset)`.
+fn image_sequence(num_images: usize, char: impl Fn(usize, usize) -> u64) -> ImageSequence<BitCharImage> {
+    let mut images = ImageSequence::new();
+    for image_index in 0..num_images {
+        let mut image = BitCharImage::new(40, 25);
+        for (offset, image_char) in image.chars.iter_mut().enumerate() {
+            *image_char = char(image_index, offset);
+        }
+        images.push(image);
+    }
+    images
+}
+
+/// Evaluate the demo and check that each frame shows the image.
+fn assert_shows_images(demo_bytes: &[u8], images: &ImageSequence<BitCharImage>) {
+    let states = evaluate(demo_bytes).unwrap();
+    assert_eq!(images.len() + 1, states.len());
+    for (image, state) in images.iter().zip(&states[1..]) {
+        let shown_chars = state
+            .text_screen
+            .screen_chars
+            .iter()
+            .map(|screen_char| state.charset.char(*screen_char))
+            .collect::<Vec<u64>>();
+        assert_eq!(image.chars, shown_chars);
+    }
+}
+
+#[test]
+fn moving_char_uses_one_charset() {
+    let images = image_sequence(4, |image, offset| if offset == image * 41 { 0xFF } else { 0x00 });
+
+    let encoding = AnimationEncoder::default().encode(&images).unwrap();
+
+    assert_eq!(3, encoding.reports.len());
+    assert!(encoding.reports.iter().all(|report| report.result.is_ok()));
+    let best_byte_size = encoding
+        .reports
+        .iter()
+        .filter_map(|report| report.result.clone().ok())
+        .min()
+        .unwrap();
+    assert_eq!(best_byte_size, encoding.demo.build().len());
+    assert_shows_images(&encoding.demo.build(), &images);
+}
+
+#[test]
+fn changing_chars_use_per_frame_charset() {
+    // 200 different chars per image, 800 in total.
+    let images = image_sequence(4, |image, offset| (image * 1000 + offset % 200) as u64);
+
+    let encoding = AnimationEncoder::default().encode(&images).unwrap();
+
+    assert_eq!("per frame charset", encoding.strategy);
+    assert_eq!(
+        vec![
+            StrategyReport {
+                name: "one charset",
+                result: Err(AnimationError::TooManyChars { needed: 800 }),
+            },
+            StrategyReport {
+                name: "static with dynamic charset",
+                result: Err(AnimationError::TooManyChars { needed: 1000 }),
+            },
+        ],
+        encoding.reports[..2]
+    );
+    assert_shows_images(&encoding.demo.build(), &images);
+}
+
+#[test]
+fn static_chars_with_dynamic_charset() {
+    // Only the first 100 offsets change, with a different char in every image.
+    let images = image_sequence(8, |image, offset| {
+        if offset < 100 {
+            (image * 100 + offset) as u64
+        } else {
+            0x1000 + (offset % 4) as u64
+        }
+    });
+
+    let encoding = AnimationEncoder::default().encode(&images).unwrap();
+
+    assert!(matches!(
+        encoding.reports[0].result,
+        Err(AnimationError::TooManyChars { needed: 804 })
+    ));
+    assert!(encoding.reports[1].result.is_ok());
+    assert_shows_images(&encoding.demo.build(), &images);
+}
+
+#[test]
+fn palette_is_set_in_first_frame() {
+    let images = image_sequence(2, |_, _| 0x00);
+    let palette = [Color::Black, Color::White, Color::DarkGrey, Color::Grey];
+
+    let encoding = AnimationEncoder::default().palette(palette).encode(&images).unwrap();
+
+    assert_eq!(
+        Command::SetPalette4(SetPalette4 { palette }),
+        encoding.demo.frames[0].commands[0]
+    );
+    assert_shows_images(&encoding.demo.build(), &images);
+}
+
+#[test]
+fn too_many_chars_in_single_image() {
+    let images = image_sequence(2, |_, offset| (offset % 300) as u64);
+
+    let error = AnimationEncoder::default().encode(&images).unwrap_err();
+
+    let AnimationError::NoStrategyFits(reports) = error else {
+        panic!("unexpected error {error:?}");
+    };
+    assert_eq!(3, reports.len());
+    assert!(reports
+        .iter()
+        .all(|report| matches!(report.result, Err(AnimationError::TooManyChars { .. }))));
+}
+
+#[test]
+fn no_images() {
+    let images = ImageSequence::new();
+
+    assert_eq!(
+        AnimationError::NoImages,
+        AnimationEncoder::default().encode(&images).unwrap_err()
+    );
+}
+
+#[test]
+fn image_not_covering_the_screen() {
+    let mut images = image_sequence(1, |_, _| 0x00);
+    images.push(BitCharImage::new(8, 8));
+
+    assert_eq!(
+        AnimationError::InvalidImageSize {
+            image: 1,
+            num_chars: 64
+        },
+        AnimationEncoder::default().encode(&images).unwrap_err()
+    );
+}
+
+#[test]
+fn optimizer_without_applicable_strategy() {
+    let images = image_sequence(2, |_, _| 0x00);
+    let optimizer = FrameOptimizer::new(vec![Box::new(UnchangedScreenChars {}), Box::new(FullCharsetUpdate {})]);
+
+    let error = AnimationEncoder::new(vec![Box::new(OneCharset {})])
+        .optimizer(optimizer)
+        .encode(&images)
+        .unwrap_err();
+
+    assert_eq!(
+        AnimationError::NoStrategyFits(vec![StrategyReport {
+            name: "one charset",
+            result: Err(AnimationError::Optimizer {
+                frame: 0,
+                error: OptimizerError::NoStrategyFits(FramePart::ScreenChars),
+            }),
+        }]),
+        error
+    );
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use c64::image_container::{
+    bit_char::{BitCharImage, BitEncodedChar},
+    image_sequence::ImageSequence,
+};
+
+/// How much replacing a char costs when chars are merged.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CharWeight {
+    /// Every char costs the same.
+    Uniform,
+    /// Chars cost the number of cells that show them in all images, which minimizes the number of changed pixels.
+    Cells,
+    /// Chars cost the number of images that show them, which favours chars that would otherwise need to be uploaded
+    /// again and again.
+    Frames,
+}
+
+/// Lossy stage that replaces chars by visually similar chars of the animation, so the animation needs fewer chars.
+///
+/// Chars are grouped in clusters that are represented by one of their chars. The cluster that is cheapest to merge,
+/// its weight times the number of pixels its representative differs from the nearest other representative, is
+/// merged first. Merging stops when the animation contains at most `max_chars` chars or when no cluster can be merged
+/// without changing more than `max_distance` pixels of a cell.
+#[derive(Debug, Copy, Clone)]
+pub struct CharClustering {
+    max_chars: Option<usize>,
+    max_distance: u32,
+    weight: CharWeight,
+}
+
+impl Default for CharClustering {
+    fn default() -> Self {
+        Self {
+            max_chars: Some(256),
+            max_distance: 8,
+            weight: CharWeight::Cells,
+        }
+    }
+}
+
+/// Chars replaced by [CharClustering::apply].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClusteringReport {
+    pub num_chars_before: usize,
+    /// Number of chars after merging, can still be more than the requested number of chars when the error budget
+    /// doesn't allow more merges.
+    pub num_chars_after: usize,
+    /// Number of pixels that changed, summed over all cells of all images.
+    pub pixel_error: usize,
+    /// Largest number of pixels that changed in a single cell.
+    pub max_cell_error: u32,
+}
+
+/// Chars that are replaced by the same representative.
+struct Cluster {
+    representative: BitEncodedChar,
+    members: Vec<BitEncodedChar>,
+    weight: usize,
+    /// Largest distance between a member and the representative.
+    radius: u32,
+    /// Nearest cluster this cluster can be merged into and the distance between the representatives.
+    nearest: Option<(usize, u32)>,
+    merged: bool,
+}
+
+impl CharClustering {
+    /// Stop merging when the animation contains at most the given number of chars. `None` merges as long as the error
+    /// budget allows, which reduces the number of chars that change between frames.
+    pub fn max_chars(&mut self, max_chars: Option<usize>) -> &mut Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    /// Error budget: the maximum number of pixels a cell may change.
+    pub fn max_distance(&mut self, max_distance: u32) -> &mut Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn weight(&mut self, weight: CharWeight) -> &mut Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Replace the chars of the images and report the error introduced.
+    pub fn apply(&self, images: &mut ImageSequence<BitCharImage>) -> ClusteringReport {
+        let mut num_cells = HashMap::<BitEncodedChar, usize>::new();
+        let mut num_frames = HashMap::<BitEncodedChar, usize>::new();
+        for image in images.iter() {
+            for char in &image.chars {
+                *num_cells.entry(*char).or_default() += 1;
+            }
+            for char in image.all_unique_chars() {
+                *num_frames.entry(char).or_default() += 1;
+            }
+        }
+        let mut chars = num_cells.keys().copied().collect::<Vec<BitEncodedChar>>();
+        chars.sort();
+
+        let mut clusters = chars
+            .iter()
+            .map(|char| Cluster {
+                representative: *char,
+                members: vec![*char],
+                weight: match self.weight {
+                    CharWeight::Uniform => 1,
+                    CharWeight::Cells => num_cells[char],
+                    CharWeight::Frames => num_frames[char],
+                },
+                radius: 0,
+                nearest: None,
+                merged: false,
+            })
+            .collect::<Vec<Cluster>>();
+        for cluster in 0..clusters.len() {
+            clusters[cluster].nearest = self.nearest(&clusters, cluster);
+        }
+
+        let mut num_clusters = clusters.len();
+        while self.max_chars.is_none_or(|max_chars| num_clusters > max_chars) {
+            let Some((_, from, into)) = clusters
+                .iter()
+                .enumerate()
+                .filter(|(_, cluster)| !cluster.merged)
+                .filter_map(|(index, cluster)| {
+                    let (into, distance) = cluster.nearest?;
+                    Some((cluster.weight * distance as usize, index, into))
+                })
+                .min()
+            else {
+                break;
+            };
+
+            let members = std::mem::take(&mut clusters[from].members);
+            let representative = clusters[into].representative;
+            let radius = members
+                .iter()
+                .map(|member| distance(*member, representative))
+                .max()
+                .unwrap_or_default();
+            clusters[into].radius = clusters[into].radius.max(radius);
+            clusters[into].weight += clusters[from].weight;
+            clusters[into].members.extend(members);
+            clusters[from].merged = true;
+            num_clusters -= 1;
+
+            for cluster in 0..clusters.len() {
+                if !clusters[cluster].merged
+                    && (cluster == into || clusters[cluster].nearest.is_some_and(|(nearest, _)| nearest == from))
+                {
+                    clusters[cluster].nearest = self.nearest(&clusters, cluster);
+                }
+            }
+        }
+
+        let replacements = clusters
+            .iter()
+            .flat_map(|cluster| cluster.members.iter().map(|member| (*member, cluster.representative)))
+            .filter(|(member, representative)| member != representative)
+            .collect::<HashMap<BitEncodedChar, BitEncodedChar>>();
+        let mut pixel_error = 0;
+        let mut max_cell_error = 0;
+        for image in images.images.iter_mut() {
+            for char in image.chars.iter_mut() {
+                if let Some(representative) = replacements.get(char) {
+                    let cell_error = distance(*char, *representative);
+                    pixel_error += cell_error as usize;
+                    max_cell_error = max_cell_error.max(cell_error);
+                    *char = *representative;
+                }
+            }
+        }
+
+        ClusteringReport {
+            num_chars_before: chars.len(),
+            num_chars_after: num_clusters,
+            pixel_error,
+            max_cell_error,
+        }
+    }
+
+    /// Nearest cluster the given cluster can be merged into without exceeding the error budget. Merging into a
+    /// cluster at distance `d` changes the pixels of a member by at most the radius of the cluster plus `d`.
+    fn nearest(&self, clusters: &[Cluster], cluster: usize) -> Option<(usize, u32)> {
+        let Cluster {
+            representative, radius, ..
+        } = clusters[cluster];
+        clusters
+            .iter()
+            .enumerate()
+            .filter(|(index, other)| *index != cluster && !other.merged)
+            .map(|(index, other)| (index, distance(representative, other.representative), other.weight))
+            .filter(|(_, distance, _)| radius + distance <= self.max_distance)
+            .min_by_key(|(index, distance, weight)| (*distance, std::cmp::Reverse(*weight), *index))
+            .map(|(index, distance, _)| (index, distance))
+    }
+}
+
+/// Number of pixels that differ between two chars.
+fn distance(a: BitEncodedChar, b: BitEncodedChar) -> u32 {
+    (a ^ b).count_ones()
+}
@@ -0,0 +1,99 @@
+use c64::image_container::{bit_char::BitCharImage, image_sequence::ImageSequence};
+
+use crate::animation::{CharClustering, CharWeight, ClusteringReport};
+
+/// 150 chars that differ at least 4 pixels from each other.
+fn base_char(index: usize) -> u64 {
+    index as u64 * 0x0001_0001_0001_0001
+}
+
+/// Char that differs a single pixel from the base char.
+fn variant_char(index: usize) -> u64 {
+    base_char(index) | 1 << 63
+}
+
+/// Two images with 150 base chars in the first image and 150 variants of them in the second image. The variants are
+/// only shown in a single cell.
+fn image_sequence() -> ImageSequence<BitCharImage> {
+    let mut images = ImageSequence::new();
+    for char in [base_char, variant_char] {
+        let mut image = BitCharImage::new(40, 25);
+        for (offset, image_char) in image.chars.iter_mut().enumerate() {
+            *image_char = base_char(offset % 150);
+        }
+        for index in 0..150 {
+            image.chars[index] = char(index);
+        }
+        images.push(image);
+    }
+    images
+}
+
+#[test]
+fn merge_similar_chars() {
+    let mut images = image_sequence();
+
+    let report = CharClustering::default().max_distance(1).apply(&mut images);
+
+    assert_eq!(
+        ClusteringReport {
+            num_chars_before: 300,
+            num_chars_after: 256,
+            pixel_error: 44,
+            max_cell_error: 1,
+        },
+        report
+    );
+    assert_eq!(256, images.all_unique_chars().len());
+    // The variants are merged into the base chars, which are used in more cells.
+    assert!((0..150).all(|index| images.all_unique_chars().contains(&base_char(index))));
+}
+
+#[test]
+fn keep_chars_within_budget() {
+    let mut images = image_sequence();
+
+    let report = CharClustering::default().max_distance(0).apply(&mut images);
+
+    assert_eq!(300, report.num_chars_after);
+    assert_eq!(0, report.pixel_error);
+}
+
+#[test]
+fn merge_as_long_as_budget_allows() {
+    let mut images = image_sequence();
+
+    let report = CharClustering::default()
+        .max_chars(None)
+        .max_distance(1)
+        .apply(&mut images);
+
+    assert_eq!(150, report.num_chars_after);
+    assert_eq!(150, report.pixel_error);
+    assert_eq!(images[0].chars, images[1].chars);
+}
+
+#[test]
+fn animation_that_fits_is_unchanged() {
+    let mut images = image_sequence();
+    let report = CharClustering::default().max_chars(Some(300)).apply(&mut images);
+
+    assert_eq!(300, report.num_chars_after);
+    assert_eq!(0, report.pixel_error);
+}
+
+#[test]
+fn weight_keeps_most_used_char() {
+    let mut images = ImageSequence::new();
+    let mut image = BitCharImage::new(40, 25);
+    image.chars[0] = 0x01;
+    images.push(image);
+
+    let report = CharClustering::default()
+        .max_chars(Some(1))
+        .weight(CharWeight::Cells)
+        .apply(&mut images);
+
+    assert_eq!(1, report.pixel_error);
+    assert!(images[0].chars.iter().all(|char| *char == 0x00));
+}
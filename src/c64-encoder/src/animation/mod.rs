@@ -0,0 +1,213 @@
+//! Encode an animation of text mode images into a demo.
+//!
+//! An [AnimationEncoder] encodes the animation with each of its [AnimationStrategy]s and keeps the smallest demo.
+#[cfg(test)]
+mod animation_test;
+mod char_clustering;
+#[cfg(test)]
+mod char_clustering_test;
+mod slot_allocator;
+#[cfg(test)]
+mod slot_allocator_test;
+mod slot_search;
+#[cfg(test)]
+mod slot_search_test;
+mod strategies;
+
+pub use char_clustering::*;
+pub use slot_allocator::*;
+pub use slot_search::*;
+pub use strategies::*;
+
+use std::collections::HashMap;
+
+use c64::image_container::{
+    bit_char::{BitCharImage, BitEncodedChar},
+    image_sequence::ImageSequence,
+};
+use c64_colors::colors::Color;
+
+use crate::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder},
+    command::{set_palette4::SetPalette4, Command},
+    encoder::Encoder,
+    optimizer::{FrameOptimizer, OptimizerError},
+};
+
+/// Number of chars in a charset.
+pub const MAX_CHARS: usize = 256;
+
+/// Number of chars of an image that covers the whole screen.
+const SCREEN_CHARS: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimationError {
+    /// The image sequence doesn't contain any images.
+    NoImages,
+    /// Image doesn't cover the screen, contains the index of the image and its number of chars.
+    InvalidImageSize { image: usize, num_chars: usize },
+    /// The strategy needs more chars than fit in a charset.
+    TooManyChars { needed: usize },
+    /// The optimizer couldn't encode the transition to the frame.
+    Optimizer { frame: usize, error: OptimizerError },
+    /// None of the strategies could encode the animation, contains the report of each strategy.
+    NoStrategyFits(Vec<StrategyReport>),
+}
+
+pub type AnimationResult<T> = Result<T, AnimationError>;
+
+/// Way of encoding an animation into frames.
+pub trait AnimationStrategy {
+    fn name(&self) -> &'static str;
+
+    /// Frames that show the images of the sequence, one frame per image.
+    fn build_frames(
+        &self,
+        images: &ImageSequence<BitCharImage>,
+        optimizer: &FrameOptimizer,
+    ) -> AnimationResult<Vec<FrameBuilder>>;
+}
+
+/// Outcome of encoding the animation with a single strategy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyReport {
+    pub name: &'static str,
+    /// Byte size of the encoded demo, or the reason the strategy couldn't encode the animation.
+    pub result: AnimationResult<usize>,
+}
+
+/// Smallest demo found by the [AnimationEncoder].
+#[derive(Debug)]
+pub struct AnimationEncoding {
+    pub demo: DemoBuilder,
+    /// Name of the strategy that encoded the demo.
+    pub strategy: &'static str,
+    /// Report of each strategy, in the order the strategies were registered.
+    pub reports: Vec<StrategyReport>,
+}
+
+/// Encode an animation using the strategy that results in the smallest demo.
+pub struct AnimationEncoder {
+    strategies: Vec<Box<dyn AnimationStrategy>>,
+    optimizer: FrameOptimizer,
+    palette: Option<[Color; 4]>,
+}
+
+impl Default for AnimationEncoder {
+    /// Encoder using all available strategies.
+    fn default() -> Self {
+        Self::new(vec![
+            Box::new(OneCharset {}),
+            Box::new(StaticWithDynamicCharset {}),
+            Box::new(PerFrameCharset::default()),
+        ])
+    }
+}
+
+impl AnimationEncoder {
+    pub fn new(strategies: Vec<Box<dyn AnimationStrategy>>) -> Self {
+        Self {
+            strategies,
+            optimizer: FrameOptimizer::default(),
+            palette: None,
+        }
+    }
+
+    pub fn add_strategy(&mut self, strategy: Box<dyn AnimationStrategy>) -> &mut Self {
+        self.strategies.push(strategy);
+        self
+    }
+
+    /// Optimizer the strategies use to encode the transitions between frames.
+    pub fn optimizer(&mut self, optimizer: FrameOptimizer) -> &mut Self {
+        self.optimizer = optimizer;
+        self
+    }
+
+    /// Set the palette in the first frame of the demo.
+    pub fn palette(&mut self, palette: [Color; 4]) -> &mut Self {
+        self.palette = Some(palette);
+        self
+    }
+
+    /// Encode the images with every strategy and return the smallest demo.
+    pub fn encode(&self, images: &ImageSequence<BitCharImage>) -> AnimationResult<AnimationEncoding> {
+        if images.len() == 0 {
+            return Err(AnimationError::NoImages);
+        }
+        if let Some((image, bit_char_image)) = images
+            .iter()
+            .enumerate()
+            .find(|(_, image)| image.chars.len() != SCREEN_CHARS)
+        {
+            return Err(AnimationError::InvalidImageSize {
+                image,
+                num_chars: bit_char_image.chars.len(),
+            });
+        }
+
+        let mut best: Option<(DemoBuilder, &'static str)> = None;
+        let mut reports = vec![];
+        for strategy in &self.strategies {
+            let result = strategy
+                .build_frames(images, &self.optimizer)
+                .map(|frames| self.build_demo(frames));
+            reports.push(StrategyReport {
+                name: strategy.name(),
+                result: result.as_ref().map(DemoBuilder::byte_size).map_err(Clone::clone),
+            });
+            if let Ok(demo) = result {
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_demo, _)| demo.byte_size() < best_demo.byte_size())
+                {
+                    best = Some((demo, strategy.name()));
+                }
+            }
+        }
+
+        match best {
+            Some((demo, strategy)) => Ok(AnimationEncoding {
+                demo,
+                strategy,
+                reports,
+            }),
+            None => Err(AnimationError::NoStrategyFits(reports)),
+        }
+    }
+
+    fn build_demo(&self, mut frames: Vec<FrameBuilder>) -> DemoBuilder {
+        if let (Some(palette), Some(first_frame)) = (self.palette, frames.first_mut()) {
+            first_frame
+                .commands
+                .insert(0, Command::SetPalette4(SetPalette4 { palette }));
+        }
+        let mut demo = DemoBuilder::default();
+        for frame in frames {
+            demo.frame(frame);
+        }
+        demo
+    }
+}
+
+/// Fail when the number of chars doesn't fit in a charset.
+fn check_num_chars(needed: usize) -> AnimationResult<()> {
+    if needed > MAX_CHARS {
+        return Err(AnimationError::TooManyChars { needed });
+    }
+    Ok(())
+}
+
+/// Screen chars that show the image using the given charset. When a char is in the charset more than once the first
+/// one is used.
+fn screen_chars(image: &BitCharImage, charset: &[BitEncodedChar]) -> [u8; 1000] {
+    let mut char_codes = HashMap::new();
+    for (char_code, char) in charset.iter().enumerate() {
+        char_codes.entry(*char).or_insert(char_code as u8);
+    }
+    let mut result = [0; 1000];
+    for (screen_char, char) in result.iter_mut().zip(&image.chars) {
+        *screen_char = *char_codes.get(char).expect("char should be in the charset");
+    }
+    result
+}
@@ -0,0 +1,208 @@
+use std::{cmp::Reverse, collections::BTreeMap, ops::Range};
+
+use c64::image_container::{
+    bit_char::{BitCharImage, BitEncodedChar},
+    image_sequence::ImageSequence,
+};
+
+/// Frames in which a char is shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharUsage {
+    pub char: BitEncodedChar,
+    /// Ranges of consecutive frames using the char, in order.
+    pub intervals: Vec<Range<usize>>,
+}
+
+/// Usage of each char of the images, ordered by char.
+pub fn char_usages(images: &ImageSequence<BitCharImage>) -> Vec<CharUsage> {
+    let mut intervals = BTreeMap::<BitEncodedChar, Vec<Range<usize>>>::new();
+    for (frame, image) in images.iter().enumerate() {
+        for char in image.all_unique_chars() {
+            let char_intervals = intervals.entry(char).or_default();
+            match char_intervals.last_mut() {
+                Some(interval) if interval.end == frame => interval.end = frame + 1,
+                _ => char_intervals.push(frame..frame + 1),
+            }
+        }
+    }
+    intervals
+        .into_iter()
+        .map(|(char, intervals)| CharUsage { char, intervals })
+        .collect()
+}
+
+/// How chars are assigned to charset slots.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SlotStrategy {
+    /// A char keeps a single slot for all frames using it. Chars used in the most frames are placed first, in the
+    /// first slot that is free in all those frames.
+    FirstFit,
+    /// Each interval of a char is placed on its own, in order of the first frame of the interval. A slot is reused as
+    /// soon as the interval occupying it ends, which needs as many slots as the frame with the most chars.
+    Intervals,
+}
+
+/// Charset of each frame after assigning the chars to slots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotAllocation {
+    /// Charset of each frame, all charsets have the same number of slots. Slots that aren't used in a frame keep the
+    /// char they had in the previous frame, so they don't need to be updated.
+    pub charsets: Vec<Vec<BitEncodedChar>>,
+}
+
+impl SlotAllocation {
+    pub fn num_slots(&self) -> usize {
+        self.charsets.first().map_or(0, Vec::len)
+    }
+}
+
+/// Assign the chars of an animation to charset slots, so the charset of each frame contains the chars of that frame.
+#[derive(Debug, Copy, Clone)]
+pub struct CharsetSlotAllocator {
+    strategy: SlotStrategy,
+    looping: bool,
+    max_gap: usize,
+}
+
+impl Default for CharsetSlotAllocator {
+    fn default() -> Self {
+        Self {
+            strategy: SlotStrategy::Intervals,
+            looping: false,
+            max_gap: 0,
+        }
+    }
+}
+
+impl CharsetSlotAllocator {
+    pub fn strategy(&mut self, strategy: SlotStrategy) -> &mut Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// The animation loops from the last frame back to the first frame.
+    ///
+    /// Chars used in both the last and the first frame keep the same slot around the wrap, and slots that aren't used
+    /// in the first frames keep the char of the last frame, so looping needs as few charset updates as possible.
+    pub fn looping(&mut self, looping: bool) -> &mut Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Keep the slot of a char when it isn't used for at most the given number of frames, instead of freeing the slot
+    /// and uploading the char again afterwards.
+    pub fn bridge_gaps(&mut self, max_gap: usize) -> &mut Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    /// Number of slots each strategy needs for the images, using the other options of this allocator.
+    pub fn report(&self, images: &ImageSequence<BitCharImage>) -> Vec<(SlotStrategy, usize)> {
+        [SlotStrategy::FirstFit, SlotStrategy::Intervals]
+            .into_iter()
+            .map(|strategy| {
+                let mut allocator = *self;
+                allocator.strategy(strategy);
+                (strategy, allocator.allocate(images).num_slots())
+            })
+            .collect()
+    }
+
+    /// Assign the chars of the images to slots. The allocation can contain more slots than fit in a charset.
+    pub fn allocate(&self, images: &ImageSequence<BitCharImage>) -> SlotAllocation {
+        self.allocate_usages(images.len(), &char_usages(images))
+    }
+
+    /// Assign chars to slots given the frames each char is used in.
+    pub fn allocate_usages(&self, num_frames: usize, usages: &[CharUsage]) -> SlotAllocation {
+        // Each slot contains the char it holds in each frame, `None` when it is free.
+        let mut slots = Vec::<Vec<Option<BitEncodedChar>>>::new();
+        for (char, intervals) in self.placements(num_frames, usages) {
+            let free = |slot: &Vec<Option<BitEncodedChar>>| {
+                intervals
+                    .iter()
+                    .all(|interval| slot[interval.clone()].iter().all(Option::is_none))
+            };
+            // Prefer a slot that already holds the char, so it doesn't need to be uploaded again.
+            let holds_char = |slot: &Vec<Option<BitEncodedChar>>| {
+                slot[..intervals[0].start].iter().rev().flatten().next() == Some(&char)
+            };
+            let slot = match slots
+                .iter()
+                .position(|slot| free(slot) && holds_char(slot))
+                .or_else(|| slots.iter().position(free))
+            {
+                Some(slot) => &mut slots[slot],
+                None => {
+                    slots.push(vec![None; num_frames]);
+                    slots.last_mut().unwrap()
+                }
+            };
+            for interval in intervals {
+                slot[interval].fill(Some(char));
+            }
+        }
+
+        let mut charsets = vec![Vec::with_capacity(slots.len()); num_frames];
+        for slot in slots {
+            // Frames before the first use of the slot wrap around from the last frame when looping.
+            let mut current_char = if self.looping {
+                slot.iter().rev().flatten().next()
+            } else {
+                slot.iter().flatten().next()
+            }
+            .copied()
+            .unwrap_or_default();
+            for (charset, char) in charsets.iter_mut().zip(slot) {
+                current_char = char.unwrap_or(current_char);
+                charset.push(current_char);
+            }
+        }
+        SlotAllocation { charsets }
+    }
+
+    /// Groups of intervals that are placed in a single slot, in the order they are placed.
+    fn placements(&self, num_frames: usize, usages: &[CharUsage]) -> Vec<(BitEncodedChar, Vec<Range<usize>>)> {
+        let mut pinned = vec![];
+        let mut placements = vec![];
+        for usage in usages {
+            let mut intervals = bridge_gaps(&usage.intervals, self.max_gap);
+            if self.strategy == SlotStrategy::FirstFit {
+                placements.push((usage.char, intervals));
+                continue;
+            }
+            let wraps = intervals.len() > 1
+                && intervals.first().is_some_and(|interval| interval.start == 0)
+                && intervals.last().is_some_and(|interval| interval.end == num_frames);
+            if self.looping && wraps {
+                let last = intervals.pop().unwrap();
+                pinned.push((usage.char, vec![intervals.remove(0), last]));
+            }
+            placements.extend(intervals.into_iter().map(|interval| (usage.char, vec![interval])));
+        }
+
+        match self.strategy {
+            SlotStrategy::FirstFit => placements.sort_by_key(|(char, intervals)| {
+                let num_frames = intervals.iter().map(ExactSizeIterator::len).sum::<usize>();
+                Reverse((num_frames, *char))
+            }),
+            SlotStrategy::Intervals => {
+                placements.sort_by_key(|(char, intervals)| (intervals[0].start, Reverse(intervals[0].len()), *char))
+            }
+        }
+        pinned.extend(placements);
+        pinned
+    }
+}
+
+/// Merge intervals that are separated by at most `max_gap` frames.
+fn bridge_gaps(intervals: &[Range<usize>], max_gap: usize) -> Vec<Range<usize>> {
+    let mut result = Vec::<Range<usize>>::with_capacity(intervals.len());
+    for interval in intervals {
+        match result.last_mut() {
+            Some(last) if interval.start - last.end <= max_gap => last.end = interval.end,
+            _ => result.push(interval.clone()),
+        }
+    }
+    result
+}
@@ -0,0 +1,107 @@
+use c64::image_container::{bit_char::BitCharImage, image_sequence::ImageSequence};
+
+use crate::animation::{char_usages, CharUsage, CharsetSlotAllocator, SlotAllocation, SlotStrategy};
+
+/// Sequence of images that contain the given chars, the last char fills the rest of the image.
+fn image_sequence(images_chars: &[&[u64]]) -> ImageSequence<BitCharImage> {
+    let mut images = ImageSequence::new();
+    for image_chars in images_chars {
+        let mut image = BitCharImage::new(40, 25);
+        for (offset, char) in image.chars.iter_mut().enumerate() {
+            *char = image_chars[offset.min(image_chars.len() - 1)];
+        }
+        images.push(image);
+    }
+    images
+}
+
+fn assert_contains_chars(allocation: &SlotAllocation, images: &ImageSequence<BitCharImage>) {
+    assert_eq!(images.len(), allocation.charsets.len());
+    for (image, charset) in images.iter().zip(&allocation.charsets) {
+        assert!(image.chars.iter().all(|char| charset.contains(char)));
+    }
+}
+
+/// Slot of the char in the charset of the frame.
+fn slot_of(allocation: &SlotAllocation, frame: usize, char: u64) -> usize {
+    allocation.charsets[frame].iter().position(|c| *c == char).unwrap()
+}
+
+#[test]
+fn usages_are_split_in_intervals() {
+    let images = image_sequence(&[&[1, 2], &[2], &[1], &[1, 2]]);
+
+    assert_eq!(
+        vec![
+            CharUsage {
+                char: 1,
+                intervals: vec![0..1, 2..4]
+            },
+            CharUsage {
+                char: 2,
+                intervals: vec![0..2, 3..4]
+            },
+        ],
+        char_usages(&images)
+    );
+}
+
+#[test]
+fn intervals_reuse_slots_in_gaps() {
+    // Every frame uses two chars, but char 1 isn't used in the middle frame.
+    let images = image_sequence(&[&[1, 2], &[2, 3], &[3, 1]]);
+
+    let allocator = CharsetSlotAllocator::default();
+    let allocation = allocator.allocate(&images);
+
+    assert_eq!(2, allocation.num_slots());
+    assert_contains_chars(&allocation, &images);
+    assert_eq!(
+        vec![(SlotStrategy::FirstFit, 3), (SlotStrategy::Intervals, 2)],
+        allocator.report(&images)
+    );
+}
+
+#[test]
+fn first_fit_keeps_slot_per_char() {
+    let images = image_sequence(&[&[1, 2], &[2, 3], &[3, 1]]);
+
+    let allocation = CharsetSlotAllocator::default()
+        .strategy(SlotStrategy::FirstFit)
+        .allocate(&images);
+
+    assert_eq!(3, allocation.num_slots());
+    assert_contains_chars(&allocation, &images);
+    assert_eq!(slot_of(&allocation, 0, 1), slot_of(&allocation, 2, 1));
+}
+
+#[test]
+fn unused_slots_keep_their_char() {
+    let images = image_sequence(&[&[1], &[2], &[1]]);
+
+    let allocation = CharsetSlotAllocator::default().bridge_gaps(1).allocate(&images);
+
+    assert_eq!(2, allocation.num_slots());
+    assert_contains_chars(&allocation, &images);
+    // Char 1 isn't uploaded again after the gap.
+    assert_eq!(allocation.charsets[0], allocation.charsets[2]);
+}
+
+#[test]
+fn looping_pins_wrap_around_slots() {
+    let images = image_sequence(&[&[5, 6], &[2, 3], &[2, 3], &[1, 5]]);
+
+    let allocation = CharsetSlotAllocator::default().allocate(&images);
+    assert_ne!(slot_of(&allocation, 0, 5), slot_of(&allocation, 3, 5));
+
+    let allocation = CharsetSlotAllocator::default().looping(true).allocate(&images);
+    assert_contains_chars(&allocation, &images);
+    assert_eq!(slot_of(&allocation, 0, 5), slot_of(&allocation, 3, 5));
+}
+
+#[test]
+fn empty_animation() {
+    let allocation = CharsetSlotAllocator::default().allocate(&ImageSequence::new());
+
+    assert_eq!(0, allocation.num_slots());
+}
@@ -0,0 +1,254 @@
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use c64::image_container::{
+    bit_char::{BitCharImage, BitEncodedChar},
+    image_sequence::ImageSequence,
+};
+
+use crate::{encoder::Encoder, optimizer::FrameOptimizer};
+
+use super::{screen_chars, strategies::build_frame, SlotAllocation};
+
+/// Search for the slot assignment that results in the smallest frames, using simulated annealing.
+///
+/// Slot counts don't say much about the size of a demo: the charset updates and screen char updates are what ends up
+/// in the frames. The search swaps the chars of two slots, either in all frames or in a range of frames, and scores
+/// every candidate with the byte size of the frames the [FrameOptimizer] encodes. Assigning contiguous char codes to
+/// neighbouring screen chars, for example, lets the optimizer use auto increment runs and ranged char updates.
+///
+/// The search is reproducible for a seed as long as it stops at the iteration limit before the time budget is spent.
+#[derive(Debug, Copy, Clone)]
+pub struct SlotSearch {
+    seed: u64,
+    time_budget: Duration,
+    max_iterations: usize,
+    initial_temperature: f64,
+}
+
+impl Default for SlotSearch {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            time_budget: Duration::from_secs(10),
+            max_iterations: 10_000,
+            initial_temperature: 8.0,
+        }
+    }
+}
+
+/// Best slot assignment found by a [SlotSearch].
+#[derive(Debug, Clone)]
+pub struct SlotSearchResult {
+    pub allocation: SlotAllocation,
+    /// Byte size of the frames of the allocation.
+    pub byte_size: usize,
+    /// Byte size of the frames of the allocation the search started with.
+    pub initial_byte_size: usize,
+    /// Number of candidates that were scored.
+    pub iterations: usize,
+}
+
+impl SlotSearch {
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Stop searching after the given time.
+    pub fn time_budget(&mut self, time_budget: Duration) -> &mut Self {
+        self.time_budget = time_budget;
+        self
+    }
+
+    /// Stop searching after scoring the given number of candidates.
+    pub fn max_iterations(&mut self, max_iterations: usize) -> &mut Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Temperature at the start of the search, in bytes. A candidate that is `n` bytes larger is accepted with a
+    /// probability of `e^(-n / temperature)`. The temperature decreases linearly to 0 at the end of the search.
+    pub fn initial_temperature(&mut self, initial_temperature: f64) -> &mut Self {
+        self.initial_temperature = initial_temperature;
+        self
+    }
+
+    /// Search for a better assignment of the chars in the allocation. The charset of each frame should contain all
+    /// chars of the image and at most 256 chars.
+    pub fn search(
+        &self,
+        images: &ImageSequence<BitCharImage>,
+        allocation: SlotAllocation,
+        optimizer: &FrameOptimizer,
+    ) -> SlotSearchResult {
+        let num_frames = allocation.charsets.len();
+        let num_slots = allocation.num_slots();
+        let mut candidate = Candidate::new(images, allocation.charsets, optimizer);
+        let initial_byte_size = candidate.byte_size();
+        let mut best = (candidate.charsets.clone(), initial_byte_size);
+
+        let mut random = Random::new(self.seed);
+        let start = Instant::now();
+        let mut iterations = 0;
+        while num_slots > 1 && iterations < self.max_iterations && start.elapsed() < self.time_budget {
+            let progress = f64::max(
+                iterations as f64 / self.max_iterations as f64,
+                start.elapsed().as_secs_f64() / self.time_budget.as_secs_f64(),
+            );
+            let temperature = self.initial_temperature * (1.0 - progress);
+
+            let slot_a = random.below(num_slots);
+            let slot_b = (slot_a + 1 + random.below(num_slots - 1)) % num_slots;
+            let frames = if random.below(2) == 0 {
+                0..num_frames
+            } else {
+                let first = random.below(num_frames);
+                first..first + 1 + random.below(num_frames - first)
+            };
+
+            let byte_size = candidate.byte_size();
+            let undo = candidate.swap(slot_a, slot_b, frames);
+            let delta = candidate.byte_size() as f64 - byte_size as f64;
+            iterations += 1;
+            if delta <= 0.0 || random.unit() < (-delta / temperature).exp() {
+                if candidate.byte_size() < best.1 {
+                    best = (candidate.charsets.clone(), candidate.byte_size());
+                }
+            } else {
+                candidate.undo(undo);
+            }
+        }
+
+        SlotSearchResult {
+            allocation: SlotAllocation { charsets: best.0 },
+            byte_size: best.1,
+            initial_byte_size,
+            iterations,
+        }
+    }
+}
+
+/// Slot assignment that is being scored, with the screen chars and byte size of each frame.
+struct Candidate<'a> {
+    optimizer: &'a FrameOptimizer,
+    charsets: Vec<Vec<BitEncodedChar>>,
+    screens: Vec<[u8; 1000]>,
+    frame_sizes: Vec<usize>,
+}
+
+/// State of the frames a swap changed, to undo the swap.
+struct Undo {
+    slots: (usize, usize),
+    frames: Range<usize>,
+    screens: Vec<[u8; 1000]>,
+    frame_sizes: Vec<usize>,
+}
+
+impl<'a> Candidate<'a> {
+    fn new(
+        images: &ImageSequence<BitCharImage>,
+        charsets: Vec<Vec<BitEncodedChar>>,
+        optimizer: &'a FrameOptimizer,
+    ) -> Self {
+        let screens = images
+            .iter()
+            .zip(&charsets)
+            .map(|(image, charset)| screen_chars(image, charset))
+            .collect::<Vec<_>>();
+        let mut result = Self {
+            optimizer,
+            frame_sizes: vec![0; charsets.len()],
+            charsets,
+            screens,
+        };
+        result.update_frame_sizes(0..result.charsets.len());
+        result
+    }
+
+    fn byte_size(&self) -> usize {
+        self.frame_sizes
+            .iter()
+            .fold(0, |sum, frame_size| sum.saturating_add(*frame_size))
+    }
+
+    /// Swap the chars of two slots in the given frames.
+    fn swap(&mut self, slot_a: usize, slot_b: usize, frames: Range<usize>) -> Undo {
+        // The frame after the range transitions from a swapped frame.
+        let changed_frames = frames.start..(frames.end + 1).min(self.charsets.len());
+        let undo = Undo {
+            slots: (slot_a, slot_b),
+            frames: frames.clone(),
+            screens: self.screens[frames.clone()].to_vec(),
+            frame_sizes: self.frame_sizes[changed_frames.clone()].to_vec(),
+        };
+        let (code_a, code_b) = (slot_a as u8, slot_b as u8);
+        for frame in frames {
+            self.charsets[frame].swap(slot_a, slot_b);
+            for screen_char in &mut self.screens[frame] {
+                if *screen_char == code_a {
+                    *screen_char = code_b;
+                } else if *screen_char == code_b {
+                    *screen_char = code_a;
+                }
+            }
+        }
+        self.update_frame_sizes(changed_frames);
+        undo
+    }
+
+    fn undo(&mut self, undo: Undo) {
+        let (slot_a, slot_b) = undo.slots;
+        for frame in undo.frames.clone() {
+            self.charsets[frame].swap(slot_a, slot_b);
+        }
+        self.screens.splice(undo.frames.clone(), undo.screens);
+        let start = undo.frames.start;
+        self.frame_sizes
+            .splice(start..start + undo.frame_sizes.len(), undo.frame_sizes);
+    }
+
+    /// Frames the optimizer can't encode count as `usize::MAX` bytes, so the search moves away from them.
+    fn update_frame_sizes(&mut self, frames: Range<usize>) {
+        for frame in frames {
+            self.frame_sizes[frame] = build_frame(&self.charsets, &self.screens, frame, self.optimizer)
+                .map_or(usize::MAX, |frame| frame.byte_size());
+        }
+    }
+}
+
+/// Xorshift random number generator, so a search can be repeated from its seed without extra dependencies.
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        // Splitmix the seed, the state of a xorshift generator may not be 0.
+        let mut state = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Self {
+            state: (state ^ (state >> 31)) | 1,
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Random number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Random number in `0.0..1.0`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
@@ -0,0 +1,98 @@
+use c64::image_container::{bit_char::BitCharImage, image_sequence::ImageSequence};
+
+use crate::{
+    animation::{
+        screen_chars, strategies::build_frame, AnimationStrategy, CharsetSlotAllocator, PerFrameCharset, SlotSearch,
+    },
+    builder::demo::DemoBuilder,
+    encoder::Encoder,
+    evaluator::evaluate,
+    optimizer::FrameOptimizer,
+};
+
+/// Images that start with a row of chars followed by an empty screen.
+fn image_sequence(images_chars: &[&[u64]]) -> ImageSequence<BitCharImage> {
+    let mut images = ImageSequence::new();
+    for image_chars in images_chars {
+        let mut image = BitCharImage::new(40, 25);
+        image.chars[..image_chars.len()].copy_from_slice(image_chars);
+        images.push(image);
+    }
+    images
+}
+
+fn search() -> SlotSearch {
+    let mut search = SlotSearch::default();
+    search.seed(1).max_iterations(500);
+    search
+}
+
+#[test]
+fn search_finds_smaller_frames() {
+    // The allocator assigns the chars in ascending order, the screen shows them in descending order.
+    let images = image_sequence(&[&[4, 3, 2, 1], &[4, 3, 2, 1, 5, 6]]);
+    let optimizer = FrameOptimizer::default();
+    let allocation = CharsetSlotAllocator::default().allocate(&images);
+
+    let result = search().search(&images, allocation, &optimizer);
+
+    assert!(result.byte_size < result.initial_byte_size);
+    let screens = images
+        .iter()
+        .zip(&result.allocation.charsets)
+        .map(|(image, charset)| screen_chars(image, charset))
+        .collect::<Vec<_>>();
+    let byte_size = (0..images.len())
+        .map(|frame| {
+            build_frame(&result.allocation.charsets, &screens, frame, &optimizer)
+                .unwrap()
+                .byte_size()
+        })
+        .sum::<usize>();
+    assert_eq!(result.byte_size, byte_size);
+    for (image, charset) in images.iter().zip(&result.allocation.charsets) {
+        assert!(image.chars.iter().all(|char| charset.contains(char)));
+    }
+}
+
+#[test]
+fn search_is_reproducible() {
+    let images = image_sequence(&[&[1, 2, 3, 4], &[3, 4, 5, 6], &[6, 5, 1, 2]]);
+    let optimizer = FrameOptimizer::default();
+    let allocation = CharsetSlotAllocator::default().allocate(&images);
+
+    let first = search().search(&images, allocation.clone(), &optimizer);
+    let second = search().search(&images, allocation, &optimizer);
+
+    assert_eq!(500, first.iterations);
+    assert_eq!(first.allocation, second.allocation);
+    assert!(first.byte_size <= first.initial_byte_size);
+}
+
+#[test]
+fn per_frame_charset_with_search() {
+    let images = image_sequence(&[&[1, 2, 3, 4], &[3, 4, 5, 6], &[6, 5, 1, 2]]);
+    let optimizer = FrameOptimizer::default();
+    let without_search = PerFrameCharset::default().build_frames(&images, &optimizer).unwrap();
+
+    let strategy = PerFrameCharset {
+        search: Some(search()),
+        ..Default::default()
+    };
+    let frames = strategy.build_frames(&images, &optimizer).unwrap();
+
+    assert!(
+        frames.iter().map(Encoder::byte_size).sum::<usize>()
+            <= without_search.iter().map(Encoder::byte_size).sum::<usize>()
+    );
+    let mut demo = DemoBuilder::default();
+    for frame in frames {
+        demo.frame(frame);
+    }
+    let states = evaluate(&demo.build()).unwrap();
+    for (image, state) in images.iter().zip(&states[1..]) {
+        for (char, screen_char) in image.chars.iter().zip(state.text_screen.screen_chars) {
+            assert_eq!(*char, state.charset.char(screen_char));
+        }
+    }
+}
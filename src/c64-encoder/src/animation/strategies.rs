@@ -0,0 +1,150 @@
+use c64::image_container::{
+    bit_char::{BitCharImage, BitEncodedChar},
+    image_sequence::ImageSequence,
+};
+
+use crate::{
+    builder::frame::FrameBuilder,
+    optimizer::{FrameOptimizer, Transition},
+};
+
+use super::{
+    check_num_chars, screen_chars, AnimationError, AnimationResult, AnimationStrategy, CharsetSlotAllocator, SlotSearch,
+};
+
+/// Upload a single charset with all chars of the animation in the first frame, later frames only update the screen
+/// chars.
+pub struct OneCharset {}
+
+impl AnimationStrategy for OneCharset {
+    fn name(&self) -> &'static str {
+        "one charset"
+    }
+
+    fn build_frames(
+        &self,
+        images: &ImageSequence<BitCharImage>,
+        optimizer: &FrameOptimizer,
+    ) -> AnimationResult<Vec<FrameBuilder>> {
+        let mut charset = images.all_unique_chars().into_iter().collect::<Vec<BitEncodedChar>>();
+        check_num_chars(charset.len())?;
+        charset.sort();
+
+        let charsets = vec![charset; images.len()];
+        let screens = images
+            .iter()
+            .map(|image| screen_chars(image, &charsets[0]))
+            .collect::<Vec<_>>();
+        build_frames(&charsets, &screens, optimizer)
+    }
+}
+
+/// Chars at offsets that don't change during the animation share a char. Every other offset gets its own char, which
+/// is updated when the offset changes. The screen chars are only set in the first frame.
+pub struct StaticWithDynamicCharset {}
+
+impl AnimationStrategy for StaticWithDynamicCharset {
+    fn name(&self) -> &'static str {
+        "static with dynamic charset"
+    }
+
+    fn build_frames(
+        &self,
+        images: &ImageSequence<BitCharImage>,
+        optimizer: &FrameOptimizer,
+    ) -> AnimationResult<Vec<FrameBuilder>> {
+        let (static_offsets, static_chars) = images.all_static_offsets_and_chars();
+        let num_dynamic_offsets = images[0].chars.len() - static_offsets.len();
+        check_num_chars(static_chars.len() + num_dynamic_offsets)?;
+
+        let mut static_charset = static_chars.into_iter().collect::<Vec<BitEncodedChar>>();
+        static_charset.sort();
+        let mut dynamic_offsets = vec![];
+        let mut screen = [0; 1000];
+        for (offset, screen_char) in screen.iter_mut().enumerate() {
+            *screen_char = if static_offsets.binary_search(&offset).is_ok() {
+                static_charset.binary_search(&images[0].chars[offset]).unwrap() as u8
+            } else {
+                // Dynamic chars are stored after the static chars, in the order of their offsets.
+                dynamic_offsets.push(offset);
+                (static_charset.len() + dynamic_offsets.len() - 1) as u8
+            };
+        }
+        let charsets = images
+            .iter()
+            .map(|image| {
+                let mut charset = static_charset.clone();
+                charset.extend(dynamic_offsets.iter().map(|offset| image.chars[*offset]));
+                charset
+            })
+            .collect::<Vec<_>>();
+        let screens = vec![screen; images.len()];
+        build_frames(&charsets, &screens, optimizer)
+    }
+}
+
+/// Assign each char to a charset slot for the frames it is used in, and update the charset every frame. Chars that
+/// are used in different frames can share a slot.
+#[derive(Default)]
+pub struct PerFrameCharset {
+    pub allocator: CharsetSlotAllocator,
+    /// Search for a slot assignment that results in smaller frames than the assignment of the allocator.
+    pub search: Option<SlotSearch>,
+}
+
+impl AnimationStrategy for PerFrameCharset {
+    fn name(&self) -> &'static str {
+        "per frame charset"
+    }
+
+    fn build_frames(
+        &self,
+        images: &ImageSequence<BitCharImage>,
+        optimizer: &FrameOptimizer,
+    ) -> AnimationResult<Vec<FrameBuilder>> {
+        let mut allocation = self.allocator.allocate(images);
+        check_num_chars(allocation.num_slots())?;
+        if let Some(search) = &self.search {
+            allocation = search.search(images, allocation, optimizer).allocation;
+        }
+        let screens = images
+            .iter()
+            .zip(&allocation.charsets)
+            .map(|(image, charset)| screen_chars(image, charset))
+            .collect::<Vec<_>>();
+        build_frames(&allocation.charsets, &screens, optimizer)
+    }
+}
+
+/// Frames that transition between the given charsets and screen chars. Nothing is known about the charset and
+/// screen before the first frame.
+fn build_frames(
+    charsets: &[Vec<BitEncodedChar>],
+    screens: &[[u8; 1000]],
+    optimizer: &FrameOptimizer,
+) -> AnimationResult<Vec<FrameBuilder>> {
+    (0..charsets.len())
+        .map(|index| build_frame(charsets, screens, index, optimizer))
+        .collect()
+}
+
+/// Frame that transitions from the previous charset and screen chars to the ones at the given index.
+pub(super) fn build_frame(
+    charsets: &[Vec<BitEncodedChar>],
+    screens: &[[u8; 1000]],
+    index: usize,
+    optimizer: &FrameOptimizer,
+) -> AnimationResult<FrameBuilder> {
+    let previous = index.checked_sub(1);
+    let commands = optimizer
+        .optimize(&Transition {
+            from_screen_chars: previous.map(|previous| &screens[previous]),
+            to_screen_chars: &screens[index],
+            from_charset: previous.map(|previous| charsets[previous].as_slice()),
+            to_charset: &charsets[index],
+        })
+        .map_err(|error| AnimationError::Optimizer { frame: index, error })?;
+    let mut frame = FrameBuilder::default();
+    frame.extend(&commands);
+    Ok(frame)
+}
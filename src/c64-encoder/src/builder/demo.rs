@@ -1,25 +1,100 @@
+use crate::command::frame_loop::{LoopEnd, LoopStart};
+use crate::command::goto_frame::GotoFrame;
+use crate::command::Command;
+use crate::container::{ContainerResult, DemoContainer, MemoryLayout};
+use crate::decoder::reader::Reader;
+use crate::decoder::{DecodeResult, Decoder};
 use crate::encoder::writer::Writer;
 use crate::encoder::Encoder;
 
 use super::frame::FrameBuilder;
 
-#[derive(Default)]
+#[derive(Default, Debug, PartialEq)]
 pub struct DemoBuilder {
     pub frames: Vec<FrameBuilder>,
+    /// Index of the first frame of the loop that hasn't been closed yet.
+    loop_start: Option<usize>,
+    /// Pack frames when they are added.
+    pack_frames: bool,
 }
 
 impl DemoBuilder {
-    pub fn frame(&mut self, frame: FrameBuilder) -> &mut Self {
+    pub fn frame(&mut self, mut frame: FrameBuilder) -> &mut Self {
+        if self.loop_start == Some(self.frames.len()) {
+            frame.push(Command::LoopStart(LoopStart {}));
+        }
+        if self.pack_frames {
+            frame.pack();
+        }
         self.frames.push(frame);
         self
     }
 
+    /// Pack the frames that are added after this call, see [FrameBuilder::pack].
+    ///
+    /// Frames are packed when they are added, so the offsets of frames targeted by [DemoBuilder::goto_frame] don't
+    /// change afterwards. Loop end and goto frame commands are added after the packed commands.
+    pub fn pack_frames(&mut self, pack_frames: bool) -> &mut Self {
+        self.pack_frames = pack_frames;
+        self
+    }
+
+    /// Start a loop at the next frame that will be added.
+    ///
+    /// Loops cannot be nested.
+    pub fn loop_start(&mut self) -> &mut Self {
+        assert!(self.loop_start.is_none(), "loops cannot be nested");
+        self.loop_start = Some(self.frames.len());
+        self
+    }
+
+    /// End the loop at the last added frame. The frames of the loop are played `count` times, a count of 0 plays
+    /// them 256 times.
+    pub fn loop_end(&mut self, count: u8) -> &mut Self {
+        let loop_start = self.loop_start.take().expect("loop_end without loop_start");
+        assert!(loop_start < self.frames.len(), "loop doesn't contain any frames");
+        self.last_frame().push(Command::LoopEnd(LoopEnd { count }));
+        self
+    }
+
+    /// Continue playback at the given frame after the last added frame has been processed.
+    ///
+    /// Only frames that have already been added can be targeted; their content should not be changed afterwards. The
+    /// frame has to start within the first 64KiB of the engine data.
+    pub fn goto_frame(&mut self, frame: usize) -> &mut Self {
+        assert!(frame < self.frames.len(), "frame {frame} hasn't been added");
+        let frame_offset = self.frame_offset(frame);
+        let offset = u16::try_from(frame_offset)
+            .unwrap_or_else(|_| panic!("frame {frame} starts at offset {frame_offset}, goto frame can't reach it"));
+        self.last_frame().push(Command::GotoFrame(GotoFrame { offset }));
+        self
+    }
+
+    /// Byte offset of the given frame from the start of the engine data.
+    pub fn frame_offset(&self, frame: usize) -> usize {
+        self.frames[..frame].iter().map(FrameBuilder::byte_size).sum::<usize>() + size_of::<u16>()
+    }
+
     pub fn build(&self) -> Vec<u8> {
         let mut result = Vec::new();
         result.resize(self.byte_size(), 0);
         self.encode(result.as_mut_slice());
         result
     }
+
+    /// Build the demo wrapped in a [DemoContainer].
+    pub fn build_container(&self, memory_layout: MemoryLayout) -> Vec<u8> {
+        DemoContainer::new(self, memory_layout).write()
+    }
+
+    /// Read the demo from a [DemoContainer]. Fails when the header of the container doesn't match the demo.
+    pub fn from_container(container_bytes: &[u8]) -> ContainerResult<DemoBuilder> {
+        DemoContainer::read(container_bytes)?.demo()
+    }
+
+    fn last_frame(&mut self) -> &mut FrameBuilder {
+        self.frames.last_mut().expect("demo doesn't contain any frames")
+    }
 }
 
 impl Encoder for DemoBuilder {
@@ -38,3 +113,16 @@ impl Encoder for DemoBuilder {
         encoded_data
     }
 }
+
+impl Decoder for DemoBuilder {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (num_frames, mut encoded_data) = encoded_data.read::<u16>()?;
+        let mut demo = DemoBuilder::default();
+        for _ in 0..num_frames {
+            let frame;
+            (frame, encoded_data) = encoded_data.read()?;
+            demo.frames.push(frame);
+        }
+        Ok((demo, encoded_data))
+    }
+}
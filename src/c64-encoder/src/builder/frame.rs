@@ -2,15 +2,23 @@ use c64_colors::colors::Color;
 
 use crate::{
     command::{
-        clear_screen_chars::ClearScreenChars, set_border_color::SetBorderColor, set_palette4::SetPalette4,
-        update_chars::UpdateCharsU16Encoded, update_text_mode_screen::UpdateTextModeScreen, Command,
+        clear_screen_chars::ClearScreenChars,
+        clear_screen_colors::ClearScreenColors,
+        packed_commands::{PackedCommands, DEPACK_BUFFER_SIZE},
+        set_border_color::SetBorderColor,
+        set_palette4::SetPalette4,
+        update_chars::{UpdateChar, UpdateCharsU16Encoded},
+        update_text_mode_screen::UpdateTextModeScreen,
+        wait_frames::WaitFrames,
+        Command,
     },
+    decoder::{reader::Reader, DecodeError, DecodeResult, Decoder},
     encoder::{writer::Writer, Encoder},
 };
 
 pub type Commands = Vec<Command>;
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Debug, PartialEq)]
 pub struct FrameBuilder {
     pub commands: Commands,
 }
@@ -28,15 +36,35 @@ impl FrameBuilder {
         self
     }
 
+    pub fn clear_screen_colors(&mut self, color: Color) -> &mut Self {
+        self.commands
+            .push(Command::ClearScreenColors(ClearScreenColors { color }));
+        self
+    }
+
     pub fn set_border_color(&mut self, color: Color) -> &mut Self {
         self.commands.push(Command::SetBorderColor(SetBorderColor { color }));
         self
     }
 
+    /// Keep the frame on screen for the given number of additional video frames.
+    pub fn wait_frames(&mut self, num_frames: u8) -> &mut Self {
+        self.commands.push(Command::WaitFrames(WaitFrames { num_frames }));
+        self
+    }
+
     pub fn update_charmap_u16(&mut self, update_chars: UpdateCharsU16Encoded) -> &mut Self {
         self.commands.push(Command::UpdateCharsU16Encoded(update_chars));
         self
     }
+
+    /// Update the given chars in the charset.
+    ///
+    /// Chars that cannot be represented losslessly by the u16 encoding are updated using all 8 bytes.
+    pub fn update_chars(&mut self, chars: &[UpdateChar]) -> &mut Self {
+        self.commands.extend(Command::update_chars(chars));
+        self
+    }
     pub fn update_text_mode_screen(&mut self, update_text_mode_screen: UpdateTextModeScreen) -> &mut Self {
         self.commands
             .push(Command::UpdateTextModeScreen(update_text_mode_screen));
@@ -57,6 +85,32 @@ impl FrameBuilder {
         self
     }
 
+    /// Replace the commands of the frame by [PackedCommands] when that makes the frame smaller.
+    ///
+    /// Frames that already contain packed commands or don't fit in the depack buffer are left unchanged.
+    pub fn pack(&mut self) -> &mut Self {
+        let unpacked_byte_size = self.commands.byte_size();
+        if self.commands.is_empty()
+            || unpacked_byte_size + size_of::<u8>() > DEPACK_BUFFER_SIZE
+            || self
+                .commands
+                .iter()
+                .any(|command| matches!(command, Command::PackedCommands(_)))
+        {
+            return self;
+        }
+        let packed = Command::PackedCommands(PackedCommands::new(self.commands.clone()));
+        if packed.byte_size() < unpacked_byte_size {
+            self.commands = vec![packed];
+        }
+        self
+    }
+
+    /// Number of commands the engine processes for this frame, as stored in the frame header.
+    pub fn engine_command_count(&self) -> usize {
+        self.commands.iter().map(Command::engine_command_count).sum::<usize>()
+    }
+
     pub fn build(&self) -> Self {
         self.clone()
     }
@@ -68,12 +122,29 @@ impl Encoder for FrameBuilder {
     }
 
     fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
-        let mut encoded_data = encoded_data.add(&(self.commands.len() as u16));
+        let mut encoded_data = encoded_data.add(&(self.engine_command_count() as u16));
         encoded_data = self.commands.encode(encoded_data);
         encoded_data
     }
 }
 
+impl Decoder for FrameBuilder {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (num_commands, mut encoded_data) = encoded_data.read::<u16>()?;
+        let mut commands_left = num_commands as usize;
+        let mut commands = Commands::with_capacity(commands_left);
+        while commands_left > 0 {
+            let command: Command;
+            (command, encoded_data) = encoded_data.read()?;
+            commands_left = commands_left
+                .checked_sub(command.engine_command_count())
+                .ok_or(DecodeError::InvalidPackedData)?;
+            commands.push(command);
+        }
+        Ok((FrameBuilder { commands }, encoded_data))
+    }
+}
+
 impl Encoder for Commands {
     fn byte_size(&self) -> usize {
         self.iter().map(Command::byte_size).sum::<usize>()
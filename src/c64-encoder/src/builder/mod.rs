@@ -1,2 +1,3 @@
 pub mod demo;
 pub mod frame;
+pub mod screen_buffers;
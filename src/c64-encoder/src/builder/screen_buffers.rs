@@ -0,0 +1,78 @@
+use crate::{
+    command::{modules::EngineConfig, Command},
+    optimizer::Transition,
+};
+
+/// Keeps track of the screen chars in the screen buffers of the engine while encoding frames.
+///
+/// Commands that update screen chars write to the buffer the engine shows after the frame. When the engine is double
+/// buffered that buffer was shown two frames earlier, so updates have to be encoded relative to that screen instead
+/// of the previous one. The buffers flip after every frame, also after frames that don't update the screen chars, so
+/// a double buffered demo should update the screen chars in every frame.
+///
+/// Sprite pointers are stored in the screen buffers as well, a `SetSpritePointers` command only changes the pointers
+/// of the buffer it is written to.
+#[derive(Debug, Clone)]
+pub struct ScreenBuffers {
+    /// Screen chars of each buffer, the buffer the next frame is written to first.
+    buffers: Vec<[u8; 1000]>,
+}
+
+impl ScreenBuffers {
+    /// Screen buffers of an engine that starts with the given screen chars in all buffers.
+    pub fn new(double_buffered: bool, screen_chars: [u8; 1000]) -> Self {
+        let num_buffers = if double_buffered { 2 } else { 1 };
+        Self {
+            buffers: vec![screen_chars; num_buffers],
+        }
+    }
+
+    /// Screen buffers of the engine built with the given config.
+    pub fn for_config(config: &EngineConfig, screen_chars: [u8; 1000]) -> Self {
+        Self::new(config.is_double_buffered(), screen_chars)
+    }
+
+    pub fn is_double_buffered(&self) -> bool {
+        self.buffers.len() == 2
+    }
+
+    /// Screen chars of the buffer the next frame is written to.
+    pub fn back_screen_chars(&self) -> &[u8; 1000] {
+        &self.buffers[0]
+    }
+
+    /// Screen chars shown after the last frame.
+    pub fn shown_screen_chars(&self) -> &[u8; 1000] {
+        self.buffers.last().unwrap()
+    }
+
+    /// Encode the screen chars of the next frame using [Command::update_screen_chars_rle] and advance to the next
+    /// frame.
+    pub fn transition(&mut self, to_screen_chars: &[u8; 1000]) -> Command {
+        let result = Command::update_screen_chars_rle(self.back_screen_chars(), to_screen_chars);
+        self.next_frame(to_screen_chars);
+        result
+    }
+
+    /// Transition of the next frame for a [crate::optimizer::FrameOptimizer], starting from the screen chars of the
+    /// buffer the frame is written to. Call [ScreenBuffers::next_frame] after encoding the frame.
+    pub fn frame_transition<'a>(
+        &'a self,
+        to_screen_chars: &'a [u8; 1000],
+        from_charset: Option<&'a [u64]>,
+        to_charset: &'a [u64],
+    ) -> Transition<'a> {
+        Transition {
+            from_screen_chars: Some(self.back_screen_chars()),
+            to_screen_chars,
+            from_charset,
+            to_charset,
+        }
+    }
+
+    /// Advance to the next frame, which leaves the given screen chars in the buffer it was written to.
+    pub fn next_frame(&mut self, screen_chars: &[u8; 1000]) {
+        self.buffers[0] = *screen_chars;
+        self.buffers.rotate_left(1);
+    }
+}
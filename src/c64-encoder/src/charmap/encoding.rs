@@ -60,3 +60,8 @@ pub fn decode_char(encoded: u16) -> u64 {
 
     result
 }
+
+/// Check if a char can be encoded to a 16 bit representation without losing any pixels.
+pub fn is_u16_encodable(char_bits: u64) -> bool {
+    decode_char(encode_char(char_bits)) == char_bits
+}
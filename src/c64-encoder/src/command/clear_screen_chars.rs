@@ -3,11 +3,14 @@ use c64_assembler::{
     Module,
 };
 
-use crate::encoder::{writer::Writer, Encoder};
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
 
 use super::{modules::CurrentPtrMacros, DecoderModule};
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct ClearScreenChars {
     pub screen_char: u8,
 }
@@ -22,6 +25,13 @@ impl Encoder for ClearScreenChars {
     }
 }
 
+impl Decoder for ClearScreenChars {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (screen_char, encoded_data) = encoded_data.read()?;
+        Ok((ClearScreenChars { screen_char }, encoded_data))
+    }
+}
+
 impl DecoderModule for ClearScreenChars {
     fn module() -> Module {
         ModuleBuilder::default()
@@ -29,15 +39,27 @@ impl DecoderModule for ClearScreenChars {
             .function(
                 FunctionBuilder::default()
                     .name("clear_screen_chars__process")
+                    .doc(&[
+                        "Fill the 1000 screen chars with the character that follows the command byte.",
+                        "",
+                        "The first 3 pages are filled completely, the last page only up to the sprite pointers.",
+                    ])
                     .instructions(
                         InstructionBuilder::default()
                             .lda_current_ptr_offs(1, "Load character to fill the screen with into the accumulator")
                             .ldx_imm(0x00)
                             .label("clear_screen_char__next")
+                            .label("clear_screen_chars__store0")
                             .sta_addr_x("SCREEN_CHARS_PAGE0")
+                            .label("clear_screen_chars__store1")
                             .sta_addr_x("SCREEN_CHARS_PAGE1")
+                            .label("clear_screen_chars__store2")
                             .sta_addr_x("SCREEN_CHARS_PAGE2")
+                            .cpx_imm(232)
+                            .bcs_addr("clear_screen_chars__skip3")
+                            .label("clear_screen_chars__store3")
                             .sta_addr_x("SCREEN_CHARS_PAGE3")
+                            .label("clear_screen_chars__skip3")
                             .inx()
                             .bne_addr("clear_screen_char__next")
                             .inc_current_ptr(2)
@@ -0,0 +1,67 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+use c64_colors::colors::Color;
+
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{modules::CurrentPtrMacros, DecoderModule};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClearScreenColors {
+    pub color: Color,
+}
+
+impl Encoder for ClearScreenColors {
+    fn byte_size(&self) -> usize {
+        size_of::<u8>()
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        encoded_data.add(&u8::from(self.color))
+    }
+}
+
+impl Decoder for ClearScreenColors {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (color, encoded_data) = encoded_data.read::<u8>()?;
+        Ok((
+            ClearScreenColors {
+                color: Color::from(color & 0x0F),
+            },
+            encoded_data,
+        ))
+    }
+}
+
+impl DecoderModule for ClearScreenColors {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("clear_screen_colors")
+            .function(
+                FunctionBuilder::default()
+                    .name("clear_screen_colors__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(1, "Load color to fill the color RAM with into the accumulator")
+                            .ldx_imm(0x00)
+                            .label("clear_screen_colors__next")
+                            .sta_addr_x("SCREEN_COLORS_PAGE0")
+                            .sta_addr_x("SCREEN_COLORS_PAGE1")
+                            .sta_addr_x("SCREEN_COLORS_PAGE2")
+                            .sta_addr_x("SCREEN_COLORS_PAGE3")
+                            .inx()
+                            .bne_addr("clear_screen_colors__next")
+                            .inc_current_ptr(2)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
@@ -0,0 +1,130 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{modules::CurrentPtrMacros, DecoderModule};
+
+/// Mark the frame containing this command as the first frame of a loop.
+///
+/// Loops cannot be nested.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LoopStart {}
+
+impl Encoder for LoopStart {
+    fn byte_size(&self) -> usize {
+        0
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        encoded_data
+    }
+}
+
+impl Decoder for LoopStart {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        Ok((LoopStart {}, encoded_data))
+    }
+}
+
+impl DecoderModule for LoopStart {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("loop_start")
+            .instructions(
+                InstructionBuilder::default()
+                    .label("loop_start__frame_ptr")
+                    .comment("Start of the first frame of the loop.")
+                    .raw(&[0x00; 2])
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("loop_start__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_addr("engine__frame__ptr")
+                            .sta_addr("loop_start__frame_ptr")
+                            .lda_addr_offs("engine__frame__ptr", 1)
+                            .sta_addr_offs("loop_start__frame_ptr", 1)
+                            .inc_current_ptr(1)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
+
+/// Mark the frame containing this command as the last frame of a loop.
+///
+/// The frames of the loop are played `count` times. A count of 0 plays the frames 256 times. Commands following this
+/// command in the same frame are only processed after the last iteration.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LoopEnd {
+    pub count: u8,
+}
+
+impl Encoder for LoopEnd {
+    fn byte_size(&self) -> usize {
+        size_of::<u8>()
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        encoded_data.add(&self.count)
+    }
+}
+
+impl Decoder for LoopEnd {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (count, encoded_data) = encoded_data.read()?;
+        Ok((LoopEnd { count }, encoded_data))
+    }
+}
+
+impl DecoderModule for LoopEnd {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("loop_end")
+            .instructions(
+                InstructionBuilder::default()
+                    .label("loop_end__iterations_left")
+                    .comment("Number of iterations left in the current loop.")
+                    .comment("When 0 no loop is active.")
+                    .raw(&[0x00])
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("loop_end__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_addr("loop_end__iterations_left")
+                            .bne_addr("loop_end__next_iteration")
+                            .lda_current_ptr_offs(1, "First time the end of the loop is reached, load the count.")
+                            .sta_addr("loop_end__iterations_left")
+                            .label("loop_end__next_iteration")
+                            .dec_addr("loop_end__iterations_left")
+                            .beq_addr("loop_end__exit")
+                            .lda_addr("loop_start__frame_ptr")
+                            .comment("Rewind to the first frame of the loop.")
+                            .sta_addr("CURRENT_PTR")
+                            .lda_addr_offs("loop_start__frame_ptr", 1)
+                            .sta_addr_offs("CURRENT_PTR", 1)
+                            .jmp_addr("engine__commands_left__set_last")
+                            .label("loop_end__exit")
+                            .inc_current_ptr(2)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
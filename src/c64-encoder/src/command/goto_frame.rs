@@ -0,0 +1,66 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::DecoderModule;
+
+/// Continue playback at another frame.
+///
+/// The offset is the byte offset of the frame from the start of the engine data. Commands following this command in
+/// the same frame are skipped.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct GotoFrame {
+    pub offset: u16,
+}
+
+impl Encoder for GotoFrame {
+    fn byte_size(&self) -> usize {
+        size_of::<u16>()
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        encoded_data.add(&self.offset)
+    }
+}
+
+impl Decoder for GotoFrame {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (offset, encoded_data) = encoded_data.read()?;
+        Ok((GotoFrame { offset }, encoded_data))
+    }
+}
+
+impl DecoderModule for GotoFrame {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("goto_frame")
+            .function(
+                FunctionBuilder::default()
+                    .name("goto_frame__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .ldy_imm(1)
+                            .comment("Add frame offset to the start of the engine data.")
+                            .clc()
+                            .lda_ind_y("CURRENT_PTR")
+                            .adc_imm_low("engine_data")
+                            .tax()
+                            .iny()
+                            .lda_ind_y("CURRENT_PTR")
+                            .adc_imm_high("engine_data")
+                            .sta_addr_offs("CURRENT_PTR", 1)
+                            .stx_addr("CURRENT_PTR")
+                            .jmp_addr("engine__commands_left__set_last")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
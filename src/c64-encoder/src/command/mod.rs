@@ -1,44 +1,117 @@
 use c64_assembler::Module;
 use clear_screen_chars::ClearScreenChars;
+use clear_screen_colors::ClearScreenColors;
+use frame_loop::{LoopEnd, LoopStart};
+use goto_frame::GotoFrame;
+use packed_commands::{PackedCommands, PackedCommandsEnd};
 use partial_update_text_mode::PartialUpdateTextModeScreen;
+use scan_order::ScanOrder;
 use set_border_color::SetBorderColor;
 use set_palette4::SetPalette4;
-use update_chars::UpdateCharsU16Encoded;
-use update_chars_ranged::UpdateCharsRangedU16Encoded;
+use set_sprite_colors::SetSpriteColors;
+use set_sprite_data::SetSpriteData;
+use set_sprite_enable::SetSpriteEnable;
+use set_sprite_pointers::SetSpritePointers;
+use set_sprite_positions::SetSpritePositions;
+use update_chars::{UpdateChar, UpdateCharsU16Encoded};
+use update_chars_ranged::{UpdateCharRanged, UpdateCharsRangedU16Encoded};
+use update_chars_ranged_raw::UpdateCharsRangedRaw;
+use update_chars_raw::UpdateCharsRaw;
 use update_screen_chars_rle::UpdateScreenCharsRLE;
+use update_screen_chars_rle_extended::UpdateScreenCharsRLEExtended;
+use update_screen_colors::UpdateScreenColors;
+use update_screen_colors_rle::UpdateScreenColorsRLE;
 use update_text_mode_screen::UpdateTextModeScreen;
+use wait_frames::WaitFrames;
 
-use crate::encoder::Encoder;
+use crate::{
+    charmap::encoding::is_u16_encodable,
+    decoder::{reader::Reader, DecodeError, DecodeResult, Decoder},
+    encoder::Encoder,
+};
 
 pub mod clear_screen_chars;
+pub mod clear_screen_colors;
+pub mod frame_loop;
+pub mod goto_frame;
 pub mod modules;
+pub mod packed_commands;
 pub mod partial_update_text_mode;
+pub mod scan_order;
 pub mod set_border_color;
 pub mod set_palette4;
+pub mod set_sprite_colors;
+pub mod set_sprite_data;
+pub mod set_sprite_enable;
+pub mod set_sprite_pointers;
+pub mod set_sprite_positions;
 pub mod update_chars;
 pub mod update_chars_ranged;
+pub mod update_chars_ranged_raw;
+pub mod update_chars_raw;
 pub mod update_screen_chars_rle;
+pub mod update_screen_chars_rle_extended;
+#[cfg(test)]
+mod update_screen_chars_rle_test;
+pub mod update_screen_colors;
+pub mod update_screen_colors_rle;
 pub mod update_text_mode_screen;
+pub mod wait_frames;
 
 pub const CLEAR_SCREEN_CHAR: u8 = 1;
 pub const SET_PALETTE4: u8 = 2;
 pub const SET_BORDER_COLOR: u8 = 3;
+pub const CLEAR_SCREEN_COLORS: u8 = 4;
+pub const WAIT_FRAMES: u8 = 5;
+pub const GOTO_FRAME: u8 = 6;
+pub const LOOP_START: u8 = 7;
+pub const LOOP_END: u8 = 8;
+pub const PACKED_COMMANDS: u8 = 9;
+pub const PACKED_COMMANDS_END: u8 = 10;
 pub const UPDATE_CHARS_U16: u8 = 16;
 pub const UPDATE_CHARS_RANGED_U16: u8 = 17;
+pub const UPDATE_CHARS_RAW: u8 = 18;
+pub const UPDATE_CHARS_RANGED_RAW: u8 = 19;
 pub const UPDATE_TEXT_MODE_SCREEN: u8 = 32;
 pub const PARTIAL_UPDATE_TEXT_MODE_SCREEN: u8 = 33;
 pub const UPDATE_SCREEN_CHARS_RLE: u8 = 34;
+pub const UPDATE_SCREEN_CHARS_RLE_EXTENDED: u8 = 35;
+pub const UPDATE_SCREEN_CHARS_RLE_COLUMNS: u8 = 36;
+pub const UPDATE_SCREEN_CHARS_RLE_TILES: u8 = 37;
+pub const UPDATE_SCREEN_COLORS: u8 = 48;
+pub const UPDATE_SCREEN_COLORS_RLE: u8 = 50;
+pub const SET_SPRITE_DATA: u8 = 64;
+pub const SET_SPRITE_POINTERS: u8 = 65;
+pub const SET_SPRITE_POSITIONS: u8 = 66;
+pub const SET_SPRITE_COLORS: u8 = 67;
+pub const SET_SPRITE_ENABLE: u8 = 68;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Command {
     ClearScreenChars(ClearScreenChars),
     SetPalette4(SetPalette4),
     SetBorderColor(SetBorderColor),
+    ClearScreenColors(ClearScreenColors),
+    WaitFrames(WaitFrames),
+    GotoFrame(GotoFrame),
+    LoopStart(LoopStart),
+    LoopEnd(LoopEnd),
+    PackedCommands(PackedCommands),
     UpdateCharsU16Encoded(UpdateCharsU16Encoded),
     UpdateCharsRangedU16Encoded(UpdateCharsRangedU16Encoded),
+    UpdateCharsRaw(UpdateCharsRaw),
+    UpdateCharsRangedRaw(UpdateCharsRangedRaw),
     UpdateTextModeScreen(UpdateTextModeScreen),
     PartialUpdateTextModeScreen(PartialUpdateTextModeScreen),
     UpdateScreenCharsRLE(UpdateScreenCharsRLE),
+    UpdateScreenCharsRLEExtended(UpdateScreenCharsRLEExtended),
+    UpdateScreenColors(UpdateScreenColors),
+    UpdateScreenColorsRLE(UpdateScreenColorsRLE),
+    SetSpriteData(SetSpriteData),
+    SetSpritePointers(SetSpritePointers),
+    SetSpritePositions(SetSpritePositions),
+    SetSpriteColors(SetSpriteColors),
+    SetSpriteEnable(SetSpriteEnable),
 }
 
 impl Encoder for Command {
@@ -47,11 +120,27 @@ impl Encoder for Command {
             Command::ClearScreenChars(fill_video_memory) => fill_video_memory.byte_size(),
             Command::SetPalette4(set_palette4) => set_palette4.byte_size(),
             Command::SetBorderColor(set_border_color) => set_border_color.byte_size(),
+            Command::ClearScreenColors(clear_screen_colors) => clear_screen_colors.byte_size(),
+            Command::WaitFrames(wait_frames) => wait_frames.byte_size(),
+            Command::GotoFrame(goto_frame) => goto_frame.byte_size(),
+            Command::LoopStart(loop_start) => loop_start.byte_size(),
+            Command::LoopEnd(loop_end) => loop_end.byte_size(),
+            Command::PackedCommands(packed_commands) => packed_commands.byte_size(),
             Command::UpdateCharsU16Encoded(update_chars) => update_chars.byte_size(),
             Command::UpdateCharsRangedU16Encoded(update_chars_ranged) => update_chars_ranged.byte_size(),
+            Command::UpdateCharsRaw(update_chars) => update_chars.byte_size(),
+            Command::UpdateCharsRangedRaw(update_chars_ranged) => update_chars_ranged.byte_size(),
             Command::UpdateTextModeScreen(update_text_mode_screen) => update_text_mode_screen.byte_size(),
             Command::PartialUpdateTextModeScreen(partial_update_text_mode) => partial_update_text_mode.byte_size(),
             Command::UpdateScreenCharsRLE(update_screen_chars_rle) => update_screen_chars_rle.byte_size(),
+            Command::UpdateScreenCharsRLEExtended(update_screen_chars_rle) => update_screen_chars_rle.byte_size(),
+            Command::UpdateScreenColors(update_screen_colors) => update_screen_colors.byte_size(),
+            Command::UpdateScreenColorsRLE(update_screen_colors_rle) => update_screen_colors_rle.byte_size(),
+            Command::SetSpriteData(set_sprite_data) => set_sprite_data.byte_size(),
+            Command::SetSpritePointers(set_sprite_pointers) => set_sprite_pointers.byte_size(),
+            Command::SetSpritePositions(set_sprite_positions) => set_sprite_positions.byte_size(),
+            Command::SetSpriteColors(set_sprite_colors) => set_sprite_colors.byte_size(),
+            Command::SetSpriteEnable(set_sprite_enable) => set_sprite_enable.byte_size(),
         };
         command_size + size_of::<u8>()
     }
@@ -73,6 +162,36 @@ impl Encoder for Command {
                 encoded_data = set_border_color.encode(encoded_data);
                 encoded_data
             }
+            Command::ClearScreenColors(clear_screen_colors) => {
+                let mut encoded_data = CLEAR_SCREEN_COLORS.encode(encoded_data);
+                encoded_data = clear_screen_colors.encode(encoded_data);
+                encoded_data
+            }
+            Command::WaitFrames(wait_frames) => {
+                let mut encoded_data = WAIT_FRAMES.encode(encoded_data);
+                encoded_data = wait_frames.encode(encoded_data);
+                encoded_data
+            }
+            Command::GotoFrame(goto_frame) => {
+                let mut encoded_data = GOTO_FRAME.encode(encoded_data);
+                encoded_data = goto_frame.encode(encoded_data);
+                encoded_data
+            }
+            Command::LoopStart(loop_start) => {
+                let mut encoded_data = LOOP_START.encode(encoded_data);
+                encoded_data = loop_start.encode(encoded_data);
+                encoded_data
+            }
+            Command::LoopEnd(loop_end) => {
+                let mut encoded_data = LOOP_END.encode(encoded_data);
+                encoded_data = loop_end.encode(encoded_data);
+                encoded_data
+            }
+            Command::PackedCommands(packed_commands) => {
+                let mut encoded_data = PACKED_COMMANDS.encode(encoded_data);
+                encoded_data = packed_commands.encode(encoded_data);
+                encoded_data
+            }
             Command::UpdateCharsU16Encoded(update_chars) => {
                 let mut encoded_data = UPDATE_CHARS_U16.encode(encoded_data);
                 encoded_data = update_chars.encode(encoded_data);
@@ -83,6 +202,16 @@ impl Encoder for Command {
                 encoded_data = update_chars.encode(encoded_data);
                 encoded_data
             }
+            Command::UpdateCharsRaw(update_chars) => {
+                let mut encoded_data = UPDATE_CHARS_RAW.encode(encoded_data);
+                encoded_data = update_chars.encode(encoded_data);
+                encoded_data
+            }
+            Command::UpdateCharsRangedRaw(update_chars) => {
+                let mut encoded_data = UPDATE_CHARS_RANGED_RAW.encode(encoded_data);
+                encoded_data = update_chars.encode(encoded_data);
+                encoded_data
+            }
             Command::UpdateTextModeScreen(update_text_mode_screen) => {
                 let mut encoded_data = UPDATE_TEXT_MODE_SCREEN.encode(encoded_data);
                 encoded_data = update_text_mode_screen.encode(encoded_data);
@@ -98,6 +227,226 @@ impl Encoder for Command {
                 encoded_data = update_screen_chars_rle.encode(encoded_data);
                 encoded_data
             }
+            Command::UpdateScreenCharsRLEExtended(update_screen_chars_rle) => {
+                let mut encoded_data = self.command_type().encode(encoded_data);
+                encoded_data = update_screen_chars_rle.encode(encoded_data);
+                encoded_data
+            }
+            Command::UpdateScreenColors(update_screen_colors) => {
+                let mut encoded_data = UPDATE_SCREEN_COLORS.encode(encoded_data);
+                encoded_data = update_screen_colors.encode(encoded_data);
+                encoded_data
+            }
+            Command::UpdateScreenColorsRLE(update_screen_colors_rle) => {
+                let mut encoded_data = UPDATE_SCREEN_COLORS_RLE.encode(encoded_data);
+                encoded_data = update_screen_colors_rle.encode(encoded_data);
+                encoded_data
+            }
+            Command::SetSpriteData(set_sprite_data) => {
+                let mut encoded_data = SET_SPRITE_DATA.encode(encoded_data);
+                encoded_data = set_sprite_data.encode(encoded_data);
+                encoded_data
+            }
+            Command::SetSpritePointers(set_sprite_pointers) => {
+                let mut encoded_data = SET_SPRITE_POINTERS.encode(encoded_data);
+                encoded_data = set_sprite_pointers.encode(encoded_data);
+                encoded_data
+            }
+            Command::SetSpritePositions(set_sprite_positions) => {
+                let mut encoded_data = SET_SPRITE_POSITIONS.encode(encoded_data);
+                encoded_data = set_sprite_positions.encode(encoded_data);
+                encoded_data
+            }
+            Command::SetSpriteColors(set_sprite_colors) => {
+                let mut encoded_data = SET_SPRITE_COLORS.encode(encoded_data);
+                encoded_data = set_sprite_colors.encode(encoded_data);
+                encoded_data
+            }
+            Command::SetSpriteEnable(set_sprite_enable) => {
+                let mut encoded_data = SET_SPRITE_ENABLE.encode(encoded_data);
+                encoded_data = set_sprite_enable.encode(encoded_data);
+                encoded_data
+            }
+        }
+    }
+}
+
+impl Decoder for Command {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (command_type, encoded_data) = encoded_data.read::<u8>()?;
+        match command_type {
+            CLEAR_SCREEN_CHAR => decode_command(encoded_data, Command::ClearScreenChars),
+            SET_PALETTE4 => decode_command(encoded_data, Command::SetPalette4),
+            SET_BORDER_COLOR => decode_command(encoded_data, Command::SetBorderColor),
+            CLEAR_SCREEN_COLORS => decode_command(encoded_data, Command::ClearScreenColors),
+            WAIT_FRAMES => decode_command(encoded_data, Command::WaitFrames),
+            GOTO_FRAME => decode_command(encoded_data, Command::GotoFrame),
+            LOOP_START => decode_command(encoded_data, Command::LoopStart),
+            LOOP_END => decode_command(encoded_data, Command::LoopEnd),
+            PACKED_COMMANDS => decode_command(encoded_data, Command::PackedCommands),
+            UPDATE_CHARS_U16 => decode_command(encoded_data, Command::UpdateCharsU16Encoded),
+            UPDATE_CHARS_RANGED_U16 => decode_command(encoded_data, Command::UpdateCharsRangedU16Encoded),
+            UPDATE_CHARS_RAW => decode_command(encoded_data, Command::UpdateCharsRaw),
+            UPDATE_CHARS_RANGED_RAW => decode_command(encoded_data, Command::UpdateCharsRangedRaw),
+            UPDATE_TEXT_MODE_SCREEN => decode_command(encoded_data, Command::UpdateTextModeScreen),
+            PARTIAL_UPDATE_TEXT_MODE_SCREEN => decode_command(encoded_data, Command::PartialUpdateTextModeScreen),
+            UPDATE_SCREEN_CHARS_RLE => decode_command(encoded_data, Command::UpdateScreenCharsRLE),
+            UPDATE_SCREEN_CHARS_RLE_EXTENDED => decode_command(encoded_data, Command::UpdateScreenCharsRLEExtended),
+            UPDATE_SCREEN_CHARS_RLE_COLUMNS => decode_command(encoded_data, |update| {
+                Command::UpdateScreenCharsRLEExtended(UpdateScreenCharsRLEExtended {
+                    scan_order: ScanOrder::ColumnMajor,
+                    ..update
+                })
+            }),
+            UPDATE_SCREEN_CHARS_RLE_TILES => decode_command(encoded_data, |update| {
+                Command::UpdateScreenCharsRLEExtended(UpdateScreenCharsRLEExtended {
+                    scan_order: ScanOrder::Tiles8x8,
+                    ..update
+                })
+            }),
+            UPDATE_SCREEN_COLORS => decode_command(encoded_data, Command::UpdateScreenColors),
+            UPDATE_SCREEN_COLORS_RLE => decode_command(encoded_data, Command::UpdateScreenColorsRLE),
+            SET_SPRITE_DATA => decode_command(encoded_data, Command::SetSpriteData),
+            SET_SPRITE_POINTERS => decode_command(encoded_data, Command::SetSpritePointers),
+            SET_SPRITE_POSITIONS => decode_command(encoded_data, Command::SetSpritePositions),
+            SET_SPRITE_COLORS => decode_command(encoded_data, Command::SetSpriteColors),
+            SET_SPRITE_ENABLE => decode_command(encoded_data, Command::SetSpriteEnable),
+            _ => Err(DecodeError::UnknownCommandType(command_type)),
+        }
+    }
+}
+
+fn decode_command<T: Decoder>(encoded_data: &[u8], command: fn(T) -> Command) -> DecodeResult<(Command, &[u8])> {
+    let (decoded, encoded_data) = encoded_data.read::<T>()?;
+    Ok((command(decoded), encoded_data))
+}
+
+impl Command {
+    /// Command type that is stored in front of the encoded command.
+    pub fn command_type(&self) -> u8 {
+        match self {
+            Command::ClearScreenChars(_) => CLEAR_SCREEN_CHAR,
+            Command::SetPalette4(_) => SET_PALETTE4,
+            Command::SetBorderColor(_) => SET_BORDER_COLOR,
+            Command::ClearScreenColors(_) => CLEAR_SCREEN_COLORS,
+            Command::WaitFrames(_) => WAIT_FRAMES,
+            Command::GotoFrame(_) => GOTO_FRAME,
+            Command::LoopStart(_) => LOOP_START,
+            Command::LoopEnd(_) => LOOP_END,
+            Command::PackedCommands(_) => PACKED_COMMANDS,
+            Command::UpdateCharsU16Encoded(_) => UPDATE_CHARS_U16,
+            Command::UpdateCharsRangedU16Encoded(_) => UPDATE_CHARS_RANGED_U16,
+            Command::UpdateCharsRaw(_) => UPDATE_CHARS_RAW,
+            Command::UpdateCharsRangedRaw(_) => UPDATE_CHARS_RANGED_RAW,
+            Command::UpdateTextModeScreen(_) => UPDATE_TEXT_MODE_SCREEN,
+            Command::PartialUpdateTextModeScreen(_) => PARTIAL_UPDATE_TEXT_MODE_SCREEN,
+            Command::UpdateScreenCharsRLE(_) => UPDATE_SCREEN_CHARS_RLE,
+            Command::UpdateScreenCharsRLEExtended(update_screen_chars_rle) => {
+                match update_screen_chars_rle.scan_order {
+                    ScanOrder::RowMajor => UPDATE_SCREEN_CHARS_RLE_EXTENDED,
+                    ScanOrder::ColumnMajor => UPDATE_SCREEN_CHARS_RLE_COLUMNS,
+                    ScanOrder::Tiles8x8 => UPDATE_SCREEN_CHARS_RLE_TILES,
+                }
+            }
+            Command::UpdateScreenColors(_) => UPDATE_SCREEN_COLORS,
+            Command::UpdateScreenColorsRLE(_) => UPDATE_SCREEN_COLORS_RLE,
+            Command::SetSpriteData(_) => SET_SPRITE_DATA,
+            Command::SetSpritePointers(_) => SET_SPRITE_POINTERS,
+            Command::SetSpritePositions(_) => SET_SPRITE_POSITIONS,
+            Command::SetSpriteColors(_) => SET_SPRITE_COLORS,
+            Command::SetSpriteEnable(_) => SET_SPRITE_ENABLE,
+        }
+    }
+
+    /// Name of the command type, used in reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::ClearScreenChars(_) => "ClearScreenChars",
+            Command::SetPalette4(_) => "SetPalette4",
+            Command::SetBorderColor(_) => "SetBorderColor",
+            Command::ClearScreenColors(_) => "ClearScreenColors",
+            Command::WaitFrames(_) => "WaitFrames",
+            Command::GotoFrame(_) => "GotoFrame",
+            Command::LoopStart(_) => "LoopStart",
+            Command::LoopEnd(_) => "LoopEnd",
+            Command::PackedCommands(_) => "PackedCommands",
+            Command::UpdateCharsU16Encoded(_) => "UpdateCharsU16Encoded",
+            Command::UpdateCharsRangedU16Encoded(_) => "UpdateCharsRangedU16Encoded",
+            Command::UpdateCharsRaw(_) => "UpdateCharsRaw",
+            Command::UpdateCharsRangedRaw(_) => "UpdateCharsRangedRaw",
+            Command::UpdateTextModeScreen(_) => "UpdateTextModeScreen",
+            Command::PartialUpdateTextModeScreen(_) => "PartialUpdateTextModeScreen",
+            Command::UpdateScreenCharsRLE(_) => "UpdateScreenCharsRLE",
+            Command::UpdateScreenCharsRLEExtended(_) => "UpdateScreenCharsRLEExtended",
+            Command::UpdateScreenColors(_) => "UpdateScreenColors",
+            Command::UpdateScreenColorsRLE(_) => "UpdateScreenColorsRLE",
+            Command::SetSpriteData(_) => "SetSpriteData",
+            Command::SetSpritePointers(_) => "SetSpritePointers",
+            Command::SetSpritePositions(_) => "SetSpritePositions",
+            Command::SetSpriteColors(_) => "SetSpriteColors",
+            Command::SetSpriteEnable(_) => "SetSpriteEnable",
+        }
+    }
+
+    /// Number of commands the engine processes for this command.
+    ///
+    /// The engine processes packed commands one by one, followed by the end of the packed commands.
+    pub fn engine_command_count(&self) -> usize {
+        match self {
+            Command::PackedCommands(packed_commands) => packed_commands.commands().len() + 2,
+            _ => 1,
+        }
+    }
+
+    /// Create the commands to update the given chars in the charset.
+    ///
+    /// Chars that can be stored losslessly using the u16 encoding are grouped in an
+    /// [Command::UpdateCharsU16Encoded], all other chars in an [Command::UpdateCharsRaw].
+    pub fn update_chars(chars: &[UpdateChar]) -> Vec<Command> {
+        let (u16_chars, raw_chars): (Vec<UpdateChar>, Vec<UpdateChar>) =
+            chars.iter().partition(|char| is_u16_encodable(char.data));
+        let mut result = vec![];
+        if !u16_chars.is_empty() {
+            result.push(Command::UpdateCharsU16Encoded(UpdateCharsU16Encoded {
+                chars: u16_chars,
+            }));
+        }
+        if !raw_chars.is_empty() {
+            result.push(Command::UpdateCharsRaw(UpdateCharsRaw { chars: raw_chars }));
+        }
+        result
+    }
+
+    /// Create the command to update a range of chars in the charset starting at the given offset.
+    ///
+    /// Uses the u16 encoding when all chars can be stored losslessly, otherwise the raw encoding.
+    pub fn update_chars_ranged(offset: u8, chars: &[u64]) -> Command {
+        let chars = chars
+            .iter()
+            .map(|char| UpdateCharRanged { data: *char })
+            .collect::<Vec<UpdateCharRanged>>();
+        if chars.iter().all(|char| is_u16_encodable(char.data)) {
+            Command::UpdateCharsRangedU16Encoded(UpdateCharsRangedU16Encoded { offset, chars })
+        } else {
+            Command::UpdateCharsRangedRaw(UpdateCharsRangedRaw { offset, chars })
+        }
+    }
+
+    /// Create the command to update the screen chars using a run-length encoding.
+    ///
+    /// Uses [Command::UpdateScreenCharsRLE] unless it needs more packets than it can store, in which case
+    /// [Command::UpdateScreenCharsRLEExtended] is used.
+    pub fn update_screen_chars_rle(from_screen_chars: &[u8], to_screen_chars: &[u8]) -> Command {
+        let update = UpdateScreenCharsRLE::optimal_transition(from_screen_chars, to_screen_chars);
+        // The number of packets is stored in a byte.
+        if update.rle_packets.len() <= u8::MAX as usize {
+            Command::UpdateScreenCharsRLE(update)
+        } else {
+            Command::UpdateScreenCharsRLEExtended(UpdateScreenCharsRLEExtended::transition(
+                from_screen_chars,
+                to_screen_chars,
+                ScanOrder::RowMajor,
+            ))
         }
     }
 }
@@ -110,7 +459,35 @@ pub fn all_decoder_modules() -> Vec<(u8, Module)> {
         (CLEAR_SCREEN_CHAR, ClearScreenChars::module()),
         (SET_BORDER_COLOR, SetBorderColor::module()),
         (SET_PALETTE4, SetPalette4::module()),
+        (CLEAR_SCREEN_COLORS, ClearScreenColors::module()),
+        (WAIT_FRAMES, WaitFrames::module()),
+        (GOTO_FRAME, GotoFrame::module()),
+        (LOOP_START, LoopStart::module()),
+        (LOOP_END, LoopEnd::module()),
+        (PACKED_COMMANDS, PackedCommands::module()),
+        (PACKED_COMMANDS_END, PackedCommandsEnd::module()),
+        (UPDATE_CHARS_U16, UpdateCharsU16Encoded::module()),
         (UPDATE_CHARS_RANGED_U16, UpdateCharsRangedU16Encoded::module()),
+        (UPDATE_CHARS_RAW, UpdateCharsRaw::module()),
+        (UPDATE_CHARS_RANGED_RAW, UpdateCharsRangedRaw::module()),
+        (UPDATE_TEXT_MODE_SCREEN, UpdateTextModeScreen::module()),
+        (PARTIAL_UPDATE_TEXT_MODE_SCREEN, PartialUpdateTextModeScreen::module()),
         (UPDATE_SCREEN_CHARS_RLE, UpdateScreenCharsRLE::module()),
+        (UPDATE_SCREEN_CHARS_RLE_EXTENDED, UpdateScreenCharsRLEExtended::module()),
+        (
+            UPDATE_SCREEN_CHARS_RLE_COLUMNS,
+            UpdateScreenCharsRLEExtended::scan_order_module(ScanOrder::ColumnMajor),
+        ),
+        (
+            UPDATE_SCREEN_CHARS_RLE_TILES,
+            UpdateScreenCharsRLEExtended::scan_order_module(ScanOrder::Tiles8x8),
+        ),
+        (UPDATE_SCREEN_COLORS, UpdateScreenColors::module()),
+        (UPDATE_SCREEN_COLORS_RLE, UpdateScreenColorsRLE::module()),
+        (SET_SPRITE_DATA, SetSpriteData::module()),
+        (SET_SPRITE_POINTERS, SetSpritePointers::module()),
+        (SET_SPRITE_POSITIONS, SetSpritePositions::module()),
+        (SET_SPRITE_COLORS, SetSpriteColors::module()),
+        (SET_SPRITE_ENABLE, SetSpriteEnable::module()),
     ]
 }
@@ -49,6 +49,25 @@ impl DecoderModule for CommandsLeft {
                     )
                     .build(),
             )
+            .function(
+                FunctionBuilder::default()
+                    .name("engine__commands_left__set_last")
+                    .doc(&[
+                        "Mark the current command as the last command of the frame.",
+                        "",
+                        "Used by commands that move the current pointer to another frame.",
+                    ])
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_imm(0x01)
+                            .sta_addr("engine__commands_left")
+                            .lda_imm(0x00)
+                            .sta_addr_offs("engine__commands_left", 1)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
             .function(
                 FunctionBuilder::default()
                     .name("engine__commands_left__is_zero")
@@ -0,0 +1,338 @@
+use crate::command::packed_commands::DEPACK_BUFFER_SIZE;
+
+/// Number of zero page bytes starting at [EngineConfig::zero_page_base].
+pub const ZERO_PAGE_SIZE: u8 = 6;
+/// Number of zero page bytes starting at [EngineConfig::pointer_zero_page_base].
+pub const POINTER_ZERO_PAGE_SIZE: u8 = 5;
+
+const VIC_BANK_SIZE: u16 = 0x4000;
+const SCREEN_SLOT_SIZE: u16 = 0x0400;
+const CHARSET_SLOT_SIZE: u16 = 0x0800;
+const SCREEN_CHARS_SIZE: u32 = 0x0400;
+const CHARSET_SIZE: u32 = 0x0800;
+const SPRITE_BLOCK_SIZE: u32 = 0x0040;
+/// The VIC-II sees the character ROM at $1000-$1FFF of banks 0 and 2.
+const CHAR_ROM_SHADOW: (u32, u32) = (0x1000, 0x2000);
+/// The engine writes screen and charset using the CPU, which sees the I/O registers at $D000-$DFFF.
+const IO_AREA: (u32, u32) = (0xD000, 0xE000);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EngineConfigError {
+    /// VIC bank must be in 0-3.
+    InvalidVicBank(u8),
+    /// Screen slot must be in 0-15.
+    InvalidScreenSlot(u8),
+    /// Charset slot must be in 0-7.
+    InvalidCharsetSlot(u8),
+    /// Screen chars and charset share memory.
+    ScreenOverlapsCharset,
+    /// Both screen buffers use the same screen slot.
+    BackScreenOverlapsScreen,
+    /// Data at the address would be read from the character ROM by the VIC-II.
+    CharRomShadow(u16),
+    /// Data at the address cannot be written as the CPU sees the I/O registers there.
+    IoArea(u16),
+    /// Data at the address overlaps the buffer packed commands are unpacked in.
+    DepackBufferOverlap(u16),
+    /// Depack buffer at the address doesn't fit in $0200-$FFFF or overlaps the IO area.
+    InvalidDepackBuffer(u16),
+    /// Program at the address overlaps the screen chars, the charset or the depack buffer.
+    ProgramOverlap(u16),
+    /// Zero page variables of the engine don't fit in $02-$FF.
+    InvalidZeroPageBase(u8),
+    /// Both blocks of zero page variables share bytes.
+    ZeroPageOverlap,
+    /// Address isn't the start of a screen or charset slot.
+    MisalignedAddress(u16),
+    /// Screen chars and charset are in different VIC banks.
+    VicBankMismatch,
+}
+
+pub type EngineConfigResult<T> = Result<T, EngineConfigError>;
+
+/// Memory used by the engine.
+///
+/// The VIC-II sees a 16KB bank of memory. Screen chars and charset are placed in slots inside that bank, the sprite
+/// pointers are stored at the end of the screen chars. When a back screen slot is set the engine is double buffered,
+/// see [EngineConfig::back_screen_slot]. The engine keeps its pointers in two blocks of zero page bytes, one of
+/// [ZERO_PAGE_SIZE] bytes starting at [EngineConfig::zero_page_base] and one of [POINTER_ZERO_PAGE_SIZE] bytes
+/// starting at [EngineConfig::pointer_zero_page_base].
+///
+/// | Block   | Offset | Define                                        |
+/// |---------|--------|-----------------------------------------------|
+/// | base    | 0      | `CHAR_DECODE_DST_PTR`, `SPRITE_DATA_DST_PTR`  |
+/// | base    | 2      | `DEPACK_DST_PTR`                              |
+/// | base    | 4      | `DEPACK_MATCH_PTR`                            |
+/// | pointer | 0      | `SCRATCH_SPACE_00`                            |
+/// | pointer | 1      | `SCREEN_CHAR_PTR`                             |
+/// | pointer | 3      | `CURRENT_PTR`, `CHAR_DECODE_SRC_PTR`          |
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// VIC bank (0-3), bank 0 is $0000-$3FFF.
+    pub vic_bank: u8,
+    /// Screen chars slot (0-15) in 1KB steps from the start of the VIC bank.
+    pub screen_slot: u8,
+    /// Second screen chars slot (0-15), enables double buffering.
+    ///
+    /// Commands write the screen chars and sprite pointers of the buffer that isn't shown. At the end of each frame
+    /// the engine shows the written buffer by updating `VIC2_MEMORY_SETUP`, so the screen never shows a partially
+    /// updated frame. A frame therefore updates the buffer that was shown two frames earlier. Color RAM and the
+    /// charset have a fixed address and aren't double buffered.
+    pub back_screen_slot: Option<u8>,
+    /// Charset slot (0-7) in 2KB steps from the start of the VIC bank.
+    pub charset_slot: u8,
+    /// First zero page address of the destination pointers used when decoding chars, sprites and packed commands.
+    pub zero_page_base: u8,
+    /// First zero page address of the scratch byte, `SCREEN_CHAR_PTR` and `CURRENT_PTR`.
+    pub pointer_zero_page_base: u8,
+    /// Start of the [DEPACK_BUFFER_SIZE] bytes packed commands are unpacked in.
+    pub depack_buffer_address: u16,
+}
+
+impl Default for EngineConfig {
+    /// Screen chars at $C000, charset at $C800, depack buffer at $8000-$9FFF and zero page variables at $10-$15 and
+    /// $FB-$FF.
+    ///
+    /// These are the addresses the engine used before the zero page was configurable. They stay clear of $F5-$FA,
+    /// which the KERNAL rewrites when scanning the keyboard.
+    fn default() -> Self {
+        Self {
+            vic_bank: 3,
+            screen_slot: 0,
+            back_screen_slot: None,
+            charset_slot: 1,
+            zero_page_base: 0x10,
+            pointer_zero_page_base: 0xFB,
+            depack_buffer_address: 0x8000,
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Check that the VIC-II can show the screen chars and charset and that the engine can write them.
+    pub fn validate(&self) -> EngineConfigResult<()> {
+        if self.vic_bank > 3 {
+            return Err(EngineConfigError::InvalidVicBank(self.vic_bank));
+        }
+        for screen_slot in self.screen_slots() {
+            if screen_slot > 15 {
+                return Err(EngineConfigError::InvalidScreenSlot(screen_slot));
+            }
+        }
+        if self.back_screen_slot == Some(self.screen_slot) {
+            return Err(EngineConfigError::BackScreenOverlapsScreen);
+        }
+        if self.charset_slot > 7 {
+            return Err(EngineConfigError::InvalidCharsetSlot(self.charset_slot));
+        }
+        let zero_page = (
+            self.zero_page_base as u32,
+            self.zero_page_base as u32 + ZERO_PAGE_SIZE as u32,
+        );
+        let pointer_zero_page = (
+            self.pointer_zero_page_base as u32,
+            self.pointer_zero_page_base as u32 + POINTER_ZERO_PAGE_SIZE as u32,
+        );
+        for (base, end) in [zero_page, pointer_zero_page] {
+            if base < 0x02 || end > 0x100 {
+                return Err(EngineConfigError::InvalidZeroPageBase(base as u8));
+            }
+        }
+        if overlaps(zero_page, pointer_zero_page) {
+            return Err(EngineConfigError::ZeroPageOverlap);
+        }
+
+        let screens = self.screens().collect::<Vec<_>>();
+        let charset = self.charset();
+        if screens.iter().any(|screen| overlaps(*screen, charset)) {
+            return Err(EngineConfigError::ScreenOverlapsCharset);
+        }
+        let bank_start = self.vic_bank_address() as u32;
+        let char_rom_shadow = (bank_start + CHAR_ROM_SHADOW.0, bank_start + CHAR_ROM_SHADOW.1);
+        let depack_buffer = self.depack_buffer();
+        if depack_buffer.0 < 0x0200 || depack_buffer.1 > 0x10000 || overlaps(depack_buffer, IO_AREA) {
+            return Err(EngineConfigError::InvalidDepackBuffer(self.depack_buffer_address));
+        }
+        for range in screens.into_iter().chain([charset]) {
+            let address = range.0 as u16;
+            if self.vic_bank.is_multiple_of(2) && overlaps(range, char_rom_shadow) {
+                return Err(EngineConfigError::CharRomShadow(address));
+            }
+            if overlaps(range, IO_AREA) {
+                return Err(EngineConfigError::IoArea(address));
+            }
+            if overlaps(range, depack_buffer) {
+                return Err(EngineConfigError::DepackBufferOverlap(address));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a program of the given size loaded at the address doesn't overlap the screen chars, the charset or
+    /// the depack buffer. The program includes the engine and the engine data linked with it.
+    pub fn check_program(&self, address: u16, size: usize) -> EngineConfigResult<()> {
+        let program = (address as u32, address as u32 + size as u32);
+        if let Some(range) = self
+            .screens()
+            .chain([self.charset(), self.depack_buffer()])
+            .find(|range| overlaps(program, *range))
+        {
+            return Err(EngineConfigError::ProgramOverlap(range.0 as u16));
+        }
+        Ok(())
+    }
+
+    /// Find the configuration that places the screen chars and charset at the given addresses. The engine is double
+    /// buffered when a back screen chars address is given.
+    pub fn from_addresses(
+        screen_chars_address: u16,
+        back_screen_chars_address: Option<u16>,
+        charset_address: u16,
+        zero_page_base: u8,
+        pointer_zero_page_base: u8,
+        depack_buffer_address: u16,
+    ) -> EngineConfigResult<EngineConfig> {
+        let screen_addresses = [Some(screen_chars_address), back_screen_chars_address];
+        for address in screen_addresses.into_iter().flatten() {
+            if address / VIC_BANK_SIZE != charset_address / VIC_BANK_SIZE {
+                return Err(EngineConfigError::VicBankMismatch);
+            }
+            if !address.is_multiple_of(SCREEN_SLOT_SIZE) {
+                return Err(EngineConfigError::MisalignedAddress(address));
+            }
+        }
+        if !charset_address.is_multiple_of(CHARSET_SLOT_SIZE) {
+            return Err(EngineConfigError::MisalignedAddress(charset_address));
+        }
+        let config = EngineConfig {
+            vic_bank: (screen_chars_address / VIC_BANK_SIZE) as u8,
+            screen_slot: (screen_chars_address % VIC_BANK_SIZE / SCREEN_SLOT_SIZE) as u8,
+            back_screen_slot: back_screen_chars_address
+                .map(|address| (address % VIC_BANK_SIZE / SCREEN_SLOT_SIZE) as u8),
+            charset_slot: (charset_address % VIC_BANK_SIZE / CHARSET_SLOT_SIZE) as u8,
+            zero_page_base,
+            pointer_zero_page_base,
+            depack_buffer_address,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn vic_bank_address(&self) -> u16 {
+        self.vic_bank as u16 * VIC_BANK_SIZE
+    }
+
+    pub fn is_double_buffered(&self) -> bool {
+        self.back_screen_slot.is_some()
+    }
+
+    /// Address of the screen chars shown after initialization.
+    pub fn screen_chars_address(&self) -> u16 {
+        self.slot_address(self.screen_slot, SCREEN_SLOT_SIZE)
+    }
+
+    /// Address of the screen chars the first frame is written to. Same as [EngineConfig::screen_chars_address] when
+    /// not double buffered.
+    pub fn back_screen_chars_address(&self) -> u16 {
+        self.slot_address(self.back_screen_slot.unwrap_or(self.screen_slot), SCREEN_SLOT_SIZE)
+    }
+
+    pub fn charset_address(&self) -> u16 {
+        self.slot_address(self.charset_slot, CHARSET_SLOT_SIZE)
+    }
+
+    pub fn sprite_pointers_address(&self) -> u16 {
+        self.screen_chars_address() + 0x03F8
+    }
+
+    /// Check if the sprite pointer refers to a 64 byte block in the VIC bank that can be used for sprite data.
+    ///
+    /// The block must not overlap the screen chars, the charset, the character ROM seen by the VIC-II or the IO area.
+    /// For the default config these are the pointers 16-31 ($C400-$C7FF) and 128-255 ($E000-$FFFF).
+    pub fn is_valid_sprite_pointer(&self, pointer: u8) -> bool {
+        let block_start = self.vic_bank_address() as u32 + pointer as u32 * SPRITE_BLOCK_SIZE;
+        let block = (block_start, block_start + SPRITE_BLOCK_SIZE);
+        let bank_start = self.vic_bank_address() as u32;
+        let mut unusable = self.screens().chain([self.charset(), IO_AREA]).collect::<Vec<_>>();
+        if self.vic_bank.is_multiple_of(2) {
+            unusable.push((bank_start + CHAR_ROM_SHADOW.0, bank_start + CHAR_ROM_SHADOW.1));
+        }
+        !unusable.into_iter().any(|range| overlaps(block, range))
+    }
+
+    /// Sprite pointers that can be used for sprite data, see [EngineConfig::is_valid_sprite_pointer].
+    pub fn valid_sprite_pointers(&self) -> Vec<u8> {
+        (0..=u8::MAX)
+            .filter(|pointer| self.is_valid_sprite_pointer(*pointer))
+            .collect()
+    }
+
+    /// Value of the VIC bank bits of `C64_BANK_SELECTION` ($DD00). The bits are inverted: %11 selects bank 0.
+    pub fn bank_selection(&self) -> u8 {
+        3 - self.vic_bank
+    }
+
+    /// Value of `VIC2_MEMORY_SETUP` ($D018) after initialization.
+    pub fn memory_setup(&self) -> u8 {
+        (self.screen_slot << 4) | (self.charset_slot << 1)
+    }
+
+    /// Bits of `VIC2_MEMORY_SETUP` that change when flipping the screen buffers, 0 when not double buffered.
+    pub fn memory_setup_flip_mask(&self) -> u8 {
+        (self.screen_slot ^ self.back_screen_slot.unwrap_or(self.screen_slot)) << 4
+    }
+
+    pub fn char_decode_dst_ptr(&self) -> u8 {
+        self.zero_page_base
+    }
+
+    pub fn depack_dst_ptr(&self) -> u8 {
+        self.zero_page_base + 2
+    }
+
+    pub fn depack_match_ptr(&self) -> u8 {
+        self.zero_page_base + 4
+    }
+
+    pub fn scratch_space(&self) -> u8 {
+        self.pointer_zero_page_base
+    }
+
+    pub fn screen_char_ptr(&self) -> u8 {
+        self.pointer_zero_page_base + 1
+    }
+
+    pub fn current_ptr(&self) -> u8 {
+        self.pointer_zero_page_base + 3
+    }
+
+    fn screen_slots(&self) -> impl Iterator<Item = u8> {
+        [Some(self.screen_slot), self.back_screen_slot].into_iter().flatten()
+    }
+
+    /// Memory ranges of the screen chars of each screen buffer.
+    fn screens(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.screen_slots().map(|screen_slot| {
+            let screen_start = self.slot_address(screen_slot, SCREEN_SLOT_SIZE) as u32;
+            (screen_start, screen_start + SCREEN_CHARS_SIZE)
+        })
+    }
+
+    fn charset(&self) -> (u32, u32) {
+        let charset_start = self.charset_address() as u32;
+        (charset_start, charset_start + CHARSET_SIZE)
+    }
+
+    fn depack_buffer(&self) -> (u32, u32) {
+        let depack_start = self.depack_buffer_address as u32;
+        (depack_start, depack_start + DEPACK_BUFFER_SIZE as u32)
+    }
+
+    fn slot_address(&self, slot: u8, slot_size: u16) -> u16 {
+        self.vic_bank_address() + slot as u16 * slot_size
+    }
+}
+
+fn overlaps(a: (u32, u32), b: (u32, u32)) -> bool {
+    a.0 < b.1 && b.0 < a.1
+}
@@ -0,0 +1,224 @@
+use crate::command::modules::{EngineConfig, EngineConfigError};
+
+fn config(vic_bank: u8, screen_slot: u8, charset_slot: u8) -> EngineConfig {
+    EngineConfig {
+        vic_bank,
+        screen_slot,
+        charset_slot,
+        ..EngineConfig::default()
+    }
+}
+
+#[test]
+fn default_config() {
+    let config = EngineConfig::default();
+    assert_eq!(Ok(()), config.validate());
+    assert_eq!(0xC000, config.screen_chars_address());
+    assert_eq!(0xC800, config.charset_address());
+    assert_eq!(0xC3F8, config.sprite_pointers_address());
+    assert_eq!(0x00, config.bank_selection());
+    assert_eq!(0b00000010, config.memory_setup());
+    assert_eq!(0xFB, config.scratch_space());
+    assert_eq!(0xFC, config.screen_char_ptr());
+    assert_eq!(0xFE, config.current_ptr());
+}
+
+#[test]
+fn default_zero_page_avoids_kernal_keyboard_variables() {
+    let config = EngineConfig::default();
+    let kernal_keyboard_variables = 0xF5..=0xFA;
+    for pointer in [
+        config.char_decode_dst_ptr(),
+        config.depack_dst_ptr(),
+        config.depack_match_ptr(),
+        config.screen_char_ptr(),
+        config.current_ptr(),
+    ] {
+        assert!(!kernal_keyboard_variables.contains(&pointer));
+        assert!(!kernal_keyboard_variables.contains(&(pointer + 1)));
+    }
+    assert!(!kernal_keyboard_variables.contains(&config.scratch_space()));
+}
+
+#[test]
+fn config_addresses() {
+    let config = EngineConfig {
+        zero_page_base: 0x80,
+        pointer_zero_page_base: 0x90,
+        ..config(1, 2, 3)
+    };
+    assert_eq!(Ok(()), config.validate());
+    assert_eq!(0x4000, config.vic_bank_address());
+    assert_eq!(0x4800, config.screen_chars_address());
+    assert_eq!(0x5800, config.charset_address());
+    assert_eq!(0x02, config.bank_selection());
+    assert_eq!(0b00100110, config.memory_setup());
+    assert_eq!(0x90, config.scratch_space());
+    assert_eq!(0x93, config.current_ptr());
+    assert_eq!(
+        Ok(config),
+        EngineConfig::from_addresses(0x4800, None, 0x5800, 0x80, 0x90, 0x8000)
+    );
+}
+
+#[test]
+fn reject_invalid_config() {
+    assert_eq!(Err(EngineConfigError::InvalidVicBank(4)), config(4, 0, 1).validate());
+    assert_eq!(
+        Err(EngineConfigError::InvalidScreenSlot(16)),
+        config(3, 16, 1).validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::InvalidCharsetSlot(8)),
+        config(3, 0, 8).validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::ScreenOverlapsCharset),
+        config(1, 3, 1).validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::CharRomShadow(0x1000)),
+        config(0, 1, 2).validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::CharRomShadow(0x9400)),
+        config(2, 5, 0).validate()
+    );
+    assert_eq!(Err(EngineConfigError::IoArea(0xD000)), config(3, 0, 2).validate());
+    assert_eq!(
+        Err(EngineConfigError::DepackBufferOverlap(0x8000)),
+        config(2, 0, 7).validate()
+    );
+    assert_eq!(Ok(()), config(1, 0, 2).validate());
+    assert_eq!(
+        Err(EngineConfigError::InvalidZeroPageBase(0xFB)),
+        EngineConfig {
+            zero_page_base: 0xFB,
+            ..EngineConfig::default()
+        }
+        .validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::InvalidZeroPageBase(0x01)),
+        EngineConfig {
+            zero_page_base: 0x01,
+            ..EngineConfig::default()
+        }
+        .validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::InvalidZeroPageBase(0xFC)),
+        EngineConfig {
+            pointer_zero_page_base: 0xFC,
+            ..EngineConfig::default()
+        }
+        .validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::ZeroPageOverlap),
+        EngineConfig {
+            pointer_zero_page_base: 0x15,
+            ..EngineConfig::default()
+        }
+        .validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::VicBankMismatch),
+        EngineConfig::from_addresses(0x0400, None, 0x4000, 0x10, 0xFB, 0x8000)
+    );
+    assert_eq!(
+        Err(EngineConfigError::MisalignedAddress(0x0500)),
+        EngineConfig::from_addresses(0x0500, None, 0x2000, 0x10, 0xFB, 0x8000)
+    );
+}
+
+#[test]
+fn double_buffered_config() {
+    let config = EngineConfig {
+        back_screen_slot: Some(1),
+        ..EngineConfig::default()
+    };
+    assert_eq!(Ok(()), config.validate());
+    assert!(config.is_double_buffered());
+    assert_eq!(0xC000, config.screen_chars_address());
+    assert_eq!(0xC400, config.back_screen_chars_address());
+    assert_eq!(0b00010000, config.memory_setup_flip_mask());
+    assert_eq!(
+        Ok(config),
+        EngineConfig::from_addresses(0xC000, Some(0xC400), 0xC800, 0x10, 0xFB, 0x8000)
+    );
+    assert!(!EngineConfig::default().is_double_buffered());
+    assert_eq!(0xC000, EngineConfig::default().back_screen_chars_address());
+    assert_eq!(0, EngineConfig::default().memory_setup_flip_mask());
+
+    let with_back_screen_slot = |back_screen_slot| EngineConfig {
+        back_screen_slot: Some(back_screen_slot),
+        ..EngineConfig::default()
+    };
+    assert_eq!(
+        Err(EngineConfigError::InvalidScreenSlot(16)),
+        with_back_screen_slot(16).validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::BackScreenOverlapsScreen),
+        with_back_screen_slot(0).validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::ScreenOverlapsCharset),
+        with_back_screen_slot(2).validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::IoArea(0xD000)),
+        with_back_screen_slot(4).validate()
+    );
+    assert_eq!(
+        Err(EngineConfigError::VicBankMismatch),
+        EngineConfig::from_addresses(0xC000, Some(0x4400), 0xC800, 0x10, 0xFB, 0x8000)
+    );
+    assert_eq!(
+        Err(EngineConfigError::MisalignedAddress(0xC500)),
+        EngineConfig::from_addresses(0xC000, Some(0xC500), 0xC800, 0x10, 0xFB, 0x8000)
+    );
+}
+
+#[test]
+fn depack_buffer_placement() {
+    // Screen chars at $8400 and charset at $8800 need the depack buffer to move out of $8000-$9FFF.
+    let config = config(2, 1, 1);
+    assert_eq!(Err(EngineConfigError::DepackBufferOverlap(0x8400)), config.validate());
+    let config = EngineConfig {
+        depack_buffer_address: 0x4000,
+        ..config
+    };
+    assert_eq!(Ok(()), config.validate());
+    assert_eq!(
+        Ok(config),
+        EngineConfig::from_addresses(0x8400, None, 0x8800, 0x10, 0xFB, 0x4000)
+    );
+
+    for depack_buffer_address in [0x0100, 0xC000, 0xE001] {
+        assert_eq!(
+            Err(EngineConfigError::InvalidDepackBuffer(depack_buffer_address)),
+            EngineConfig {
+                depack_buffer_address,
+                ..EngineConfig::default()
+            }
+            .validate()
+        );
+    }
+}
+
+#[test]
+fn program_placement() {
+    let config = EngineConfig::default();
+    assert_eq!(Ok(()), config.check_program(0x0801, 0x8000 - 0x0801));
+    assert_eq!(Ok(()), config.check_program(0xA000, 0x2000));
+    assert_eq!(
+        Err(EngineConfigError::ProgramOverlap(0x8000)),
+        config.check_program(0x0801, 0x8000 - 0x0800)
+    );
+    assert_eq!(
+        Err(EngineConfigError::ProgramOverlap(0xC000)),
+        config.check_program(0xBFFF, 2)
+    );
+}
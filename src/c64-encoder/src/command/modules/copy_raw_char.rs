@@ -0,0 +1,38 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::command::DecoderModule;
+
+pub struct CopyRawChar {}
+
+impl DecoderModule for CopyRawChar {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("Copy raw char")
+            .function(
+                FunctionBuilder::default()
+                    .name("char__copy_raw")
+                    .doc(&[
+                        "Copies the 8 bytes of the character referenced by 'CHAR_DECODE_SRC_PTR'",
+                        "to the memory address referenced by 'CHAR_DECODE_DST_PTR'",
+                        "",
+                        "Uses Accumulator and Y indexer",
+                    ])
+                    .instructions(
+                        InstructionBuilder::default()
+                            .ldy_imm(7)
+                            .label("char__copy_raw__next")
+                            .lda_ind_y("CHAR_DECODE_SRC_PTR")
+                            .sta_ind_y("CHAR_DECODE_DST_PTR")
+                            .dey()
+                            .bpl_addr("char__copy_raw__next")
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
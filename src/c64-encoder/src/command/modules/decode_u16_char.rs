@@ -7,6 +7,34 @@ use crate::command::DecoderModule;
 
 pub struct DecodeU16Char {}
 
+pub trait CharDecodeDstPtrMacros {
+    /// Point 'CHAR_DECODE_DST_PTR' to the char in the charset that is stored in the accumulator.
+    ///
+    /// Covers all 256 chars; chars 32 and above are located in the next pages of the charset.
+    fn set_char_decode_dst_ptr_from_acc(&mut self) -> &mut Self;
+}
+
+impl CharDecodeDstPtrMacros for InstructionBuilder {
+    fn set_char_decode_dst_ptr_from_acc(&mut self) -> &mut Self {
+        self.pha()
+            .asl_acc()
+            .comment("Multiply accumulator with 8 by bit shifting 3 times")
+            .asl_acc()
+            .asl_acc()
+            .sta_addr("CHAR_DECODE_DST_PTR")
+            .pla()
+            .lsr_acc()
+            .comment("Shift 5 times for the page.")
+            .lsr_acc()
+            .lsr_acc()
+            .lsr_acc()
+            .lsr_acc()
+            .clc()
+            .adc_imm_high("CHARSET_PTR_PAGE0")
+            .sta_addr_offs("CHAR_DECODE_DST_PTR", 1)
+    }
+}
+
 impl DecoderModule for DecodeU16Char {
     fn module() -> Module {
         ModuleBuilder::default()
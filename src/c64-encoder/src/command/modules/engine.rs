@@ -4,38 +4,101 @@ use c64_assembler::{
 };
 use c64_assembler_macro::function;
 
-use super::{CommandsLeft, CurrentPTR, CurrentPtrMacros, DecodeU16Char, ScreenCharPTR};
+use super::{
+    irq_player_module, CommandsLeft, CopyRawChar, CurrentPTR, CurrentPtrMacros, DecodeU16Char, EngineConfig,
+    IrqPlayerOptions, Raster, ScreenCharPTR, MIN_RASTER_LINE,
+};
 use crate::command::{all_decoder_modules, DecoderModule};
 
+#[derive(Debug, Default, Copy, Clone)]
+pub struct EngineOptions {
+    /// Wait for the given raster line before processing the next frame.
+    ///
+    /// When not set, frames are processed as fast as possible and playback speed depends on how heavy each frame
+    /// is. Must be [MIN_RASTER_LINE] or above, lower lines cannot be told apart from the lines after 256.
+    pub sync_raster_line: Option<u8>,
+    /// Memory used by the engine.
+    pub config: EngineConfig,
+    /// Play frames from a raster interrupt installed by `engine__init`. Cannot be combined with
+    /// [EngineOptions::sync_raster_line], the interrupt already syncs the frames.
+    pub irq_player: Option<IrqPlayerOptions>,
+}
+
 pub trait EngineBuilder {
     fn add_engine(&mut self) -> &mut Self;
+    /// Add the engine using the given options.
+    ///
+    /// Panics when the engine config is invalid, see [EngineConfig::validate], when the options cannot be combined or
+    /// when [EngineOptions::sync_raster_line] is below [MIN_RASTER_LINE].
+    fn add_engine_with_options(&mut self, options: EngineOptions) -> &mut Self;
 }
 
 impl EngineBuilder for ApplicationBuilder {
     fn add_engine(&mut self) -> &mut Self {
-        self.define_address("CURRENT_PTR", 0xFE)
-            .define_address("SCREEN_CHAR_PTR", 0xFC)
-            .define_address("CHAR_DECODE_SRC_PTR", 0xFE)
-            .define_address("CHAR_DECODE_DST_PTR", 0x10)
-            .define_address("SCREEN_CHARS_PAGE0", 0xC000)
-            .define_address("SCREEN_CHARS_PAGE1", 0xC100)
-            .define_address("SCREEN_CHARS_PAGE2", 0xC200)
-            .define_address("SCREEN_CHARS_PAGE3", 0xC300)
+        self.add_engine_with_options(EngineOptions::default())
+    }
+
+    fn add_engine_with_options(&mut self, options: EngineOptions) -> &mut Self {
+        let config = options.config;
+        if let Err(error) = config.validate() {
+            panic!("invalid engine config {config:?}: {error:?}");
+        }
+        assert!(
+            options.irq_player.is_none() || options.sync_raster_line.is_none(),
+            "the IRQ player cannot be combined with sync_raster_line"
+        );
+        assert!(
+            options
+                .sync_raster_line
+                .is_none_or(|raster_line| raster_line >= MIN_RASTER_LINE),
+            "sync_raster_line must be {MIN_RASTER_LINE} or above"
+        );
+        let screen_chars = config.screen_chars_address();
+        let charset = config.charset_address();
+        self.define_address("CURRENT_PTR", config.current_ptr() as u16)
+            .define_address("SCREEN_CHAR_PTR", config.screen_char_ptr() as u16)
+            .define_address("CHAR_DECODE_SRC_PTR", config.current_ptr() as u16)
+            .define_address("CHAR_DECODE_DST_PTR", config.char_decode_dst_ptr() as u16)
+            .define_address("SCREEN_CHARS_PAGE0", screen_chars)
+            .define_address("SCREEN_CHARS_PAGE1", screen_chars + 0x100)
+            .define_address("SCREEN_CHARS_PAGE2", screen_chars + 0x200)
+            .define_address("SCREEN_CHARS_PAGE3", screen_chars + 0x300)
             .define_address("SCREEN_COLORS_PAGE0", 0xD800)
             .define_address("SCREEN_COLORS_PAGE1", 0xD900)
             .define_address("SCREEN_COLORS_PAGE2", 0xDA00)
             .define_address("SCREEN_COLORS_PAGE3", 0xDB00)
-            .define_address("CHARSET_PTR_PAGE0", 0xC800)
-            .define_address("CHARSET_PTR_PAGE1", 0xC900)
-            .define_address("CHARSET_PTR_PAGE2", 0xCA00)
-            .define_address("CHARSET_PTR_PAGE3", 0xCB00)
+            .define_address("CHARSET_PTR_PAGE0", charset)
+            .define_address("CHARSET_PTR_PAGE1", charset + 0x100)
+            .define_address("CHARSET_PTR_PAGE2", charset + 0x200)
+            .define_address("CHARSET_PTR_PAGE3", charset + 0x300)
             .define_address("C64_BANK_SELECTION", 0xDD00)
-            .define_address("SCRATCH_SPACE_00", 0xFB)
-            .module(Engine::module())
+            .define_address("RASTER_LINE", 0xD012)
+            .define_address("SCREEN_CONTROL_1", 0xD011)
+            .define_address("IRQ_STATUS", 0xD019)
+            .define_address("IRQ_ENABLE", 0xD01A)
+            .define_address("CIA1_INTERRUPT_CONTROL", 0xDC0D)
+            .define_address("KERNAL_IRQ_VECTOR", 0x0314)
+            .define_address("KERNAL_IRQ_RETURN", 0xEA81)
+            .define_address("VIC_BANK_START", config.vic_bank_address())
+            .define_address("SPRITE_DATA_DST_PTR", config.char_decode_dst_ptr() as u16)
+            .define_address("SPRITE_POINTERS", config.sprite_pointers_address())
+            .define_address("SPRITE_0_X", 0xD000)
+            .define_address("SPRITE_ENABLE", 0xD015)
+            .define_address("SPRITE_0_COLOR", 0xD027)
+            .define_address("SCRATCH_SPACE_00", config.scratch_space() as u16)
+            .define_address("DEPACK_DST_PTR", config.depack_dst_ptr() as u16)
+            .define_address("DEPACK_MATCH_PTR", config.depack_match_ptr() as u16)
+            .define_address("DEPACK_BUFFER", config.depack_buffer_address)
+            .module(engine_module(config))
             .module(CurrentPTR::module())
             .module(ScreenCharPTR::module())
             .module(CommandsLeft::module())
-            .module(DecodeU16Char::module());
+            .module(DecodeU16Char::module())
+            .module(CopyRawChar::module())
+            .module(Raster::module())
+            .module(frame_sync_module(options))
+            .module(screen_buffer_module(config))
+            .module(irq_player_module(options.irq_player));
         for (_, module) in all_decoder_modules() {
             self.module(module);
         }
@@ -46,83 +109,241 @@ pub struct Engine {}
 
 impl DecoderModule for Engine {
     fn module() -> Module {
-        ModuleBuilder::default()
-            .name("engine")
-            .function(
-                FunctionBuilder::default()
-                    .name("engine__init")
-                    .doc(&[
-                        "Initialize the engine.",
-                        "",
-                        " - assumes engine data is stored at 'engine-data'",
-                        " - sets the current pointer to the first frame",
-                    ])
-                    .instructions(
-                        InstructionBuilder::default()
-                            .lda_imm_low("engine_data")
-                            .sta_addr("CURRENT_PTR")
-                            .lda_imm_high("engine_data")
-                            .sta_addr_offs("CURRENT_PTR", 1)
-                            .inc_current_ptr(2)
-                            .comment("Advance the pointer with 2 bytes.")
-                            .comment("Number of frames is only needed when reading directly from disk.")
-                            .lda_imm(0)
-                            .comment("Attach the VIC-II to bank 3 ($C000-$FFFF)")
-                            .sta_addr("C64_BANK_SELECTION")
-                            .lda_imm(0b00000011)
-                            .comment("Set screen chars to $C000 and charset to $C800.")
-                            .sta_addr("VIC2_MEMORY_SETUP")
-                            .rts()
-                            .build(),
-                    )
-                    .build(),
-            )
-            .function(
-                FunctionBuilder::default()
-                    .name("engine__deinit")
-                    .instructions(
-                        InstructionBuilder::default()
-                            .lda_imm(3)
-                            .comment("Attach the VIC-II to bank 0 ($0000-$7FFF")
-                            .sta_addr("C64_BANK_SELECTION")
-                            .lda_imm(0b0010101)
-                            .sta_addr("VIC2_MEMORY_SETUP")
-                            .rts()
-                            .build(),
-                    )
-                    .build(),
-            )
-            .function(function!(
-            name="engine__frame__process"
-            instructions!(
-                jsr engine__commands_left__init
-                lda #$2
-                jsr engine__current_ptr__advance
-            engine__frame_commands__next:
-                jsr engine__commands_left__is_zero
-                bne engine__frame_command__process
-            engine__frame__exit:
-                "All commands in frame have been processed."
-                "Update VIC2 registries"
-                "TODO: use shadow registries"
-                "TODO: wait for vblank"
-                rts
+        engine_module(EngineConfig::default())
+    }
+}
+
+fn engine_module(config: EngineConfig) -> Module {
+    ModuleBuilder::default()
+        .name("engine")
+        .function(
+            FunctionBuilder::default()
+                .name("engine__init")
+                .doc(&[
+                    "Initialize the engine.",
+                    "",
+                    " - assumes engine data is stored at 'engine-data'",
+                    " - sets the current pointer to the first frame",
+                    " - installs the raster interrupt when using the IRQ player",
+                ])
+                .instructions(
+                    InstructionBuilder::default()
+                        .lda_imm_low("engine_data")
+                        .sta_addr("CURRENT_PTR")
+                        .lda_imm_high("engine_data")
+                        .sta_addr_offs("CURRENT_PTR", 1)
+                        .inc_current_ptr(2)
+                        .comment("Advance the pointer with 2 bytes.")
+                        .comment("Number of frames is only needed when reading directly from disk.")
+                        .lda_imm(config.bank_selection())
+                        .comment(&format!(
+                            "Attach the VIC-II to bank {} (${:04X}-${:04X})",
+                            config.vic_bank,
+                            config.vic_bank_address(),
+                            config.vic_bank_address() as u32 + 0x3FFF
+                        ))
+                        .sta_addr("C64_BANK_SELECTION")
+                        .lda_imm(config.memory_setup())
+                        .comment(&format!(
+                            "Set screen chars to ${:04X} and charset to ${:04X}.",
+                            config.screen_chars_address(),
+                            config.charset_address()
+                        ))
+                        .sta_addr("VIC2_MEMORY_SETUP")
+                        .jsr_addr("engine__screen__init")
+                        .jmp_addr("engine__irq__install")
+                        .build(),
+                )
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name("engine__deinit")
+                .instructions(
+                    InstructionBuilder::default()
+                        .jsr_addr("engine__irq__uninstall")
+                        .lda_imm(3)
+                        .comment("Attach the VIC-II to bank 0 ($0000-$7FFF")
+                        .sta_addr("C64_BANK_SELECTION")
+                        .lda_imm(0b0010101)
+                        .sta_addr("VIC2_MEMORY_SETUP")
+                        .rts()
+                        .build(),
+                )
+                .build(),
+        )
+        .instructions(
+            InstructionBuilder::default()
+                .label("engine__frame__ptr")
+                .comment("Start of the frame that is currently being processed.")
+                .comment("Used by commands that need to jump back to this frame.")
+                .raw(&[0x00; 2])
+                .build(),
+        )
+        .function(function!(
+        name="engine__frame__process"
+        instructions!(
+            jsr engine__frame__sync
+            jsr engine__frame__store_ptr
+            jsr engine__commands_left__init
+            lda #$2
+            jsr engine__current_ptr__advance
+        engine__frame_commands__next:
+            jsr engine__commands_left__is_zero
+            bne engine__frame_command__process
+        engine__frame__exit:
+            "All commands in frame have been processed."
+            "Update VIC2 registries"
+            "TODO: use shadow registries"
+            jmp engine__screen__flip
 
-            engine__frame_command__process:
-                jsr engine__frame_command__switch
-                jsr engine__commands_left__decrease
-                jmp engine__frame_commands__next
+        engine__frame_command__process:
+            jsr engine__frame_command__switch
+            jsr engine__commands_left__decrease
+            jmp engine__frame_commands__next
 
+            )
+        ))
+        .function(
+            FunctionBuilder::default()
+                .name("engine__frame__store_ptr")
+                .instructions(
+                    InstructionBuilder::default()
+                        .lda_addr("CURRENT_PTR")
+                        .sta_addr("engine__frame__ptr")
+                        .lda_addr_offs("CURRENT_PTR", 1)
+                        .sta_addr_offs("engine__frame__ptr", 1)
+                        .rts()
+                        .build(),
                 )
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name("engine__frame_command__switch")
+                .instructions(command_dispatcher())
+                .build(),
+        )
+        .build()
+}
+
+fn frame_sync_module(options: EngineOptions) -> Module {
+    let mut instructions = InstructionBuilder::default();
+    match options.sync_raster_line {
+        Some(raster_line) => instructions
+            .lda_imm(raster_line)
+            .comment("Wait for the raster line before processing the frame")
+            .jmp_addr("engine__raster__wait"),
+        None => instructions.rts(),
+    };
+    ModuleBuilder::default()
+        .name("engine__frame_sync")
+        .function(
+            FunctionBuilder::default()
+                .name("engine__frame__sync")
+                .instructions(instructions.build())
+                .build(),
+        )
+        .build()
+}
+
+/// Instructions that write screen chars or sprite pointers, with the offset of the operand high byte from the label and
+/// the screen page the instruction writes to. A test checks that every label of the engine that looks like a patch
+/// point is listed.
+pub(super) const SCREEN_PAGE_OPERANDS: [(&str, u16, u8); 15] = [
+    ("clear_screen_chars__store0", 2, 0),
+    ("update_text_mode_screen__store0", 2, 0),
+    ("partial_update_text_mode_screen__page", 1, 0),
+    ("update_screen_chars_rle__destination_page", 1, 0),
+    ("update_screen_chars_rle_extended__destination_page", 1, 0),
+    ("update_screen_chars_rle_columns__destination_page", 1, 0),
+    ("update_screen_chars_rle_tiles__destination_page", 1, 0),
+    ("engine__screen_char_ptr__reset_page", 1, 0),
+    ("clear_screen_chars__store1", 2, 1),
+    ("update_text_mode_screen__store1", 2, 1),
+    ("clear_screen_chars__store2", 2, 2),
+    ("update_text_mode_screen__store2", 2, 2),
+    ("clear_screen_chars__store3", 2, 3),
+    ("update_text_mode_screen__store3", 2, 3),
+    ("set_sprite_pointers__store", 2, 3),
+];
+
+/// Build the module that flips the screen buffers at the end of each frame.
+///
+/// When double buffered, the instructions writing screen chars and sprite pointers are patched to write to the back
+/// screen buffer. When not double buffered all functions return immediately.
+fn screen_buffer_module(config: EngineConfig) -> Module {
+    let mut init = InstructionBuilder::default();
+    let mut flip = InstructionBuilder::default();
+    let mut patch = InstructionBuilder::default();
+    if config.is_double_buffered() {
+        let front_page = (config.screen_chars_address() >> 8) as u8;
+        let back_page = (config.back_screen_chars_address() >> 8) as u8;
+        init.lda_imm(back_page)
+            .comment(&format!(
+                "Write the first frame to the screen chars at ${:04X}",
+                config.back_screen_chars_address()
             ))
-            .function(
-                FunctionBuilder::default()
-                    .name("engine__frame_command__switch")
-                    .instructions(command_dispatcher())
-                    .build(),
-            )
-            .build()
+            .sta_addr("engine__screen__back_page")
+            .jmp_addr("engine__screen__patch");
+        flip.lda_addr("VIC2_MEMORY_SETUP")
+            .eor_imm(config.memory_setup_flip_mask())
+            .comment("Show the screen buffer the frame was written to")
+            .sta_addr("VIC2_MEMORY_SETUP")
+            .lda_addr("engine__screen__back_page")
+            .eor_imm(front_page ^ back_page)
+            .comment("Write the next frame to the other screen buffer")
+            .sta_addr("engine__screen__back_page")
+            .jmp_addr("engine__screen__patch");
+        patch.lda_addr("engine__screen__back_page");
+        let mut current_page = 0;
+        for (label, operand_offset, page) in SCREEN_PAGE_OPERANDS {
+            if page != current_page {
+                patch.clc().adc_imm(page - current_page);
+                current_page = page;
+            }
+            patch.sta_addr_offs(label, operand_offset);
+        }
+        patch.rts();
+    } else {
+        init.rts();
+        flip.rts();
+        patch.rts();
     }
+    ModuleBuilder::default()
+        .name("engine__screen")
+        .instructions(
+            InstructionBuilder::default()
+                .label("engine__screen__back_page")
+                .comment("High byte of the screen chars the current frame is written to.")
+                .raw(&[0x00])
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name("engine__screen__init")
+                .doc(&["Select the screen buffer the first frame is written to."])
+                .instructions(init.build())
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name("engine__screen__flip")
+                .doc(&["Show the screen buffer the frame was written to and write the next frame to the other one."])
+                .instructions(flip.build())
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name("engine__screen__patch")
+                .doc(&[
+                    "Patch the instructions that write screen chars and sprite pointers to write to the screen buffer",
+                    "stored in 'engine__screen__back_page'.",
+                ])
+                .instructions(patch.build())
+                .build(),
+        )
+        .build()
 }
 
 fn command_dispatcher() -> Instructions {
@@ -0,0 +1,77 @@
+use c64_assembler::{
+    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    generator::{DasmGenerator, Generator},
+};
+
+use crate::command::modules::{engine::SCREEN_PAGE_OPERANDS, EngineBuilder, EngineConfig, EngineOptions};
+
+/// Labels that look like a patch point, but don't write screen chars or sprite pointers.
+const NOT_SCREEN_PAGE_OPERANDS: [&str; 9] = [
+    "engine__frame__store_ptr",
+    "engine__screen__back_page",
+    "update_screen_colors_rle__destination_page",
+    "update_screen_colors__store0",
+    "update_screen_colors__store1",
+    "update_screen_colors__store2",
+    "update_screen_colors__store3",
+    "update_screen_chars_rle_columns__store",
+    "update_screen_chars_rle_tiles__store",
+];
+
+fn is_patch_point(label: &str) -> bool {
+    label.ends_with("__destination_page") || label.ends_with("_page") || label.contains("__store")
+}
+
+/// Labels of the double buffered engine, taken from the lines of the listing that aren't indented.
+fn engine_labels() -> Vec<String> {
+    let application = ApplicationBuilder::default()
+        .include_vic2_defines()
+        .add_engine_with_options(EngineOptions {
+            config: EngineConfig {
+                back_screen_slot: Some(1),
+                ..EngineConfig::default()
+            },
+            ..EngineOptions::default()
+        })
+        .module(
+            ModuleBuilder::default()
+                .name("engine_data")
+                .instructions(
+                    InstructionBuilder::default()
+                        .label("engine_data")
+                        .raw(&[0x00, 0x00])
+                        .build(),
+                )
+                .build(),
+        )
+        .build()
+        .unwrap();
+    let listing = DasmGenerator::default().generate(application).unwrap();
+    listing
+        .lines()
+        .filter(|line| !line.starts_with(char::is_whitespace) && !line.starts_with(';'))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|label| label.trim_end_matches(':').to_string())
+        .collect()
+}
+
+#[test]
+fn screen_page_operands_are_complete() {
+    let labels = engine_labels();
+    for (label, _, _) in SCREEN_PAGE_OPERANDS {
+        assert!(
+            labels.iter().any(|engine_label| engine_label == label),
+            "{label} isn't part of the engine"
+        );
+    }
+    for label in labels.iter().filter(|label| is_patch_point(label)) {
+        assert!(
+            SCREEN_PAGE_OPERANDS
+                .iter()
+                .any(|(operand_label, _, _)| operand_label == label)
+                || NOT_SCREEN_PAGE_OPERANDS.contains(&label.as_str()),
+            "{label} isn't listed in SCREEN_PAGE_OPERANDS; add it when it writes screen chars or sprite pointers, or \
+             to NOT_SCREEN_PAGE_OPERANDS otherwise"
+        );
+    }
+}
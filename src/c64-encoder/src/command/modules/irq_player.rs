@@ -0,0 +1,192 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use super::DEFAULT_RASTER_LINE;
+
+/// Number of raster lines of a PAL video frame.
+const RASTER_LINES: u16 = 312;
+
+/// Play the demo from a raster interrupt instead of calling `engine__frame__process` from a main loop.
+///
+/// `engine__init` installs the interrupt handler in the KERNAL IRQ vector and disables the CIA1 timer interrupts the
+/// KERNAL uses. `engine__deinit` restores both. Main code keeps running while the demo plays, for example to wait for
+/// input or to run game logic.
+///
+/// Commands run with interrupts disabled. A [crate::command::wait_frames::WaitFrames] command doesn't wait inside the
+/// interrupt, it sets a countdown of interrupts that skip processing a frame but still call the hook. Commands after
+/// it in the same frame are processed right away.
+#[derive(Debug, Copy, Clone)]
+pub struct IrqPlayerOptions {
+    /// Raster line (0-311) that triggers the interrupt.
+    pub raster_line: u16,
+    /// Number of interrupts per processed frame. 1 processes a frame every video frame, 2 every other video frame.
+    pub interrupts_per_frame: u8,
+    /// Label of a function that is called on every interrupt after the frame has been processed, for example to play
+    /// music or scan the keyboard. The function may use all registers.
+    pub hook: Option<&'static str>,
+}
+
+impl Default for IrqPlayerOptions {
+    fn default() -> Self {
+        Self {
+            raster_line: DEFAULT_RASTER_LINE as u16,
+            interrupts_per_frame: 1,
+            hook: None,
+        }
+    }
+}
+
+/// Build the module that installs and removes the raster interrupt. When no options are given the demo is played
+/// from a main loop, and installing and removing return immediately.
+pub(crate) fn irq_player_module(options: Option<IrqPlayerOptions>) -> Module {
+    let mut module = ModuleBuilder::default();
+    module.name("engine__irq");
+    let Some(options) = options else {
+        return module
+            .function(
+                FunctionBuilder::default()
+                    .name("engine__irq__install")
+                    .instructions(InstructionBuilder::default().rts().build())
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("engine__irq__uninstall")
+                    .instructions(InstructionBuilder::default().rts().build())
+                    .build(),
+            )
+            .build();
+    };
+    assert!(
+        options.raster_line < RASTER_LINES,
+        "raster line {} doesn't exist",
+        options.raster_line
+    );
+    assert!(
+        options.interrupts_per_frame > 0,
+        "at least one interrupt per frame is needed"
+    );
+
+    let mut handler = InstructionBuilder::default();
+    handler
+        .lda_imm(0x01)
+        .comment("Acknowledge the raster interrupt")
+        .sta_addr("IRQ_STATUS")
+        .lda_addr("wait_frames__countdown")
+        .beq_addr("engine__irq__divide")
+        .dec_addr("wait_frames__countdown")
+        .comment("Skip processing frames while waiting")
+        .jmp_addr("engine__irq__call_hook")
+        .label("engine__irq__divide")
+        .dec_addr("engine__irq__countdown")
+        .bne_addr("engine__irq__call_hook")
+        .lda_imm(options.interrupts_per_frame)
+        .sta_addr("engine__irq__countdown")
+        .jsr_addr("engine__frame__process")
+        .label("engine__irq__call_hook");
+    if let Some(hook) = options.hook {
+        handler.jsr_addr(hook);
+    }
+    handler
+        .jmp_addr("KERNAL_IRQ_RETURN")
+        .comment("Restore the registers pushed by the KERNAL and return from the interrupt");
+
+    module
+        .instructions(
+            InstructionBuilder::default()
+                .label("engine__irq__kernal_vector")
+                .comment("IRQ vector before installing, high byte is 0 when not installed.")
+                .raw(&[0x00; 2])
+                .label("engine__irq__countdown")
+                .comment("Number of interrupts until the next frame is processed.")
+                .raw(&[0x00])
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name("engine__irq__install")
+                .doc(&["Install the raster interrupt that plays the demo."])
+                .instructions(
+                    InstructionBuilder::default()
+                        .sei()
+                        .lda_addr("KERNAL_IRQ_VECTOR")
+                        .sta_addr("engine__irq__kernal_vector")
+                        .lda_addr_offs("KERNAL_IRQ_VECTOR", 1)
+                        .sta_addr_offs("engine__irq__kernal_vector", 1)
+                        .lda_imm_low("engine__irq__handler")
+                        .sta_addr("KERNAL_IRQ_VECTOR")
+                        .lda_imm_high("engine__irq__handler")
+                        .sta_addr_offs("KERNAL_IRQ_VECTOR", 1)
+                        .lda_imm(0x7F)
+                        .comment("Disable the CIA1 timer interrupts")
+                        .sta_addr("CIA1_INTERRUPT_CONTROL")
+                        .lda_addr("CIA1_INTERRUPT_CONTROL")
+                        .comment("Acknowledge pending CIA1 interrupts")
+                        .lda_imm((options.raster_line & 0xFF) as u8)
+                        .comment(&format!("Trigger the interrupt at raster line {}", options.raster_line))
+                        .sta_addr("RASTER_LINE")
+                        .lda_addr("SCREEN_CONTROL_1")
+                        .and_imm(0x7F)
+                        .ora_imm(((options.raster_line >> 8) << 7) as u8)
+                        .comment("Bit 7 is bit 8 of the raster line")
+                        .sta_addr("SCREEN_CONTROL_1")
+                        .lda_imm(0x01)
+                        .comment("Process the first frame at the first interrupt")
+                        .sta_addr("engine__irq__countdown")
+                        .sta_addr("wait_frames__deferred")
+                        .comment("Let the interrupt handler count down waits")
+                        .sta_addr("IRQ_STATUS")
+                        .sta_addr("IRQ_ENABLE")
+                        .cli()
+                        .rts()
+                        .build(),
+                )
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name("engine__irq__uninstall")
+                .doc(&["Remove the raster interrupt and restore the KERNAL interrupts."])
+                .instructions(
+                    InstructionBuilder::default()
+                        .lda_addr_offs("engine__irq__kernal_vector", 1)
+                        .beq_addr("engine__irq__uninstall_exit")
+                        .sei()
+                        .lda_imm(0x00)
+                        .sta_addr("IRQ_ENABLE")
+                        .sta_addr("wait_frames__deferred")
+                        .sta_addr("wait_frames__countdown")
+                        .lda_imm(0x01)
+                        .sta_addr("IRQ_STATUS")
+                        .lda_addr("engine__irq__kernal_vector")
+                        .sta_addr("KERNAL_IRQ_VECTOR")
+                        .lda_addr_offs("engine__irq__kernal_vector", 1)
+                        .sta_addr_offs("KERNAL_IRQ_VECTOR", 1)
+                        .lda_imm(0x00)
+                        .sta_addr_offs("engine__irq__kernal_vector", 1)
+                        .lda_imm(0x81)
+                        .comment("Enable the CIA1 timer A interrupt used by the KERNAL")
+                        .sta_addr("CIA1_INTERRUPT_CONTROL")
+                        .cli()
+                        .label("engine__irq__uninstall_exit")
+                        .rts()
+                        .build(),
+                )
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name("engine__irq__handler")
+                .doc(&[
+                    "Process a frame every 'interrupts_per_frame' raster interrupts and call the hook. Interrupts during",
+                    "a wait only call the hook.",
+                    "",
+                    "Called by the KERNAL IRQ handler, which already pushed the registers.",
+                ])
+                .instructions(handler.build())
+                .build(),
+        )
+        .build()
+}
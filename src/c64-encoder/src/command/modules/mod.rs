@@ -1,11 +1,23 @@
 mod commands_left;
+mod config;
+#[cfg(test)]
+mod config_test;
+mod copy_raw_char;
 mod current_ptr;
 mod decode_u16_char;
 mod engine;
+#[cfg(test)]
+mod engine_test;
+mod irq_player;
+mod raster;
 mod screen_char_ptr;
 
 pub use commands_left::*;
+pub use config::*;
+pub use copy_raw_char::*;
 pub use current_ptr::*;
 pub use decode_u16_char::*;
 pub use engine::*;
+pub use irq_player::*;
+pub use raster::*;
 pub use screen_char_ptr::*;
@@ -0,0 +1,51 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::command::DecoderModule;
+
+/// Raster line to wait for when waiting for the next video frame.
+///
+/// Located in the lower border, just after the last line of the text screen. Lines 256 and above have a low byte
+/// below 56, so comparing the low byte is enough to detect this line.
+pub const DEFAULT_RASTER_LINE: u8 = 251;
+
+/// Lowest raster line `engine__raster__wait` can wait for.
+///
+/// Only the low byte of the raster line is compared. Lines 256 and above (up to 311 on PAL, 262 on NTSC) have a low
+/// byte below 56, so waiting for a lower line would also return at the matching line after 256.
+pub const MIN_RASTER_LINE: u8 = 56;
+
+pub struct Raster {}
+
+impl DecoderModule for Raster {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("engine__raster")
+            .function(
+                FunctionBuilder::default()
+                    .name("engine__raster__wait")
+                    .doc(&[
+                        "Wait until the VIC2 reaches the raster line stored in the accumulator.",
+                        "",
+                        "Only the low byte of the raster line is compared, lines below 56 cannot be waited for.",
+                        "",
+                        "Returns when the raster line has been left, so calling it again waits for the next video frame.",
+                    ])
+                    .instructions(
+                        InstructionBuilder::default()
+                            .label("engine__raster__wait_enter")
+                            .cmp_addr("RASTER_LINE")
+                            .bne_addr("engine__raster__wait_enter")
+                            .label("engine__raster__wait_leave")
+                            .cmp_addr("RASTER_LINE")
+                            .beq_addr("engine__raster__wait_leave")
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
@@ -15,6 +15,7 @@ impl DecoderModule for ScreenCharPTR {
                         InstructionBuilder::default()
                             .lda_imm_low("SCREEN_CHARS_PAGE0")
                             .sta_addr("SCREEN_CHAR_PTR")
+                            .label("engine__screen_char_ptr__reset_page")
                             .lda_imm_high("SCREEN_CHARS_PAGE0")
                             .sta_addr_offs("SCREEN_CHAR_PTR", 1)
                             .rts()
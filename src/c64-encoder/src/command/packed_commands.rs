@@ -0,0 +1,234 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    builder::frame::Commands,
+    decoder::{reader::Reader, DecodeError, DecodeResult, Decoder},
+    encoder::Encoder,
+    lz,
+};
+
+use super::{modules::CurrentPtrMacros, Command, DecoderModule, PACKED_COMMANDS_END};
+
+/// Size of the buffer the engine unpacks packed commands into, located at
+/// [super::modules::EngineConfig::depack_buffer_address].
+pub const DEPACK_BUFFER_SIZE: usize = 0x2000;
+
+/// Commands that are stored LZ packed in the engine data, see [crate::lz] for the format.
+///
+/// The engine unpacks the commands into the depack buffer and processes them from there. The unpacked commands end
+/// with a [PackedCommandsEnd] command that continues with the command following the packed commands. Both are counted
+/// in the number of commands of the frame, see [Command::engine_command_count].
+///
+/// Packed commands cannot be nested.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackedCommands {
+    commands: Commands,
+    packed_data: Vec<u8>,
+}
+
+impl PackedCommands {
+    pub fn new(commands: Commands) -> Self {
+        assert!(
+            !commands
+                .iter()
+                .any(|command| matches!(command, Command::PackedCommands(_))),
+            "packed commands cannot be nested"
+        );
+        let mut unpacked_data = vec![0; commands.byte_size() + size_of::<u8>()];
+        commands.encode(&mut unpacked_data);
+        *unpacked_data.last_mut().unwrap() = PACKED_COMMANDS_END;
+        assert!(
+            unpacked_data.len() <= DEPACK_BUFFER_SIZE,
+            "unpacked commands ({} bytes) don't fit in the depack buffer",
+            unpacked_data.len()
+        );
+        let packed_data = lz::pack(&unpacked_data);
+        Self { commands, packed_data }
+    }
+
+    pub fn commands(&self) -> &Commands {
+        &self.commands
+    }
+
+    /// Number of bytes of the commands before packing, including the [PackedCommandsEnd] command.
+    pub fn unpacked_byte_size(&self) -> usize {
+        self.commands.byte_size() + size_of::<u8>()
+    }
+}
+
+impl Encoder for PackedCommands {
+    fn byte_size(&self) -> usize {
+        self.packed_data.len()
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        encoded_data[..self.packed_data.len()].copy_from_slice(&self.packed_data);
+        &mut encoded_data[self.packed_data.len()..]
+    }
+}
+
+impl Decoder for PackedCommands {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (unpacked_data, remaining) = lz::unpack(encoded_data)?;
+        if unpacked_data.len() > DEPACK_BUFFER_SIZE {
+            return Err(DecodeError::InvalidPackedData);
+        }
+        let mut commands = Commands::default();
+        let mut unpacked_data = unpacked_data.as_slice();
+        while unpacked_data != [PACKED_COMMANDS_END] {
+            if unpacked_data.is_empty() {
+                return Err(DecodeError::InvalidPackedData);
+            }
+            let command;
+            (command, unpacked_data) = unpacked_data.read::<Command>()?;
+            if matches!(command, Command::PackedCommands(_)) {
+                return Err(DecodeError::InvalidPackedData);
+            }
+            commands.push(command);
+        }
+        let packed_data = encoded_data[..encoded_data.len() - remaining.len()].to_vec();
+        Ok((PackedCommands { commands, packed_data }, remaining))
+    }
+}
+
+impl DecoderModule for PackedCommands {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("packed_commands")
+            .instructions(
+                InstructionBuilder::default()
+                    .label("packed_commands__resume_ptr")
+                    .comment("Position in the engine data after the packed commands.")
+                    .raw(&[0x00; 2])
+                    .label("packed_commands__len")
+                    .comment("Number of bytes of the current literal or match.")
+                    .raw(&[0x00])
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("packed_commands__process")
+                    .doc(&[
+                        "Unpack the commands into the depack buffer.",
+                        "",
+                        "Stores the position after the packed data and moves the current pointer to the start of",
+                        "the depack buffer, so the unpacked commands are processed next.",
+                    ])
+                    .instructions(
+                        InstructionBuilder::default()
+                            .inc_current_ptr(1)
+                            .lda_imm_low("DEPACK_BUFFER")
+                            .sta_addr("DEPACK_DST_PTR")
+                            .lda_imm_high("DEPACK_BUFFER")
+                            .sta_addr_offs("DEPACK_DST_PTR", 1)
+                            .label("packed_commands__next_token")
+                            .lda_current_ptr_offs(0, "Load the next token")
+                            .beq_addr("packed_commands__end")
+                            .bmi_addr("packed_commands__match")
+                            .comment("Copy literal bytes.")
+                            .sta_addr("packed_commands__len")
+                            .inc_current_ptr(1)
+                            .ldy_imm(0)
+                            .label("packed_commands__literal")
+                            .lda_ind_y("CURRENT_PTR")
+                            .sta_ind_y("DEPACK_DST_PTR")
+                            .iny()
+                            .cpy_addr("packed_commands__len")
+                            .bne_addr("packed_commands__literal")
+                            .lda_addr("packed_commands__len")
+                            .jsr_addr("engine__current_ptr__advance")
+                            .jsr_addr("packed_commands__advance_dst")
+                            .jmp_addr("packed_commands__next_token")
+                            .label("packed_commands__match")
+                            .comment("Copy a match from earlier unpacked bytes, one byte at a time as it can overlap.")
+                            .and_imm(0x7F)
+                            .clc()
+                            .adc_imm(lz::MIN_MATCH_LEN as u8)
+                            .sta_addr("packed_commands__len")
+                            .ldy_imm(1)
+                            .clc()
+                            .lda_ind_y("CURRENT_PTR")
+                            .adc_imm_low("DEPACK_BUFFER")
+                            .sta_addr("DEPACK_MATCH_PTR")
+                            .iny()
+                            .lda_ind_y("CURRENT_PTR")
+                            .adc_imm_high("DEPACK_BUFFER")
+                            .sta_addr_offs("DEPACK_MATCH_PTR", 1)
+                            .inc_current_ptr(3)
+                            .ldy_imm(0)
+                            .label("packed_commands__match_copy")
+                            .lda_ind_y("DEPACK_MATCH_PTR")
+                            .sta_ind_y("DEPACK_DST_PTR")
+                            .iny()
+                            .cpy_addr("packed_commands__len")
+                            .bne_addr("packed_commands__match_copy")
+                            .jsr_addr("packed_commands__advance_dst")
+                            .jmp_addr("packed_commands__next_token")
+                            .label("packed_commands__end")
+                            .inc_current_ptr(1)
+                            .lda_addr("CURRENT_PTR")
+                            .sta_addr("packed_commands__resume_ptr")
+                            .lda_addr_offs("CURRENT_PTR", 1)
+                            .sta_addr_offs("packed_commands__resume_ptr", 1)
+                            .lda_imm_low("DEPACK_BUFFER")
+                            .comment("Process the unpacked commands.")
+                            .sta_addr("CURRENT_PTR")
+                            .lda_imm_high("DEPACK_BUFFER")
+                            .sta_addr_offs("CURRENT_PTR", 1)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("packed_commands__advance_dst")
+                    .doc(&["Advance the depack destination pointer with the length of the last literal or match."])
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_addr("packed_commands__len")
+                            .clc()
+                            .adc_addr("DEPACK_DST_PTR")
+                            .sta_addr("DEPACK_DST_PTR")
+                            .lda_imm(0x00)
+                            .adc_addr_offs("DEPACK_DST_PTR", 1)
+                            .sta_addr_offs("DEPACK_DST_PTR", 1)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
+
+/// Last unpacked command of [PackedCommands]. Continues with the command following the packed commands in the
+/// engine data.
+///
+/// Only exists in unpacked data and is therefore not a [Command].
+pub struct PackedCommandsEnd {}
+
+impl DecoderModule for PackedCommandsEnd {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("packed_commands_end")
+            .function(
+                FunctionBuilder::default()
+                    .name("packed_commands_end__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_addr("packed_commands__resume_ptr")
+                            .sta_addr("CURRENT_PTR")
+                            .lda_addr_offs("packed_commands__resume_ptr", 1)
+                            .sta_addr_offs("CURRENT_PTR", 1)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
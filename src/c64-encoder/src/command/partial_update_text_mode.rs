@@ -1,6 +1,19 @@
-use crate::encoder::{writer::Writer, Encoder};
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
 
-#[derive(Debug, Clone)]
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{
+    modules::{CurrentPtrMacros, ScreenCharPtrMacros},
+    DecoderModule,
+};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct PartialUpdateTextModeScreen {
     pub changes: Vec<UpdateSingleChar>,
 }
@@ -21,7 +34,7 @@ impl PartialUpdateTextModeScreen {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct UpdateSingleChar {
     pub offset: u16,
     pub char: u8,
@@ -43,6 +56,19 @@ impl Encoder for PartialUpdateTextModeScreen {
     }
 }
 
+impl Decoder for PartialUpdateTextModeScreen {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (num_changes, mut encoded_data) = encoded_data.read::<u16>()?;
+        let mut changes = Vec::with_capacity(num_changes as usize);
+        for _ in 0..num_changes {
+            let change;
+            (change, encoded_data) = encoded_data.read()?;
+            changes.push(change);
+        }
+        Ok((PartialUpdateTextModeScreen { changes }, encoded_data))
+    }
+}
+
 impl Encoder for UpdateSingleChar {
     fn byte_size(&self) -> usize {
         2 + 1
@@ -52,3 +78,69 @@ impl Encoder for UpdateSingleChar {
         encoded_data.add(&self.offset).add(&self.char)
     }
 }
+
+impl Decoder for UpdateSingleChar {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (offset, encoded_data) = encoded_data.read()?;
+        let (char, encoded_data) = encoded_data.read()?;
+        Ok((UpdateSingleChar { offset, char }, encoded_data))
+    }
+}
+
+impl DecoderModule for PartialUpdateTextModeScreen {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("partial_update_text_mode_screen")
+            .instructions(
+                InstructionBuilder::default()
+                    .label("partial_update_text_mode_screen__changes_left")
+                    .comment("Number of changes left to process in the current command.")
+                    .raw(&[0x00; 2])
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("partial_update_text_mode_screen__process")
+                    .doc(&[
+                        "Update individual screen chars.",
+                        "",
+                        "Each change contains a u16 offset relative to 'SCREEN_CHARS_PAGE0' followed by the screen char.",
+                        "Uses 'SCREEN_CHAR_PTR' to address the screen char to update.",
+                    ])
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(1, "Load low byte of the number of changes into the accumulator")
+                            .sta_addr("partial_update_text_mode_screen__changes_left")
+                            .lda_current_ptr_offs(2, "Load high byte of the number of changes into the accumulator")
+                            .sta_addr_offs("partial_update_text_mode_screen__changes_left", 1)
+                            .inc_current_ptr(3)
+                            .label("partial_update_text_mode_screen__next")
+                            .lda_addr("partial_update_text_mode_screen__changes_left")
+                            .ora_addr_offs("partial_update_text_mode_screen__changes_left", 1)
+                            .beq_addr("partial_update_text_mode_screen__exit")
+                            .lda_current_ptr_offs(0, "Point SCREEN_CHAR_PTR to the screen char to update")
+                            .clc()
+                            .adc_imm_low("SCREEN_CHARS_PAGE0")
+                            .sta_addr("SCREEN_CHAR_PTR")
+                            .lda_current_ptr_offs(1, "Load high byte of the offset into the accumulator")
+                            .label("partial_update_text_mode_screen__page")
+                            .adc_imm_high("SCREEN_CHARS_PAGE0")
+                            .sta_addr_offs("SCREEN_CHAR_PTR", 1)
+                            .lda_current_ptr_offs(2, "Load screen char into the accumulator")
+                            .sta_screen_char_ptr_offs(0)
+                            .inc_current_ptr(3)
+                            .lda_addr("partial_update_text_mode_screen__changes_left")
+                            .bne_addr("partial_update_text_mode_screen__decrease_low")
+                            .dec_addr_offs("partial_update_text_mode_screen__changes_left", 1)
+                            .label("partial_update_text_mode_screen__decrease_low")
+                            .dec_addr("partial_update_text_mode_screen__changes_left")
+                            .jmp_addr("partial_update_text_mode_screen__next")
+                            .label("partial_update_text_mode_screen__exit")
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
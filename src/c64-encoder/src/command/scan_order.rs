@@ -0,0 +1,63 @@
+//! Order in which a run-length encoding visits the screen chars.
+//!
+//! Animations that move vertically or in small areas have longer runs when the screen chars aren't visited row by
+//! row.
+
+/// Number of screen chars in a row of the screen.
+pub const SCREEN_COLUMNS: usize = 40;
+/// Number of rows of the screen.
+pub const SCREEN_ROWS: usize = 25;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ScanOrder {
+    /// Rows from top to bottom, each row from left to right.
+    #[default]
+    RowMajor,
+    /// Columns from left to right, each column from top to bottom.
+    ColumnMajor,
+    /// Tiles of 8x8 screen chars from left to right and top to bottom, each tile in row-major order. The tiles at the
+    /// bottom of the screen are a single row high.
+    Tiles8x8,
+}
+
+impl ScanOrder {
+    pub const ALL: [ScanOrder; 3] = [ScanOrder::RowMajor, ScanOrder::ColumnMajor, ScanOrder::Tiles8x8];
+
+    /// Width and height in screen chars of the blocks that are visited in row-major order. The screen chars inside a
+    /// block are visited in row-major order as well.
+    pub fn block_size(&self) -> (usize, usize) {
+        match self {
+            ScanOrder::RowMajor => (SCREEN_COLUMNS, 1),
+            ScanOrder::ColumnMajor => (1, SCREEN_ROWS),
+            ScanOrder::Tiles8x8 => (8, 8),
+        }
+    }
+
+    /// Offset of each screen char in the order it is visited.
+    pub fn offsets(&self) -> Vec<usize> {
+        let (block_width, block_height) = self.block_size();
+        let mut result = Vec::with_capacity(SCREEN_COLUMNS * SCREEN_ROWS);
+        for block_row in (0..SCREEN_ROWS).step_by(block_height) {
+            for block_column in (0..SCREEN_COLUMNS).step_by(block_width) {
+                for row in block_row..(block_row + block_height).min(SCREEN_ROWS) {
+                    for column in block_column..block_column + block_width {
+                        result.push(row * SCREEN_COLUMNS + column);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Screen chars in the order they are visited.
+    pub fn gather(&self, screen_chars: &[u8]) -> Vec<u8> {
+        self.offsets().into_iter().map(|offset| screen_chars[offset]).collect()
+    }
+
+    /// Store screen chars that are in the order they are visited at their offsets in the screen.
+    pub fn scatter(&self, scanned_screen_chars: &[u8], screen_chars: &mut [u8]) {
+        for (offset, screen_char) in self.offsets().into_iter().zip(scanned_screen_chars) {
+            screen_chars[offset] = *screen_char;
+        }
+    }
+}
@@ -1,11 +1,14 @@
 use c64_assembler::builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder};
 use c64_colors::colors::Color;
 
-use crate::encoder::{writer::Writer, Encoder};
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
 
 use super::{modules::CurrentPtrMacros, DecoderModule};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct SetBorderColor {
     pub color: Color,
 }
@@ -20,6 +23,18 @@ impl Encoder for SetBorderColor {
     }
 }
 
+impl Decoder for SetBorderColor {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (color, encoded_data) = encoded_data.read::<u8>()?;
+        Ok((
+            SetBorderColor {
+                color: Color::from(color & 0x0F),
+            },
+            encoded_data,
+        ))
+    }
+}
+
 impl DecoderModule for SetBorderColor {
     fn module() -> c64_assembler::Module {
         ModuleBuilder::default()
@@ -1,11 +1,14 @@
 use c64_assembler::builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder};
 use c64_colors::colors::Color;
 
-use crate::encoder::{writer::Writer, Encoder};
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
 
 use super::{modules::CurrentPtrMacros, DecoderModule};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct SetPalette4 {
     pub palette: [Color; 4],
 }
@@ -22,6 +25,15 @@ impl Encoder for SetPalette4 {
     }
 }
 
+impl Decoder for SetPalette4 {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (b1, encoded_data) = encoded_data.read::<u8>()?;
+        let (b2, encoded_data) = encoded_data.read::<u8>()?;
+        let palette = [b1 & 0x0F, b1 >> 4, b2 & 0x0F, b2 >> 4].map(Color::from);
+        Ok((SetPalette4 { palette }, encoded_data))
+    }
+}
+
 impl DecoderModule for SetPalette4 {
     fn module() -> c64_assembler::Module {
         ModuleBuilder::default()
@@ -0,0 +1,66 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+use c64_colors::colors::Color;
+
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{modules::CurrentPtrMacros, DecoderModule};
+
+/// Set the colors of all 8 sprites.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SetSpriteColors {
+    pub colors: [Color; 8],
+}
+
+impl Encoder for SetSpriteColors {
+    fn byte_size(&self) -> usize {
+        8
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        let mut encoded_data = encoded_data;
+        for color in &self.colors {
+            encoded_data = encoded_data.add(&u8::from(*color));
+        }
+        encoded_data
+    }
+}
+
+impl Decoder for SetSpriteColors {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (colors, encoded_data) = encoded_data.read::<[u8; 8]>()?;
+        let colors = colors.map(|color| Color::from(color & 0x0F));
+        Ok((SetSpriteColors { colors }, encoded_data))
+    }
+}
+
+impl DecoderModule for SetSpriteColors {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("set_sprite_colors")
+            .function(
+                FunctionBuilder::default()
+                    .name("set_sprite_colors__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .inc_current_ptr(1)
+                            .ldy_imm(7)
+                            .label("set_sprite_colors__next")
+                            .lda_ind_y("CURRENT_PTR")
+                            .sta_addr_y("SPRITE_0_COLOR")
+                            .dey()
+                            .bpl_addr("set_sprite_colors__next")
+                            .inc_current_ptr(8)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
@@ -0,0 +1,93 @@
+//! Upload a sprite shape into the VIC bank.
+//!
+//! The sprite pointer selects the 64 byte block in the VIC bank the shape is written to. Only blocks that don't
+//! overlap the screen chars, the charset or the IO area can be used; see
+//! [super::modules::EngineConfig::is_valid_sprite_pointer].
+
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{modules::CurrentPtrMacros, DecoderModule};
+
+pub const SPRITE_DATA_SIZE: usize = 63;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SetSpriteData {
+    pub pointer: u8,
+    pub data: [u8; SPRITE_DATA_SIZE],
+}
+
+impl Encoder for SetSpriteData {
+    fn byte_size(&self) -> usize {
+        1 + SPRITE_DATA_SIZE
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        let mut encoded_data = encoded_data.add(&self.pointer);
+        for byte in &self.data {
+            encoded_data = encoded_data.add(byte);
+        }
+        encoded_data
+    }
+}
+
+impl Decoder for SetSpriteData {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (pointer, encoded_data) = encoded_data.read()?;
+        let (data, encoded_data) = encoded_data.read()?;
+        Ok((SetSpriteData { pointer, data }, encoded_data))
+    }
+}
+
+impl DecoderModule for SetSpriteData {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("set_sprite_data")
+            .function(
+                FunctionBuilder::default()
+                    .name("set_sprite_data__process")
+                    .doc(&[
+                        "Copy 63 bytes of sprite data to the block referenced by the sprite pointer.",
+                        "",
+                        "The block is located at 'VIC_BANK_START' + pointer * 64.",
+                    ])
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(1, "Load sprite pointer into the accumulator")
+                            .pha()
+                            .lsr_acc()
+                            .comment("Divide by 4 for the page.")
+                            .lsr_acc()
+                            .clc()
+                            .adc_imm_high("VIC_BANK_START")
+                            .sta_addr_offs("SPRITE_DATA_DST_PTR", 1)
+                            .pla()
+                            .and_imm(0b00000011)
+                            .comment("Multiply the lower 2 bits by 64 for the offset inside the page.")
+                            .lsr_acc()
+                            .ror_acc()
+                            .ror_acc()
+                            .sta_addr("SPRITE_DATA_DST_PTR")
+                            .inc_current_ptr(2)
+                            .ldy_imm(SPRITE_DATA_SIZE as u8 - 1)
+                            .label("set_sprite_data__next")
+                            .lda_ind_y("CURRENT_PTR")
+                            .sta_ind_y("SPRITE_DATA_DST_PTR")
+                            .dey()
+                            .bpl_addr("set_sprite_data__next")
+                            .inc_current_ptr(SPRITE_DATA_SIZE as u8)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
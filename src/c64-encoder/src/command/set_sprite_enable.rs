@@ -0,0 +1,55 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{modules::CurrentPtrMacros, DecoderModule};
+
+/// Enable sprites. Bit n of the mask enables sprite n.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SetSpriteEnable {
+    pub mask: u8,
+}
+
+impl Encoder for SetSpriteEnable {
+    fn byte_size(&self) -> usize {
+        size_of::<u8>()
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        encoded_data.add(&self.mask)
+    }
+}
+
+impl Decoder for SetSpriteEnable {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (mask, encoded_data) = encoded_data.read()?;
+        Ok((SetSpriteEnable { mask }, encoded_data))
+    }
+}
+
+impl DecoderModule for SetSpriteEnable {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("set_sprite_enable")
+            .function(
+                FunctionBuilder::default()
+                    .name("set_sprite_enable__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(1, "Load sprite enable mask into the accumulator.")
+                            .sta_addr("SPRITE_ENABLE")
+                            .inc_current_ptr(2)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
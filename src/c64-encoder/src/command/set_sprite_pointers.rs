@@ -0,0 +1,65 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{modules::CurrentPtrMacros, DecoderModule};
+
+/// Set the sprite pointers of all 8 sprites.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SetSpritePointers {
+    pub pointers: [u8; 8],
+}
+
+impl Encoder for SetSpritePointers {
+    fn byte_size(&self) -> usize {
+        8
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        let mut encoded_data = encoded_data;
+        for pointer in &self.pointers {
+            encoded_data = encoded_data.add(pointer);
+        }
+        encoded_data
+    }
+}
+
+impl Decoder for SetSpritePointers {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (pointers, encoded_data) = encoded_data.read()?;
+        Ok((SetSpritePointers { pointers }, encoded_data))
+    }
+}
+
+impl DecoderModule for SetSpritePointers {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("set_sprite_pointers")
+            .function(
+                FunctionBuilder::default()
+                    .name("set_sprite_pointers__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .inc_current_ptr(1)
+                            .ldy_imm(7)
+                            .label("set_sprite_pointers__next")
+                            .lda_ind_y("CURRENT_PTR")
+                            .label("set_sprite_pointers__store")
+                            .sta_addr_y("SPRITE_POINTERS")
+                            .dey()
+                            .bpl_addr("set_sprite_pointers__next")
+                            .inc_current_ptr(8)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
@@ -0,0 +1,101 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{modules::CurrentPtrMacros, DecoderModule};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpritePosition {
+    /// Horizontal position in the range 0..512. Bit 8 is stored in the X MSB register.
+    pub x: u16,
+    pub y: u8,
+}
+
+/// Set the positions of all 8 sprites.
+///
+/// Encoded in the same layout as the VIC2 registers: x low byte and y for each sprite followed by the X MSB
+/// register, so the decoder can copy the bytes directly.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SetSpritePositions {
+    pub positions: [SpritePosition; 8],
+}
+
+impl SetSpritePositions {
+    fn x_msb(&self) -> u8 {
+        self.positions
+            .iter()
+            .enumerate()
+            .filter(|(_sprite, position)| position.x & 0x100 != 0)
+            .map(|(sprite, _position)| 1 << sprite)
+            .sum()
+    }
+}
+
+impl Encoder for SetSpritePositions {
+    fn byte_size(&self) -> usize {
+        8 * 2 + 1
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        let mut encoded_data = encoded_data;
+        for position in &self.positions {
+            assert!(position.x < 512);
+            let x_low = (position.x & 0xFF) as u8;
+            encoded_data = encoded_data.add(&x_low).add(&position.y);
+        }
+        encoded_data.add(&self.x_msb())
+    }
+}
+
+impl Decoder for SetSpritePositions {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let mut encoded_data = encoded_data;
+        let mut positions = [SpritePosition::default(); 8];
+        for position in &mut positions {
+            let x_low;
+            (x_low, encoded_data) = encoded_data.read::<u8>()?;
+            (position.y, encoded_data) = encoded_data.read()?;
+            position.x = x_low as u16;
+        }
+        let (x_msb, encoded_data) = encoded_data.read::<u8>()?;
+        for (sprite, position) in positions.iter_mut().enumerate() {
+            if x_msb & (1 << sprite) != 0 {
+                position.x |= 0x100;
+            }
+        }
+        Ok((SetSpritePositions { positions }, encoded_data))
+    }
+}
+
+impl DecoderModule for SetSpritePositions {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("set_sprite_positions")
+            .function(
+                FunctionBuilder::default()
+                    .name("set_sprite_positions__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .inc_current_ptr(1)
+                            .ldy_imm(8 * 2)
+                            .comment("Copy positions and X MSB directly to the VIC2 registers")
+                            .label("set_sprite_positions__next")
+                            .lda_ind_y("CURRENT_PTR")
+                            .sta_addr_y("SPRITE_0_X")
+                            .dey()
+                            .bpl_addr("set_sprite_positions__next")
+                            .inc_current_ptr(8 * 2 + 1)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
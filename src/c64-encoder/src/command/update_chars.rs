@@ -1,13 +1,24 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
 use crate::{
-    charmap::encoding::encode_char,
+    charmap::encoding::{decode_char, encode_char},
+    decoder::{read_num_chars, reader::Reader, DecodeResult, Decoder},
     encoder::{writer::Writer, Encoder},
 };
 
-#[derive(Default, Debug, Clone)]
+use super::{
+    modules::{CharDecodeDstPtrMacros, CurrentPtrMacros},
+    DecoderModule,
+};
+
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct UpdateCharsU16Encoded {
     pub chars: Vec<UpdateChar>,
 }
-#[derive(Default, Debug, Copy, Clone)]
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
 pub struct UpdateChar {
     pub char: u8,
     pub data: u64,
@@ -27,12 +38,28 @@ impl Encoder for UpdateChar {
     }
 }
 
+impl Decoder for UpdateChar {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (char, encoded_data) = encoded_data.read()?;
+        let (encoded_char, encoded_data) = encoded_data.read()?;
+        Ok((
+            UpdateChar {
+                char,
+                data: decode_char(encoded_char),
+            },
+            encoded_data,
+        ))
+    }
+}
+
 impl Encoder for UpdateCharsU16Encoded {
     fn byte_size(&self) -> usize {
         1 + self.chars.len() * UpdateChar::default().byte_size()
     }
 
+    // NOTE: number of chars is stored in a single byte. Updating all 256 chars is stored as 0.
     fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        assert!(!self.chars.is_empty() && self.chars.len() <= 256);
         let mut encoded_data = encoded_data;
         let num_chars = self.chars.len() as u8;
         encoded_data = encoded_data.add(&num_chars);
@@ -43,3 +70,51 @@ impl Encoder for UpdateCharsU16Encoded {
         encoded_data
     }
 }
+
+impl Decoder for UpdateCharsU16Encoded {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (num_chars, mut encoded_data) = read_num_chars(encoded_data)?;
+        let mut chars = Vec::with_capacity(num_chars);
+        for _ in 0..num_chars {
+            let char;
+            (char, encoded_data) = encoded_data.read()?;
+            chars.push(char);
+        }
+        Ok((UpdateCharsU16Encoded { chars }, encoded_data))
+    }
+}
+
+impl DecoderModule for UpdateCharsU16Encoded {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("update_chars_u16")
+            .function(
+                FunctionBuilder::default()
+                    .name("update_chars_u16__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(1, "Load the number of characters into the accumulator.")
+                            .pha()
+                            .inc_current_ptr(2)
+                            .label("update_chars_u16__decode_next")
+                            .lda_current_ptr_offs(0, "Load the character to update into the accumulator.")
+                            .set_char_decode_dst_ptr_from_acc()
+                            .inc_current_ptr(1)
+                            .jsr_addr("char__decode_u16")
+                            .inc_current_ptr(2)
+                            .pla()
+                            .tax()
+                            .dex()
+                            .beq_addr("update_chars_u16__exit")
+                            .txa()
+                            .pha()
+                            .jmp_addr("update_chars_u16__decode_next")
+                            .label("update_chars_u16__exit")
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
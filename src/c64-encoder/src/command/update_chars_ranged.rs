@@ -4,18 +4,22 @@ use c64_assembler::{
 };
 
 use crate::{
-    charmap::encoding::encode_char,
+    charmap::encoding::{decode_char, encode_char},
+    decoder::{read_num_chars, reader::Reader, DecodeResult, Decoder},
     encoder::{writer::Writer, Encoder},
 };
 
-use super::{modules::CurrentPtrMacros, DecoderModule};
+use super::{
+    modules::{CharDecodeDstPtrMacros, CurrentPtrMacros},
+    DecoderModule,
+};
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct UpdateCharsRangedU16Encoded {
     pub offset: u8,
     pub chars: Vec<UpdateCharRanged>,
 }
-#[derive(Default, Debug, Copy, Clone)]
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
 pub struct UpdateCharRanged {
     pub data: u64,
 }
@@ -34,6 +38,18 @@ impl Encoder for UpdateCharRanged {
     }
 }
 
+impl Decoder for UpdateCharRanged {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (encoded_char, encoded_data) = encoded_data.read()?;
+        Ok((
+            UpdateCharRanged {
+                data: decode_char(encoded_char),
+            },
+            encoded_data,
+        ))
+    }
+}
+
 impl Encoder for UpdateCharsRangedU16Encoded {
     fn byte_size(&self) -> usize {
         1 + 1 + self.chars.len() * UpdateCharRanged::default().byte_size()
@@ -51,6 +67,20 @@ impl Encoder for UpdateCharsRangedU16Encoded {
     }
 }
 
+impl Decoder for UpdateCharsRangedU16Encoded {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (num_chars, encoded_data) = read_num_chars(encoded_data)?;
+        let (offset, mut encoded_data) = encoded_data.read()?;
+        let mut chars = Vec::with_capacity(num_chars);
+        for _ in 0..num_chars {
+            let char;
+            (char, encoded_data) = encoded_data.read()?;
+            chars.push(char);
+        }
+        Ok((UpdateCharsRangedU16Encoded { offset, chars }, encoded_data))
+    }
+}
+
 impl DecoderModule for UpdateCharsRangedU16Encoded {
     fn module() -> Module {
         ModuleBuilder::default()
@@ -61,22 +91,7 @@ impl DecoderModule for UpdateCharsRangedU16Encoded {
                     .instructions(
                         InstructionBuilder::default()
                             .lda_current_ptr_offs(2, "Load the starting character to update into the accumulator.")
-                            .pha()
-                            // TODO: ASL & LSR support other addressing modes as well. Need to check how they work.
-                            .asl_acc()
-                            .comment("Multiply accumulator with 8 by bit shifting 3 times")
-                            .asl_acc()
-                            .asl_acc()
-                            .sta_addr("CHAR_DECODE_DST_PTR")
-                            .pla()
-                            .lsr_acc()
-                            .comment("Shift 5 times for the page.")
-                            .lsr_acc()
-                            .lsr_acc()
-                            .lsr_acc()
-                            .lsr_acc()
-                            .adc_imm_high("CHARSET_PTR_PAGE0")
-                            .sta_addr_offs("CHAR_DECODE_DST_PTR", 1)
+                            .set_char_decode_dst_ptr_from_acc()
                             .lda_current_ptr_offs(1, "Load the number of characters into the accumulator.")
                             .pha()
                             .inc_current_ptr(3)
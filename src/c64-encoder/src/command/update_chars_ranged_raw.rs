@@ -0,0 +1,103 @@
+//! Update a range of chars in the charset using all 8 bytes of each char.
+//!
+//! Used for chars that cannot be represented losslessly by the u16 encoding.
+
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    decoder::{read_num_chars, reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{
+    modules::{CharDecodeDstPtrMacros, CurrentPtrMacros},
+    update_chars_ranged::UpdateCharRanged,
+    DecoderModule,
+};
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct UpdateCharsRangedRaw {
+    pub offset: u8,
+    pub chars: Vec<UpdateCharRanged>,
+}
+
+impl Encoder for UpdateCharsRangedRaw {
+    fn byte_size(&self) -> usize {
+        1 + 1 + self.chars.len() * 8
+    }
+
+    // NOTE: number of chars is stored in a single byte. Updating all 256 chars is stored as 0.
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        assert!(!self.chars.is_empty() && self.offset as usize + self.chars.len() <= 256);
+        let mut encoded_data = encoded_data;
+        let num_chars = self.chars.len() as u8;
+        encoded_data = encoded_data.add(&num_chars).add(&self.offset);
+        for char in &self.chars {
+            for byte in char.data.to_be_bytes() {
+                encoded_data = encoded_data.add(&byte);
+            }
+        }
+
+        encoded_data
+    }
+}
+
+impl Decoder for UpdateCharsRangedRaw {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (num_chars, encoded_data) = read_num_chars(encoded_data)?;
+        let (offset, mut encoded_data) = encoded_data.read()?;
+        let mut chars = Vec::with_capacity(num_chars);
+        for _ in 0..num_chars {
+            let data;
+            (data, encoded_data) = encoded_data.read()?;
+            chars.push(UpdateCharRanged {
+                data: u64::from_be_bytes(data),
+            });
+        }
+        Ok((UpdateCharsRangedRaw { offset, chars }, encoded_data))
+    }
+}
+
+impl DecoderModule for UpdateCharsRangedRaw {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("update_chars_ranged_raw")
+            .function(
+                FunctionBuilder::default()
+                    .name("update_chars_ranged_raw__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(2, "Load the starting character to update into the accumulator.")
+                            .set_char_decode_dst_ptr_from_acc()
+                            .lda_current_ptr_offs(1, "Load the number of characters into the accumulator.")
+                            .pha()
+                            .inc_current_ptr(3)
+                            .label("update_chars_ranged_raw__copy_next")
+                            .jsr_addr("char__copy_raw")
+                            .inc_current_ptr(8)
+                            .pla()
+                            .tax()
+                            .dex()
+                            .beq_addr("update_chars_ranged_raw__exit")
+                            .txa()
+                            .pha()
+                            .clc()
+                            .lda_imm(8)
+                            .adc_addr("CHAR_DECODE_DST_PTR")
+                            .sta_addr("CHAR_DECODE_DST_PTR")
+                            .lda_imm(0)
+                            .adc_addr_offs("CHAR_DECODE_DST_PTR", 1)
+                            .sta_addr_offs("CHAR_DECODE_DST_PTR", 1)
+                            .jmp_addr("update_chars_ranged_raw__copy_next")
+                            .label("update_chars_ranged_raw__exit")
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
@@ -0,0 +1,98 @@
+//! Update chars in the charset using all 8 bytes of each char.
+//!
+//! Used for chars that cannot be represented losslessly by the u16 encoding.
+
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    decoder::{read_num_chars, reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{
+    modules::{CharDecodeDstPtrMacros, CurrentPtrMacros},
+    update_chars::UpdateChar,
+    DecoderModule,
+};
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct UpdateCharsRaw {
+    pub chars: Vec<UpdateChar>,
+}
+
+impl Encoder for UpdateCharsRaw {
+    fn byte_size(&self) -> usize {
+        1 + self.chars.len() * (1 + 8)
+    }
+
+    // NOTE: number of chars is stored in a single byte. Updating all 256 chars is stored as 0.
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        assert!(!self.chars.is_empty() && self.chars.len() <= 256);
+        let mut encoded_data = encoded_data;
+        let num_chars = self.chars.len() as u8;
+        encoded_data = encoded_data.add(&num_chars);
+        for char in &self.chars {
+            encoded_data = encoded_data.add(&char.char);
+            for byte in char.data.to_be_bytes() {
+                encoded_data = encoded_data.add(&byte);
+            }
+        }
+
+        encoded_data
+    }
+}
+
+impl Decoder for UpdateCharsRaw {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (num_chars, mut encoded_data) = read_num_chars(encoded_data)?;
+        let mut chars = Vec::with_capacity(num_chars);
+        for _ in 0..num_chars {
+            let (char, data);
+            (char, encoded_data) = encoded_data.read()?;
+            (data, encoded_data) = encoded_data.read()?;
+            chars.push(UpdateChar {
+                char,
+                data: u64::from_be_bytes(data),
+            });
+        }
+        Ok((UpdateCharsRaw { chars }, encoded_data))
+    }
+}
+
+impl DecoderModule for UpdateCharsRaw {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("update_chars_raw")
+            .function(
+                FunctionBuilder::default()
+                    .name("update_chars_raw__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(1, "Load the number of characters into the accumulator.")
+                            .pha()
+                            .inc_current_ptr(2)
+                            .label("update_chars_raw__copy_next")
+                            .lda_current_ptr_offs(0, "Load the character to update into the accumulator.")
+                            .set_char_decode_dst_ptr_from_acc()
+                            .inc_current_ptr(1)
+                            .jsr_addr("char__copy_raw")
+                            .inc_current_ptr(8)
+                            .pla()
+                            .tax()
+                            .dex()
+                            .beq_addr("update_chars_raw__exit")
+                            .txa()
+                            .pha()
+                            .jmp_addr("update_chars_raw__copy_next")
+                            .label("update_chars_raw__exit")
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
@@ -7,16 +7,21 @@
 //! - Auto increment the next n chars. Will be followed by a single byte, but each next byte will be incremented
 //!
 
+use std::collections::VecDeque;
+
 use c64_assembler::{
     builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
     Module,
 };
 
-use crate::encoder::{writer::Writer, Encoder};
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
 
 use super::{modules::CurrentPtrMacros, DecoderModule};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum RLECommand {
     UpdateWithSingleValue(u8),
     UpdateValues(Vec<u8>),
@@ -31,14 +36,14 @@ pub const RLE_MASK_AUTO_INCREMENT: u8 = 0b11000000;
 pub const RLE_MASK_BITS: u8 = 0b11000000;
 pub const RLE_MASK_FRAMES: u8 = 0b00111111;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RLEPacket {
     // Number of chars this package covers. May not go over 64.
     pub num_screen_chars: u8,
     pub command: RLECommand,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct UpdateScreenCharsRLE {
     pub rle_packets: Vec<RLEPacket>,
 }
@@ -139,20 +144,20 @@ impl UpdateScreenCharsRLE {
                         result.rle_packets.append(&mut packets);
                     }
                     RLE_MASK_AUTO_INCREMENT => {
-                        let mut screen_char = to_screen_chars[offset];
                         let mut packets = vec![RLEPacket {
                             num_screen_chars: 0,
-                            command: RLECommand::AutoIncrement(screen_char),
+                            command: RLECommand::AutoIncrement(to_screen_chars[offset]),
                         }];
+                        let mut next_screen_char = Some(to_screen_chars[offset]);
                         for add_offset in 0..num_screen_chars_in_packet {
                             let screen_char_at_offset = to_screen_chars[offset + add_offset as usize];
-                            if screen_char_at_offset != screen_char + add_offset {
+                            if Some(screen_char_at_offset) != next_screen_char {
                                 packets.push(RLEPacket {
                                     num_screen_chars: 0,
                                     command: RLECommand::AutoIncrement(screen_char_at_offset),
                                 });
-                                screen_char = screen_char_at_offset;
                             }
+                            next_screen_char = screen_char_at_offset.checked_add(1);
 
                             let packet = packets.last_mut().unwrap();
                             packet.num_screen_chars += 1;
@@ -180,24 +185,240 @@ impl UpdateScreenCharsRLE {
 
         result
     }
+
+    /// Transition with the smallest byte size, found using dynamic programming over the screen chars.
+    ///
+    /// [UpdateScreenCharsRLE::transition] classifies each screen char by looking at its neighbours. This variant
+    /// considers every packet that can start at each screen char, so for example an unchanged screen char between two
+    /// runs of changed screen chars is rewritten when that is smaller than skipping it. When several transitions have
+    /// the same byte size the one with the fewest packets is used.
+    pub fn optimal_transition(from_screen_chars: &[u8], to_screen_chars: &[u8]) -> UpdateScreenCharsRLE {
+        let result = UpdateScreenCharsRLE {
+            rle_packets: optimal_rle_packets(
+                from_screen_chars,
+                to_screen_chars,
+                RLE_MASK_FRAMES as usize,
+                packet_byte_size,
+            ),
+        };
+        debug_assert!(validate_decode(from_screen_chars, to_screen_chars, &result.rle_packets));
+        result
+    }
+}
+
+/// Byte size of an encoded packet with the given packet type covering the given number of screen chars.
+pub(crate) fn packet_byte_size(rle_command_mask: u8, num_screen_chars: usize) -> usize {
+    match rle_command_mask {
+        RLE_MASK_UPDATE_VALUES => 1 + num_screen_chars,
+        RLE_MASK_SKIP_VALUES => 1,
+        _ => 2,
+    }
+}
+
+/// Packets with the smallest byte size that update the screen chars, found using dynamic programming.
+///
+/// Packets cover at most `max_num_screen_chars` screen chars. `packet_byte_size` returns the byte size of a packet
+/// with the given packet type covering the given number of screen chars.
+pub(crate) fn optimal_rle_packets(
+    from_screen_chars: &[u8],
+    to_screen_chars: &[u8],
+    max_num_screen_chars: usize,
+    packet_byte_size: fn(u8, usize) -> usize,
+) -> Vec<RLEPacket> {
+    let num_screen_chars = to_screen_chars.len();
+    // First offset of the run each screen char is part of, for each packet type. A packet of the type can start at any
+    // offset of the run up to the screen char. A screen char that changes isn't part of a run of unchanged chars, so
+    // the run starts after it.
+    let mut run_starts = [
+        RLE_MASK_SKIP_VALUES,
+        RLE_MASK_UPDATE_WITH_SINGLE_VALUE,
+        RLE_MASK_AUTO_INCREMENT,
+    ]
+    .map(|rle_command_mask| (rle_command_mask, vec![0; num_screen_chars]));
+    for offset in 0..num_screen_chars {
+        let screen_char = to_screen_chars[offset];
+        let previous_screen_char = offset.checked_sub(1).map(|previous| to_screen_chars[previous]);
+        for (rle_command_mask, run_start) in &mut run_starts {
+            let continues_run = match *rle_command_mask {
+                RLE_MASK_SKIP_VALUES => from_screen_chars[offset] == screen_char,
+                RLE_MASK_UPDATE_WITH_SINGLE_VALUE => previous_screen_char == Some(screen_char),
+                _ => previous_screen_char.and_then(|previous| previous.checked_add(1)) == Some(screen_char),
+            };
+            run_start[offset] = if continues_run {
+                offset.checked_sub(1).map_or(offset, |previous| run_start[previous])
+            } else if *rle_command_mask == RLE_MASK_SKIP_VALUES {
+                offset + 1
+            } else {
+                offset
+            };
+        }
+    }
+    let [(_, skip_run_starts), ..] = &run_starts;
+
+    // For each packet type and range of lengths, the possible starts of a packet ending at the current offset, see
+    // [PacketStarts].
+    let mut packet_starts = Vec::new();
+    for (rle_command_mask, run_start) in &run_starts {
+        let ranges = byte_size_ranges(max_num_screen_chars, |length| {
+            packet_byte_size(*rle_command_mask, length)
+        });
+        for (min_length, max_length, byte_size) in ranges {
+            packet_starts.push(PacketStarts::new(
+                *rle_command_mask,
+                min_length,
+                max_length,
+                byte_size,
+                Some(run_start),
+            ));
+        }
+    }
+    let values_ranges = byte_size_ranges(max_num_screen_chars, |length| {
+        packet_byte_size(RLE_MASK_UPDATE_VALUES, length) - length
+    });
+    for (min_length, max_length, header_byte_size) in values_ranges {
+        packet_starts.push(PacketStarts::new(
+            RLE_MASK_UPDATE_VALUES,
+            min_length,
+            max_length,
+            header_byte_size,
+            None,
+        ));
+    }
+
+    // Smallest (byte size, number of packets) that updates the screen chars before each offset, and the start and
+    // packet type of the last packet of that update.
+    let mut costs = vec![(0, 0); num_screen_chars + 1];
+    let mut last_packets = vec![(0, RLE_MASK_SKIP_VALUES); num_screen_chars + 1];
+    for end in 1..=num_screen_chars {
+        costs[end] = (usize::MAX, usize::MAX);
+        for packet_starts in &mut packet_starts {
+            if let Some((cost, start)) = packet_starts.advance(end, &costs) {
+                if cost < costs[end] {
+                    costs[end] = cost;
+                    last_packets[end] = (start, packet_starts.rle_command_mask);
+                }
+            }
+        }
+    }
+
+    // Trailing unchanged screen chars don't need a packet.
+    let first_trailing = skip_run_starts.last().copied().unwrap_or(0);
+    let mut offset = (first_trailing..=num_screen_chars)
+        .min_by_key(|end| costs[*end])
+        .unwrap();
+
+    let mut result = Vec::new();
+    while offset > 0 {
+        let (start, rle_command_mask) = last_packets[offset];
+        let command = match rle_command_mask {
+            RLE_MASK_SKIP_VALUES => RLECommand::SkipValues,
+            RLE_MASK_UPDATE_WITH_SINGLE_VALUE => RLECommand::UpdateWithSingleValue(to_screen_chars[start]),
+            RLE_MASK_AUTO_INCREMENT => RLECommand::AutoIncrement(to_screen_chars[start]),
+            _ => RLECommand::UpdateValues(to_screen_chars[start..offset].to_vec()),
+        };
+        result.push(RLEPacket {
+            num_screen_chars: (offset - start) as u8,
+            command,
+        });
+        offset = start;
+    }
+    result.reverse();
+    result
+}
+
+/// Possible starts of a packet of one type with a length in a range where the packet has the same byte size, for
+/// packets ending at increasing offsets.
+///
+/// The starts are kept in a monotonic queue with the start leading to the smallest cost in front. As the end advances,
+/// starts are added when the packet gets long enough and removed when the packet gets too long or the start isn't part
+/// of the run ending at the end anymore.
+struct PacketStarts<'a> {
+    rle_command_mask: u8,
+    min_length: usize,
+    max_length: usize,
+    /// Byte size of the packet, or of its header for packets of values.
+    byte_size: usize,
+    /// Start of the run of each screen char, `None` for packets of values.
+    run_starts: Option<&'a [usize]>,
+    /// Starts with their cost. For packets of values, the cost doesn't include the values before the start.
+    starts: VecDeque<((usize, usize), usize)>,
+}
+
+impl<'a> PacketStarts<'a> {
+    fn new(
+        rle_command_mask: u8,
+        min_length: usize,
+        max_length: usize,
+        byte_size: usize,
+        run_starts: Option<&'a [usize]>,
+    ) -> Self {
+        Self {
+            rle_command_mask,
+            min_length,
+            max_length,
+            byte_size,
+            run_starts,
+            starts: VecDeque::new(),
+        }
+    }
+
+    /// Cost of the smallest update of the screen chars before `end` ending with this packet, and the start of the
+    /// packet. `costs` must contain the final costs before `end`.
+    fn advance(&mut self, end: usize, costs: &[(usize, usize)]) -> Option<((usize, usize), usize)> {
+        let num_screen_chars = costs.len();
+        if let Some(start) = end.checked_sub(self.min_length) {
+            let (byte_size, num_packets) = costs[start];
+            let cost = match self.run_starts {
+                Some(_) => (byte_size, num_packets),
+                None => (byte_size + num_screen_chars - start, num_packets),
+            };
+            while self.starts.back().is_some_and(|(back_cost, _)| *back_cost >= cost) {
+                self.starts.pop_back();
+            }
+            self.starts.push_back((cost, start));
+        }
+        let first_start = end.saturating_sub(self.max_length);
+        let first_start = match self.run_starts {
+            Some(run_starts) => first_start.max(run_starts[end - 1]),
+            None => first_start,
+        };
+        while self.starts.front().is_some_and(|(_, start)| *start < first_start) {
+            self.starts.pop_front();
+        }
+
+        let ((byte_size, num_packets), start) = self.starts.front()?;
+        let byte_size = match self.run_starts {
+            Some(_) => byte_size + self.byte_size,
+            None => byte_size + end + self.byte_size - num_screen_chars,
+        };
+        Some(((byte_size, num_packets + 1), *start))
+    }
+}
+
+/// Ranges of packet lengths up to the given maximum with the same byte size, as (minimum length, maximum length, byte
+/// size).
+fn byte_size_ranges(max_num_screen_chars: usize, byte_size: impl Fn(usize) -> usize) -> Vec<(usize, usize, usize)> {
+    let mut result: Vec<(usize, usize, usize)> = Vec::new();
+    for num_screen_chars in 1..=max_num_screen_chars {
+        let byte_size = byte_size(num_screen_chars);
+        match result.last_mut() {
+            Some((_, max_length, last_byte_size)) if *last_byte_size == byte_size => *max_length = num_screen_chars,
+            _ => result.push((num_screen_chars, num_screen_chars, byte_size)),
+        }
+    }
+    result
 }
 
 fn validate(expected: &[u8], source: &UpdateScreenCharsRLE) -> bool {
     let mut decoded = vec![];
     for packet in &source.rle_packets {
-        let mask = match &packet.command {
-            RLECommand::UpdateWithSingleValue(_) => RLE_MASK_UPDATE_WITH_SINGLE_VALUE,
-            RLECommand::UpdateValues(_items) => RLE_MASK_UPDATE_VALUES,
-            RLECommand::SkipValues => RLE_MASK_SKIP_VALUES,
-            RLECommand::AutoIncrement(_) => RLE_MASK_AUTO_INCREMENT,
-        };
-        decoded.extend(vec![mask; packet.num_screen_chars as usize]);
+        decoded.extend(vec![packet.command.mask(); packet.num_screen_chars as usize]);
     }
     assert_eq!(expected, decoded);
     true
 }
 
-fn validate_decode(from_screen: &[u8], to_screen: &[u8], packets: &[RLEPacket]) -> bool {
+pub(crate) fn validate_decode(from_screen: &[u8], to_screen: &[u8], packets: &[RLEPacket]) -> bool {
     let mut decoded = Vec::from(from_screen);
     let mut packet_per_offset = Vec::<RLEPacket>::new();
     for packet in packets {
@@ -206,12 +427,6 @@ fn validate_decode(from_screen: &[u8], to_screen: &[u8], packets: &[RLEPacket])
         }
     }
 
-    let mut offset = 0;
-    for packet in packets {
-        println!("- {}: {:?}", offset, packet);
-        offset += packet.num_screen_chars as usize;
-    }
-
     let mut offset = 0;
     for packet in packets {
         match &packet.command {
@@ -253,42 +468,94 @@ fn validate_decode(from_screen: &[u8], to_screen: &[u8], packets: &[RLEPacket])
 
 impl Encoder for RLEPacket {
     fn byte_size(&self) -> usize {
-        let result = match &self.command {
-            RLECommand::UpdateWithSingleValue(_) => 1,
-            RLECommand::UpdateValues(items) => items.len(),
-            RLECommand::SkipValues => 0,
-            RLECommand::AutoIncrement(_) => 1,
-        } + 1;
-        result
+        packet_byte_size(self.command.mask(), self.num_screen_chars as usize)
     }
 
     fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
         assert!(self.num_screen_chars < 64);
+        let encoded_command = self.num_screen_chars | self.command.mask();
+        let encoded_data = encoded_data.add(&encoded_command);
+        self.command.encode_data(encoded_data)
+    }
+}
+
+impl Decoder for RLEPacket {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (header, encoded_data) = encoded_data.read::<u8>()?;
+        let num_screen_chars = header & RLE_MASK_FRAMES;
+        let (command, encoded_data) = RLECommand::decode_data(header & RLE_MASK_BITS, num_screen_chars, encoded_data)?;
+        Ok((
+            RLEPacket {
+                num_screen_chars,
+                command,
+            },
+            encoded_data,
+        ))
+    }
+}
+
+impl RLECommand {
+    /// Packet type stored in the header of the packet.
+    pub fn mask(&self) -> u8 {
+        match self {
+            RLECommand::UpdateWithSingleValue(_) => RLE_MASK_UPDATE_WITH_SINGLE_VALUE,
+            RLECommand::UpdateValues(_) => RLE_MASK_UPDATE_VALUES,
+            RLECommand::SkipValues => RLE_MASK_SKIP_VALUES,
+            RLECommand::AutoIncrement(_) => RLE_MASK_AUTO_INCREMENT,
+        }
+    }
+
+    /// Encode the data that follows the header of the packet.
+    pub(crate) fn encode_data<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
         let mut encoded_data = encoded_data;
-        match &self.command {
+        match self {
             RLECommand::UpdateWithSingleValue(value) => {
-                let encoded_command = self.num_screen_chars | RLE_MASK_UPDATE_WITH_SINGLE_VALUE;
-                encoded_data = encoded_data.add(&encoded_command).add(value);
+                encoded_data = encoded_data.add(value);
             }
             RLECommand::UpdateValues(values) => {
-                let encoded_command = self.num_screen_chars | RLE_MASK_UPDATE_VALUES;
-                encoded_data = encoded_data.add(&encoded_command);
                 for value in values {
                     encoded_data = encoded_data.add(value);
                 }
             }
-            RLECommand::SkipValues => {
-                let encoded_command = self.num_screen_chars | RLE_MASK_SKIP_VALUES;
-                encoded_data = encoded_data.add(&encoded_command);
-            }
+            RLECommand::SkipValues => {}
             RLECommand::AutoIncrement(start_value) => {
-                let encoded_command = self.num_screen_chars | RLE_MASK_AUTO_INCREMENT;
-                encoded_data = encoded_data.add(&encoded_command).add(start_value);
+                encoded_data = encoded_data.add(start_value);
             }
         }
-
         encoded_data
     }
+
+    /// Decode the data that follows the header of a packet with the given packet type.
+    pub(crate) fn decode_data(
+        rle_command_mask: u8,
+        num_screen_chars: u8,
+        encoded_data: &[u8],
+    ) -> DecodeResult<(RLECommand, &[u8])> {
+        let mut encoded_data = encoded_data;
+        let command = match rle_command_mask {
+            RLE_MASK_UPDATE_WITH_SINGLE_VALUE => {
+                let value;
+                (value, encoded_data) = encoded_data.read()?;
+                RLECommand::UpdateWithSingleValue(value)
+            }
+            RLE_MASK_UPDATE_VALUES => {
+                let mut values = Vec::with_capacity(num_screen_chars as usize);
+                for _ in 0..num_screen_chars {
+                    let value;
+                    (value, encoded_data) = encoded_data.read()?;
+                    values.push(value);
+                }
+                RLECommand::UpdateValues(values)
+            }
+            RLE_MASK_SKIP_VALUES => RLECommand::SkipValues,
+            _ => {
+                let start_value;
+                (start_value, encoded_data) = encoded_data.read()?;
+                RLECommand::AutoIncrement(start_value)
+            }
+        };
+        Ok((command, encoded_data))
+    }
 }
 
 impl Encoder for UpdateScreenCharsRLE {
@@ -308,151 +575,183 @@ impl Encoder for UpdateScreenCharsRLE {
     }
 }
 
+impl Decoder for UpdateScreenCharsRLE {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (rle_packets, encoded_data) = read_rle_packets(encoded_data)?;
+        Ok((UpdateScreenCharsRLE { rle_packets }, encoded_data))
+    }
+}
+
+/// Read RLE packets prefixed with the number of packets.
+pub(crate) fn read_rle_packets(encoded_data: &[u8]) -> DecodeResult<(Vec<RLEPacket>, &[u8])> {
+    let (num_rle_packets, mut encoded_data) = encoded_data.read::<u8>()?;
+    let mut rle_packets = Vec::with_capacity(num_rle_packets as usize);
+    for _ in 0..num_rle_packets {
+        let rle_packet;
+        (rle_packet, encoded_data) = encoded_data.read()?;
+        rle_packets.push(rle_packet);
+    }
+    Ok((rle_packets, encoded_data))
+}
+
 impl DecoderModule for UpdateScreenCharsRLE {
     fn module() -> Module {
-        ModuleBuilder::default()
-            .name("update_screen_chars_rle")
-            .function(
-                FunctionBuilder::default()
-                    .name("update_screen_chars_rle__process")
-                    .instructions(
-                        InstructionBuilder::default()
-                            .lda_current_ptr_offs(1, "Load number of RLE packets in the accumulator")
-                            .pha()
-                            .inc_current_ptr(2)
-                            .jsr_addr("engine__screen_char_ptr__reset")
-                            .label("update_screen_chars_rle__next_packet")
-                            .lda_current_ptr_offs(0, "Load the RLEPacket header byte into the accumulator.")
-                            .pha()
-                            .and_imm(RLE_MASK_FRAMES)
-                            .comment("Extract the number of chars the packet covers and store in X")
-                            .tax()
-                            .pla()
-                            .and_imm(RLE_MASK_BITS)
-                            .comment("Accumulator contains the packet type")
-                            .label("update_screen_chars_rle__switch_single")
-                            .cmp_imm(RLE_MASK_UPDATE_WITH_SINGLE_VALUE)
-                            .bne_addr("update_screen_chars_rle__switch_values")
-                            .jmp_addr("update_screen_chars_rle__single")
-                            .label("update_screen_chars_rle__switch_values")
-                            .cmp_imm(RLE_MASK_UPDATE_VALUES)
-                            .bne_addr("update_screen_chars_rle__switch_skip")
-                            .jmp_addr("update_screen_chars_rle__values")
-                            .label("update_screen_chars_rle__switch_skip")
-                            .cmp_imm(RLE_MASK_SKIP_VALUES)
-                            .bne_addr("update_screen_chars_rle__switch_auto")
-                            .jmp_addr("update_screen_chars_rle__skip")
-                            .label("update_screen_chars_rle__switch_auto")
-                            .jmp_addr("update_screen_chars_rle__auto")
-                            .label("update_screen_chars_rle__packet_done")
-                            .pla()
-                            .tax()
-                            .dex()
-                            .beq_addr("update_screen_chars_rle__exit")
-                            .txa()
-                            .pha()
-                            .jmp_addr("update_screen_chars_rle__next_packet")
-                            .label("update_screen_chars_rle__exit")
-                            .rts()
-                            .build(),
-                    )
-                    .build(),
-            )
-            .function(
-                FunctionBuilder::default()
-                    .name("update_screen_chars_rle__values")
-                    .instructions(
-                        InstructionBuilder::default()
-                            .txa()
-                            .pha()
-                            .pha()
-                            .tay()
-                            .label("update_screen_chars_rle__values_next")
-                            .lda_ind_y("CURRENT_PTR")
-                            .dey()
-                            .bmi_addr("update_screen_chars_rle__values_done")
-                            .sta_ind_y("SCREEN_CHAR_PTR")
-                            .jmp_addr("update_screen_chars_rle__values_next")
-                            .label("update_screen_chars_rle__values_done")
-                            .pla()
-                            .jsr_addr("engine__screen_char_ptr__advance")
-                            .pla()
-                            .tax()
-                            .inx()
-                            .txa()
-                            .jsr_addr("engine__current_ptr__advance")
-                            .jmp_addr("update_screen_chars_rle__packet_done")
-                            .build(),
-                    )
-                    .build(),
-            )
-            .function(
-                FunctionBuilder::default()
-                    .name("update_screen_chars_rle__skip")
-                    .instructions(
-                        InstructionBuilder::default()
-                            .txa()
-                            .jsr_addr("engine__screen_char_ptr__advance")
-                            .inc_current_ptr(1)
-                            .jmp_addr("update_screen_chars_rle__packet_done")
-                            .build(),
-                    )
-                    .build(),
-            )
-            .function(
-                FunctionBuilder::default()
-                    .name("update_screen_chars_rle__auto")
-                    .instructions(
-                        InstructionBuilder::default()
-                            .lda_current_ptr_offs(1, "Load the single value that will be copied")
-                            .sta_addr("SCRATCH_SPACE_00")
-                            .txa()
-                            .pha()
-                            .tay()
-                            .adc_addr("SCRATCH_SPACE_00")
-                            .tax()
-                            .dex()
-                            .label("update_screen_chars_rle__auto_next")
-                            .dex()
-                            .dey()
-                            .bmi_addr("update_screen_chars_rle__auto_done")
-                            .txa()
-                            .sta_ind_y("SCREEN_CHAR_PTR")
-                            .jmp_addr("update_screen_chars_rle__auto_next")
-                            .label("update_screen_chars_rle__auto_done")
-                            .inc_current_ptr(2)
-                            .pla()
-                            .jsr_addr("engine__screen_char_ptr__advance")
-                            .jmp_addr("update_screen_chars_rle__packet_done")
-                            .build(),
-                    )
-                    .build(),
-            )
-            .function(
-                FunctionBuilder::default()
-                    .name("update_screen_chars_rle__single")
-                    .instructions(
-                        InstructionBuilder::default()
-                            .lda_current_ptr_offs(1, "Load the single value that will be copied")
-                            .sta_addr("SCRATCH_SPACE_00")
-                            .txa()
-                            .pha()
-                            .tay()
-                            .lda_addr("SCRATCH_SPACE_00")
-                            .label("update_screen_chars_rle__single_next")
-                            .dey()
-                            .bmi_addr("update_screen_chars_rle__single_done")
-                            .sta_ind_y("SCREEN_CHAR_PTR")
-                            .jmp_addr("update_screen_chars_rle__single_next")
-                            .label("update_screen_chars_rle__single_done")
-                            .inc_current_ptr(2)
-                            .pla()
-                            .jsr_addr("engine__screen_char_ptr__advance")
-                            .jmp_addr("update_screen_chars_rle__packet_done")
-                            .build(),
-                    )
-                    .build(),
-            )
-            .build()
+        rle_decoder_module("update_screen_chars_rle", "SCREEN_CHARS_PAGE0")
     }
 }
+
+/// Build the decoder for RLE packets that are written to the memory starting at `destination`.
+///
+/// All labels are prefixed with `name`, the entry point is `{name}__process`. `SCREEN_CHAR_PTR` is used to keep track
+/// of the current position in the destination, the instruction loading its high byte is labeled
+/// `{name}__destination_page`.
+pub(crate) fn rle_decoder_module(name: &str, destination: &str) -> Module {
+    ModuleBuilder::default()
+        .name(name)
+        .function(
+            FunctionBuilder::default()
+                .name(format!("{name}__process").as_str())
+                .instructions(
+                    InstructionBuilder::default()
+                        .lda_current_ptr_offs(1, "Load number of RLE packets in the accumulator")
+                        .pha()
+                        .inc_current_ptr(2)
+                        .lda_imm_low(destination)
+                        .sta_addr("SCREEN_CHAR_PTR")
+                        .label(format!("{name}__destination_page").as_str())
+                        .lda_imm_high(destination)
+                        .sta_addr_offs("SCREEN_CHAR_PTR", 1)
+                        .label(format!("{name}__next_packet").as_str())
+                        .lda_current_ptr_offs(0, "Load the RLEPacket header byte into the accumulator.")
+                        .pha()
+                        .and_imm(RLE_MASK_FRAMES)
+                        .comment("Extract the number of chars the packet covers and store in X")
+                        .tax()
+                        .pla()
+                        .and_imm(RLE_MASK_BITS)
+                        .comment("Accumulator contains the packet type")
+                        .label(format!("{name}__switch_single").as_str())
+                        .cmp_imm(RLE_MASK_UPDATE_WITH_SINGLE_VALUE)
+                        .bne_addr(format!("{name}__switch_values").as_str())
+                        .jmp_addr(format!("{name}__single").as_str())
+                        .label(format!("{name}__switch_values").as_str())
+                        .cmp_imm(RLE_MASK_UPDATE_VALUES)
+                        .bne_addr(format!("{name}__switch_skip").as_str())
+                        .jmp_addr(format!("{name}__values").as_str())
+                        .label(format!("{name}__switch_skip").as_str())
+                        .cmp_imm(RLE_MASK_SKIP_VALUES)
+                        .bne_addr(format!("{name}__switch_auto").as_str())
+                        .jmp_addr(format!("{name}__skip").as_str())
+                        .label(format!("{name}__switch_auto").as_str())
+                        .jmp_addr(format!("{name}__auto").as_str())
+                        .label(format!("{name}__packet_done").as_str())
+                        .pla()
+                        .tax()
+                        .dex()
+                        .beq_addr(format!("{name}__exit").as_str())
+                        .txa()
+                        .pha()
+                        .jmp_addr(format!("{name}__next_packet").as_str())
+                        .label(format!("{name}__exit").as_str())
+                        .rts()
+                        .build(),
+                )
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name(format!("{name}__values").as_str())
+                .instructions(
+                    InstructionBuilder::default()
+                        .txa()
+                        .pha()
+                        .pha()
+                        .tay()
+                        .label(format!("{name}__values_next").as_str())
+                        .lda_ind_y("CURRENT_PTR")
+                        .dey()
+                        .bmi_addr(format!("{name}__values_done").as_str())
+                        .sta_ind_y("SCREEN_CHAR_PTR")
+                        .jmp_addr(format!("{name}__values_next").as_str())
+                        .label(format!("{name}__values_done").as_str())
+                        .pla()
+                        .jsr_addr("engine__screen_char_ptr__advance")
+                        .pla()
+                        .tax()
+                        .inx()
+                        .txa()
+                        .jsr_addr("engine__current_ptr__advance")
+                        .jmp_addr(format!("{name}__packet_done").as_str())
+                        .build(),
+                )
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name(format!("{name}__skip").as_str())
+                .instructions(
+                    InstructionBuilder::default()
+                        .txa()
+                        .jsr_addr("engine__screen_char_ptr__advance")
+                        .inc_current_ptr(1)
+                        .jmp_addr(format!("{name}__packet_done").as_str())
+                        .build(),
+                )
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name(format!("{name}__auto").as_str())
+                .instructions(
+                    InstructionBuilder::default()
+                        .lda_current_ptr_offs(1, "Load the single value that will be copied")
+                        .sta_addr("SCRATCH_SPACE_00")
+                        .txa()
+                        .pha()
+                        .tay()
+                        .adc_addr("SCRATCH_SPACE_00")
+                        .tax()
+                        .dex()
+                        .label(format!("{name}__auto_next").as_str())
+                        .dex()
+                        .dey()
+                        .bmi_addr(format!("{name}__auto_done").as_str())
+                        .txa()
+                        .sta_ind_y("SCREEN_CHAR_PTR")
+                        .jmp_addr(format!("{name}__auto_next").as_str())
+                        .label(format!("{name}__auto_done").as_str())
+                        .inc_current_ptr(2)
+                        .pla()
+                        .jsr_addr("engine__screen_char_ptr__advance")
+                        .jmp_addr(format!("{name}__packet_done").as_str())
+                        .build(),
+                )
+                .build(),
+        )
+        .function(
+            FunctionBuilder::default()
+                .name(format!("{name}__single").as_str())
+                .instructions(
+                    InstructionBuilder::default()
+                        .lda_current_ptr_offs(1, "Load the single value that will be copied")
+                        .sta_addr("SCRATCH_SPACE_00")
+                        .txa()
+                        .pha()
+                        .tay()
+                        .lda_addr("SCRATCH_SPACE_00")
+                        .label(format!("{name}__single_next").as_str())
+                        .dey()
+                        .bmi_addr(format!("{name}__single_done").as_str())
+                        .sta_ind_y("SCREEN_CHAR_PTR")
+                        .jmp_addr(format!("{name}__single_next").as_str())
+                        .label(format!("{name}__single_done").as_str())
+                        .inc_current_ptr(2)
+                        .pla()
+                        .jsr_addr("engine__screen_char_ptr__advance")
+                        .jmp_addr(format!("{name}__packet_done").as_str())
+                        .build(),
+                )
+                .build(),
+        )
+        .build()
+}
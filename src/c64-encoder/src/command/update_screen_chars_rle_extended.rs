@@ -0,0 +1,543 @@
+//! Update screen chars using a run-length encoding without the limits of [super::update_screen_chars_rle].
+//!
+//! Uses the same packet types, but:
+//! - The packets aren't prefixed with the number of packets. They end with a terminator, a header byte without any
+//!   chars, so any number of packets can be stored.
+//! - When all bits storing the number of chars are set, the number of chars is stored in the byte following the
+//!   header. A packet covers up to 255 chars.
+//!
+//! The packets can visit the screen chars in another [ScanOrder] than row-major. Each scan order has its own command
+//! type and decoder module.
+
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    decoder::{reader::Reader, DecodeError, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{
+    modules::CurrentPtrMacros,
+    scan_order::{ScanOrder, SCREEN_COLUMNS, SCREEN_ROWS},
+    update_screen_chars_rle::{
+        optimal_rle_packets, packet_byte_size, validate_decode, RLECommand, RLEPacket, RLE_MASK_BITS, RLE_MASK_FRAMES,
+        RLE_MASK_SKIP_VALUES, RLE_MASK_UPDATE_VALUES, RLE_MASK_UPDATE_WITH_SINGLE_VALUE,
+    },
+    DecoderModule,
+};
+
+/// Header byte that ends the packets.
+pub const RLE_TERMINATOR: u8 = 0b00000000;
+/// Number of chars in the header byte marking that the number of chars is stored in the next byte.
+pub const RLE_EXTENDED_NUM_SCREEN_CHARS: u8 = RLE_MASK_FRAMES;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpdateScreenCharsRLEExtended {
+    /// Order in which the packets visit the screen chars. Isn't encoded, but selects the command type.
+    pub scan_order: ScanOrder,
+    pub rle_packets: Vec<RLEPacket>,
+}
+
+impl UpdateScreenCharsRLEExtended {
+    /// Transition with the smallest byte size visiting the screen chars in the given order, see
+    /// [super::update_screen_chars_rle::UpdateScreenCharsRLE::optimal_transition].
+    pub fn transition(
+        from_screen_chars: &[u8],
+        to_screen_chars: &[u8],
+        scan_order: ScanOrder,
+    ) -> UpdateScreenCharsRLEExtended {
+        let from_screen_chars = scan_order.gather(from_screen_chars);
+        let to_screen_chars = scan_order.gather(to_screen_chars);
+        let result = UpdateScreenCharsRLEExtended {
+            scan_order,
+            rle_packets: optimal_rle_packets(
+                &from_screen_chars,
+                &to_screen_chars,
+                u8::MAX as usize,
+                extended_packet_byte_size,
+            ),
+        };
+        debug_assert!(validate_decode(
+            &from_screen_chars,
+            &to_screen_chars,
+            &result.rle_packets
+        ));
+        result
+    }
+
+    /// Decoder module for the packets visiting the screen chars in the given order.
+    ///
+    /// The module is named `update_screen_chars_rle_columns` for [ScanOrder::ColumnMajor] and
+    /// `update_screen_chars_rle_tiles` for [ScanOrder::Tiles8x8]. Instead of advancing 'SCREEN_CHAR_PTR' by the
+    /// number of chars of a packet, the module keeps track of the position inside the current block and advances
+    /// 'SCREEN_CHAR_PTR' one char at a time.
+    pub fn scan_order_module(scan_order: ScanOrder) -> Module {
+        let name = match scan_order {
+            ScanOrder::RowMajor => return Self::module(),
+            ScanOrder::ColumnMajor => "update_screen_chars_rle_columns",
+            ScanOrder::Tiles8x8 => "update_screen_chars_rle_tiles",
+        };
+        let label = |suffix: &str| format!("{name}__{suffix}");
+        let (block_width, block_height) = scan_order.block_size();
+        // Going from the last block of a band of blocks to the first block of the next band.
+        let next_band_increment = (SCREEN_COLUMNS * block_height - SCREEN_COLUMNS + block_width) as u16;
+
+        ModuleBuilder::default()
+            .name(name)
+            .instructions(
+                InstructionBuilder::default()
+                    .label(&label("block"))
+                    .comment("Address of the first char of the current block.")
+                    .raw(&[0x00; 2])
+                    .label(&label("line"))
+                    .comment("Address of the first char of the current line of the current block.")
+                    .raw(&[0x00; 2])
+                    .label(&label("x"))
+                    .comment("Column of the current char inside the current block.")
+                    .raw(&[0x00])
+                    .label(&label("y"))
+                    .comment("Line of the current char inside the current block.")
+                    .raw(&[0x00])
+                    .label(&label("row"))
+                    .comment("Row of the current char on the screen.")
+                    .raw(&[0x00])
+                    .label(&label("band_row"))
+                    .comment("Row of the first line of the current block on the screen.")
+                    .raw(&[0x00])
+                    .label(&label("block_x"))
+                    .comment("Index of the current block inside the current band of blocks.")
+                    .raw(&[0x00])
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name(&label("process"))
+                    .instructions(
+                        InstructionBuilder::default()
+                            .inc_current_ptr(1)
+                            .lda_imm(0)
+                            .sta_addr(&label("x"))
+                            .sta_addr(&label("y"))
+                            .sta_addr(&label("row"))
+                            .sta_addr(&label("band_row"))
+                            .sta_addr(&label("block_x"))
+                            .lda_imm_low("SCREEN_CHARS_PAGE0")
+                            .sta_addr("SCREEN_CHAR_PTR")
+                            .sta_addr(&label("line"))
+                            .sta_addr(&label("block"))
+                            .label(&label("destination_page"))
+                            .lda_imm_high("SCREEN_CHARS_PAGE0")
+                            .sta_addr_offs("SCREEN_CHAR_PTR", 1)
+                            .sta_addr_offs(&label("line"), 1)
+                            .sta_addr_offs(&label("block"), 1)
+                            .label(&label("next_packet"))
+                            .lda_current_ptr_offs(0, "Load the RLEPacket header byte into the accumulator.")
+                            .pha()
+                            .and_imm(RLE_MASK_FRAMES)
+                            .comment("Extract the number of chars the packet covers")
+                            .beq_addr(&label("exit"))
+                            .cmp_imm(RLE_EXTENDED_NUM_SCREEN_CHARS)
+                            .bne_addr(&label("header_done"))
+                            .iny()
+                            .lda_ind_y("CURRENT_PTR")
+                            .comment("Load the number of chars from the extended header")
+                            .label(&label("header_done"))
+                            .tax()
+                            .comment("Store the number of chars in X and advance to the data of the packet")
+                            .iny()
+                            .tya()
+                            .jsr_addr("engine__current_ptr__advance")
+                            .pla()
+                            .and_imm(RLE_MASK_BITS)
+                            .comment("Accumulator contains the packet type")
+                            .cmp_imm(RLE_MASK_UPDATE_WITH_SINGLE_VALUE)
+                            .bne_addr(&label("switch_values"))
+                            .jmp_addr(&label("single"))
+                            .label(&label("switch_values"))
+                            .cmp_imm(RLE_MASK_UPDATE_VALUES)
+                            .bne_addr(&label("switch_skip"))
+                            .jmp_addr(&label("values"))
+                            .label(&label("switch_skip"))
+                            .cmp_imm(RLE_MASK_SKIP_VALUES)
+                            .bne_addr(&label("switch_auto"))
+                            .jmp_addr(&label("skip"))
+                            .label(&label("switch_auto"))
+                            .jmp_addr(&label("auto"))
+                            .label(&label("exit"))
+                            .pla()
+                            .inc_current_ptr(1)
+                            .comment("Skip the terminator")
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name(&label("values"))
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(0, "Load the value of the next char")
+                            .jsr_addr(&label("store"))
+                            .inc_current_ptr(1)
+                            .dex()
+                            .bne_addr(&label("values"))
+                            .jmp_addr(&label("next_packet"))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name(&label("skip"))
+                    .instructions(
+                        InstructionBuilder::default()
+                            .jsr_addr(&label("advance"))
+                            .dex()
+                            .bne_addr(&label("skip"))
+                            .jmp_addr(&label("next_packet"))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name(&label("auto"))
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(0, "Load the value of the first char")
+                            .label(&label("auto_next"))
+                            .jsr_addr(&label("store"))
+                            .clc()
+                            .adc_imm(1)
+                            .dex()
+                            .bne_addr(&label("auto_next"))
+                            .inc_current_ptr(1)
+                            .jmp_addr(&label("next_packet"))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name(&label("single"))
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(0, "Load the single value that will be copied")
+                            .label(&label("single_next"))
+                            .jsr_addr(&label("store"))
+                            .dex()
+                            .bne_addr(&label("single_next"))
+                            .inc_current_ptr(1)
+                            .jmp_addr(&label("next_packet"))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name(&label("store"))
+                    .doc(&["Store the accumulator in the current char and advance to the next char in scan order."])
+                    .instructions(
+                        InstructionBuilder::default()
+                            .ldy_imm(0)
+                            .sta_ind_y("SCREEN_CHAR_PTR")
+                            .pha()
+                            .jsr_addr(&label("advance"))
+                            .pla()
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name(&label("advance"))
+                    .doc(&[
+                        "Advance 'SCREEN_CHAR_PTR' to the next char in scan order.",
+                        "",
+                        "Preserves the X register.",
+                    ])
+                    .instructions(
+                        InstructionBuilder::default()
+                            .inc_addr(&label("x"))
+                            .lda_addr(&label("x"))
+                            .cmp_imm(block_width as u8)
+                            .beq_addr(&label("next_line"))
+                            .lda_imm(1)
+                            .jmp_addr("engine__screen_char_ptr__advance")
+                            .label(&label("next_line"))
+                            .lda_imm(0)
+                            .sta_addr(&label("x"))
+                            .inc_addr(&label("row"))
+                            .lda_addr(&label("row"))
+                            .cmp_imm(SCREEN_ROWS as u8)
+                            .comment("Blocks at the bottom of the screen can have fewer lines")
+                            .beq_addr(&label("next_block"))
+                            .inc_addr(&label("y"))
+                            .lda_addr(&label("y"))
+                            .cmp_imm(block_height as u8)
+                            .beq_addr(&label("next_block"))
+                            .clc()
+                            .lda_addr(&label("line"))
+                            .adc_imm(SCREEN_COLUMNS as u8)
+                            .sta_addr(&label("line"))
+                            .sta_addr("SCREEN_CHAR_PTR")
+                            .lda_addr_offs(&label("line"), 1)
+                            .adc_imm(0)
+                            .sta_addr_offs(&label("line"), 1)
+                            .sta_addr_offs("SCREEN_CHAR_PTR", 1)
+                            .rts()
+                            .label(&label("next_block"))
+                            .lda_imm(0)
+                            .sta_addr(&label("y"))
+                            .lda_addr(&label("band_row"))
+                            .sta_addr(&label("row"))
+                            .inc_addr(&label("block_x"))
+                            .lda_addr(&label("block_x"))
+                            .cmp_imm((SCREEN_COLUMNS / block_width) as u8)
+                            .beq_addr(&label("next_band"))
+                            .clc()
+                            .lda_addr(&label("block"))
+                            .adc_imm(block_width as u8)
+                            .sta_addr(&label("block"))
+                            .sta_addr(&label("line"))
+                            .sta_addr("SCREEN_CHAR_PTR")
+                            .lda_addr_offs(&label("block"), 1)
+                            .adc_imm(0)
+                            .sta_addr_offs(&label("block"), 1)
+                            .sta_addr_offs(&label("line"), 1)
+                            .sta_addr_offs("SCREEN_CHAR_PTR", 1)
+                            .rts()
+                            .label(&label("next_band"))
+                            .lda_imm(0)
+                            .sta_addr(&label("block_x"))
+                            .clc()
+                            .lda_addr(&label("band_row"))
+                            .adc_imm(block_height as u8)
+                            .sta_addr(&label("band_row"))
+                            .sta_addr(&label("row"))
+                            .clc()
+                            .lda_addr(&label("block"))
+                            .adc_imm(next_band_increment.to_le_bytes()[0])
+                            .sta_addr(&label("block"))
+                            .sta_addr(&label("line"))
+                            .sta_addr("SCREEN_CHAR_PTR")
+                            .lda_addr_offs(&label("block"), 1)
+                            .adc_imm(next_band_increment.to_le_bytes()[1])
+                            .sta_addr_offs(&label("block"), 1)
+                            .sta_addr_offs(&label("line"), 1)
+                            .sta_addr_offs("SCREEN_CHAR_PTR", 1)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
+
+/// Byte size of an encoded packet with the given packet type covering the given number of screen chars.
+fn extended_packet_byte_size(rle_command_mask: u8, num_screen_chars: usize) -> usize {
+    let extended_header = num_screen_chars >= RLE_EXTENDED_NUM_SCREEN_CHARS as usize;
+    packet_byte_size(rle_command_mask, num_screen_chars) + extended_header as usize
+}
+
+impl Encoder for UpdateScreenCharsRLEExtended {
+    fn byte_size(&self) -> usize {
+        self.rle_packets
+            .iter()
+            .map(|rle_packet| {
+                extended_packet_byte_size(rle_packet.command.mask(), rle_packet.num_screen_chars as usize)
+            })
+            .sum::<usize>()
+            + 1
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        let mut encoded_data = encoded_data;
+        for rle_packet in &self.rle_packets {
+            assert!(rle_packet.num_screen_chars > 0);
+            let mask = rle_packet.command.mask();
+            if rle_packet.num_screen_chars < RLE_EXTENDED_NUM_SCREEN_CHARS {
+                encoded_data = encoded_data.add(&(mask | rle_packet.num_screen_chars));
+            } else {
+                encoded_data = encoded_data
+                    .add(&(mask | RLE_EXTENDED_NUM_SCREEN_CHARS))
+                    .add(&rle_packet.num_screen_chars);
+            }
+            encoded_data = rle_packet.command.encode_data(encoded_data);
+        }
+        encoded_data.add(&RLE_TERMINATOR)
+    }
+}
+
+impl Decoder for UpdateScreenCharsRLEExtended {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let mut encoded_data = encoded_data;
+        let mut rle_packets = Vec::new();
+        loop {
+            let header;
+            (header, encoded_data) = encoded_data.read::<u8>()?;
+            let mut num_screen_chars = header & RLE_MASK_FRAMES;
+            if num_screen_chars == 0 {
+                break;
+            }
+            if num_screen_chars == RLE_EXTENDED_NUM_SCREEN_CHARS {
+                (num_screen_chars, encoded_data) = encoded_data.read::<u8>()?;
+                if num_screen_chars < RLE_EXTENDED_NUM_SCREEN_CHARS {
+                    return Err(DecodeError::InvalidExtendedCount(num_screen_chars));
+                }
+            }
+            let command;
+            (command, encoded_data) = RLECommand::decode_data(header & RLE_MASK_BITS, num_screen_chars, encoded_data)?;
+            rle_packets.push(RLEPacket {
+                num_screen_chars,
+                command,
+            });
+        }
+        let update = UpdateScreenCharsRLEExtended {
+            scan_order: ScanOrder::RowMajor,
+            rle_packets,
+        };
+        Ok((update, encoded_data))
+    }
+}
+
+impl DecoderModule for UpdateScreenCharsRLEExtended {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("update_screen_chars_rle_extended")
+            .function(
+                FunctionBuilder::default()
+                    .name("update_screen_chars_rle_extended__process")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .inc_current_ptr(1)
+                            .lda_imm_low("SCREEN_CHARS_PAGE0")
+                            .sta_addr("SCREEN_CHAR_PTR")
+                            .label("update_screen_chars_rle_extended__destination_page")
+                            .lda_imm_high("SCREEN_CHARS_PAGE0")
+                            .sta_addr_offs("SCREEN_CHAR_PTR", 1)
+                            .label("update_screen_chars_rle_extended__next_packet")
+                            .lda_current_ptr_offs(0, "Load the RLEPacket header byte into the accumulator.")
+                            .pha()
+                            .and_imm(RLE_MASK_FRAMES)
+                            .comment("Extract the number of chars the packet covers")
+                            .beq_addr("update_screen_chars_rle_extended__exit")
+                            .cmp_imm(RLE_EXTENDED_NUM_SCREEN_CHARS)
+                            .bne_addr("update_screen_chars_rle_extended__header_done")
+                            .iny()
+                            .lda_ind_y("CURRENT_PTR")
+                            .comment("Load the number of chars from the extended header")
+                            .label("update_screen_chars_rle_extended__header_done")
+                            .tax()
+                            .comment("Store the number of chars in X and advance to the data of the packet")
+                            .iny()
+                            .tya()
+                            .jsr_addr("engine__current_ptr__advance")
+                            .pla()
+                            .and_imm(RLE_MASK_BITS)
+                            .comment("Accumulator contains the packet type")
+                            .cmp_imm(RLE_MASK_UPDATE_WITH_SINGLE_VALUE)
+                            .bne_addr("update_screen_chars_rle_extended__switch_values")
+                            .jmp_addr("update_screen_chars_rle_extended__single")
+                            .label("update_screen_chars_rle_extended__switch_values")
+                            .cmp_imm(RLE_MASK_UPDATE_VALUES)
+                            .bne_addr("update_screen_chars_rle_extended__switch_skip")
+                            .jmp_addr("update_screen_chars_rle_extended__values")
+                            .label("update_screen_chars_rle_extended__switch_skip")
+                            .cmp_imm(RLE_MASK_SKIP_VALUES)
+                            .bne_addr("update_screen_chars_rle_extended__switch_auto")
+                            .jmp_addr("update_screen_chars_rle_extended__skip")
+                            .label("update_screen_chars_rle_extended__switch_auto")
+                            .jmp_addr("update_screen_chars_rle_extended__auto")
+                            .label("update_screen_chars_rle_extended__exit")
+                            .pla()
+                            .inc_current_ptr(1)
+                            .comment("Skip the terminator")
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("update_screen_chars_rle_extended__values")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .ldy_imm(0)
+                            .label("update_screen_chars_rle_extended__values_next")
+                            .lda_ind_y("CURRENT_PTR")
+                            .sta_ind_y("SCREEN_CHAR_PTR")
+                            .iny()
+                            .dex()
+                            .bne_addr("update_screen_chars_rle_extended__values_next")
+                            .tya()
+                            .pha()
+                            .jsr_addr("engine__screen_char_ptr__advance")
+                            .pla()
+                            .jsr_addr("engine__current_ptr__advance")
+                            .jmp_addr("update_screen_chars_rle_extended__next_packet")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("update_screen_chars_rle_extended__skip")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .txa()
+                            .jsr_addr("engine__screen_char_ptr__advance")
+                            .jmp_addr("update_screen_chars_rle_extended__next_packet")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("update_screen_chars_rle_extended__auto")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(0, "Load the value of the first char")
+                            .label("update_screen_chars_rle_extended__auto_next")
+                            .sta_ind_y("SCREEN_CHAR_PTR")
+                            .clc()
+                            .adc_imm(1)
+                            .iny()
+                            .dex()
+                            .bne_addr("update_screen_chars_rle_extended__auto_next")
+                            .tya()
+                            .jsr_addr("engine__screen_char_ptr__advance")
+                            .inc_current_ptr(1)
+                            .jmp_addr("update_screen_chars_rle_extended__next_packet")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("update_screen_chars_rle_extended__single")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(0, "Load the single value that will be copied")
+                            .label("update_screen_chars_rle_extended__single_next")
+                            .sta_ind_y("SCREEN_CHAR_PTR")
+                            .iny()
+                            .dex()
+                            .bne_addr("update_screen_chars_rle_extended__single_next")
+                            .tya()
+                            .jsr_addr("engine__screen_char_ptr__advance")
+                            .inc_current_ptr(1)
+                            .jmp_addr("update_screen_chars_rle_extended__next_packet")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
@@ -0,0 +1,283 @@
+use proptest::{collection::vec, prelude::*};
+
+use crate::{
+    command::{
+        scan_order::ScanOrder,
+        update_screen_chars_rle::{RLECommand, RLEPacket, UpdateScreenCharsRLE},
+        update_screen_chars_rle_extended::UpdateScreenCharsRLEExtended,
+        Command,
+    },
+    decoder::Decoder,
+    encoder::Encoder,
+};
+
+/// Apply the packets to the screen chars.
+fn apply(from_screen_chars: &[u8], rle_packets: &[RLEPacket]) -> Vec<u8> {
+    let mut result = from_screen_chars.to_vec();
+    let mut offset = 0;
+    for packet in rle_packets {
+        let num_screen_chars = packet.num_screen_chars as usize;
+        let screen_chars = &mut result[offset..offset + num_screen_chars];
+        match &packet.command {
+            RLECommand::UpdateWithSingleValue(value) => screen_chars.fill(*value),
+            RLECommand::UpdateValues(values) => screen_chars.copy_from_slice(values),
+            RLECommand::SkipValues => {}
+            RLECommand::AutoIncrement(start_value) => {
+                for (increment, screen_char) in screen_chars.iter_mut().enumerate() {
+                    *screen_char = start_value + increment as u8;
+                }
+            }
+        }
+        offset += num_screen_chars;
+    }
+    result
+}
+
+/// Screen chars with few different values, so the transitions contain runs of each packet type.
+fn screen_chars() -> impl Strategy<Value = Vec<u8>> {
+    vec(0_u8..4, 1000)
+}
+
+proptest! {
+    #[test]
+    fn optimal_transition_is_not_larger_than_greedy(from in screen_chars(), to in screen_chars()) {
+        let greedy = UpdateScreenCharsRLE::transition(&from, &to);
+        let optimal = UpdateScreenCharsRLE::optimal_transition(&from, &to);
+
+        prop_assert!(optimal.byte_size() <= greedy.byte_size());
+        prop_assert_eq!(&to, &apply(&from, &optimal.rle_packets));
+    }
+}
+
+#[test]
+fn rewrite_unchanged_screen_char_between_runs() {
+    let from = [0x00; 1000];
+    let mut to = from;
+    to[10..15].copy_from_slice(&[1, 5, 0, 7, 3]);
+
+    let greedy = UpdateScreenCharsRLE::transition(&from, &to);
+    let optimal = UpdateScreenCharsRLE::optimal_transition(&from, &to);
+
+    assert_eq!(5, greedy.rle_packets.len());
+    assert_eq!(
+        vec![
+            RLEPacket {
+                num_screen_chars: 10,
+                command: RLECommand::SkipValues
+            },
+            RLEPacket {
+                num_screen_chars: 5,
+                command: RLECommand::UpdateValues(vec![1, 5, 0, 7, 3])
+            },
+        ],
+        optimal.rle_packets
+    );
+    assert_eq!(8, optimal.byte_size());
+    assert!(optimal.byte_size() < greedy.byte_size());
+}
+
+#[test]
+fn optimal_transition_splits_long_runs() {
+    let from = [0x00; 1000];
+    let to = [0x01; 1000];
+
+    let optimal = UpdateScreenCharsRLE::optimal_transition(&from, &to);
+
+    // 15 packets of 63 screen chars and one of 55.
+    assert_eq!(16, optimal.rle_packets.len());
+    assert_eq!(33, optimal.byte_size());
+    assert_eq!(to.to_vec(), apply(&from, &optimal.rle_packets));
+}
+
+#[test]
+fn optimal_transition_of_identical_screens() {
+    let screen = [0x20; 1000];
+
+    let optimal = UpdateScreenCharsRLE::optimal_transition(&screen, &screen);
+
+    assert!(optimal.rle_packets.is_empty());
+}
+
+#[test]
+fn optimal_transition_round_trip() {
+    let from = [0x00; 1000];
+    let mut to = from;
+    for (offset, screen_char) in to.iter_mut().enumerate().skip(100).take(300) {
+        *screen_char = (offset % 50) as u8;
+    }
+    let optimal = UpdateScreenCharsRLE::optimal_transition(&from, &to);
+    let mut encoded = vec![0; optimal.byte_size()];
+    optimal.encode(&mut encoded);
+
+    let (decoded, remaining) = UpdateScreenCharsRLE::decode(&encoded).unwrap();
+
+    assert!(remaining.is_empty());
+    assert_eq!(optimal, decoded);
+}
+
+#[test]
+fn extended_transition_uses_long_runs() {
+    let from = [0x00; 1000];
+    let to = [0x01; 1000];
+
+    let update = UpdateScreenCharsRLEExtended::transition(&from, &to, ScanOrder::RowMajor);
+
+    assert_eq!(
+        vec![255, 255, 255, 235],
+        update
+            .rle_packets
+            .iter()
+            .map(|packet| packet.num_screen_chars)
+            .collect::<Vec<u8>>()
+    );
+    // 4 packets with an extended header and the terminator.
+    assert_eq!(13, update.byte_size());
+    assert_eq!(to.to_vec(), apply(&from, &update.rle_packets));
+}
+
+#[test]
+fn extended_round_trip() {
+    let from = [0x00; 1000];
+    let mut to = from;
+    for (offset, screen_char) in to.iter_mut().enumerate().skip(100) {
+        *screen_char = if offset < 400 { (offset / 3) as u8 } else { 0x20 };
+    }
+    let update = UpdateScreenCharsRLEExtended::transition(&from, &to, ScanOrder::RowMajor);
+    let mut encoded = vec![0; update.byte_size()];
+    update.encode(&mut encoded);
+
+    let (decoded, remaining) = UpdateScreenCharsRLEExtended::decode(&encoded).unwrap();
+
+    assert!(remaining.is_empty());
+    assert_eq!(update, decoded);
+}
+
+#[test]
+fn small_transition_uses_rle() {
+    let from = [0x00; 1000];
+    let mut to = from;
+    to[10..15].copy_from_slice(&[1, 5, 0, 7, 3]);
+
+    let command = Command::update_screen_chars_rle(&from, &to);
+
+    assert!(matches!(command, Command::UpdateScreenCharsRLE(_)));
+}
+
+#[test]
+fn busy_transition_uses_extended_rle() {
+    let from = [0x00; 1000];
+    // Runs of 3 screen chars with a different value, which needs more than 255 packets.
+    let to = std::array::from_fn::<u8, 1000, _>(|offset| (offset / 3 * 7 % 255 + 1) as u8);
+    assert!(UpdateScreenCharsRLE::optimal_transition(&from, &to).rle_packets.len() > 255);
+
+    let command = Command::update_screen_chars_rle(&from, &to);
+
+    let Command::UpdateScreenCharsRLEExtended(update) = &command else {
+        panic!("unexpected command {command:?}");
+    };
+    assert_eq!(to.to_vec(), apply(&from, &update.rle_packets));
+    let mut encoded = vec![0; command.byte_size()];
+    command.encode(&mut encoded);
+    assert_eq!(command, Command::decode(&encoded).unwrap().0);
+}
+
+/// Screen with columns of the same screen char, a different one for each column.
+fn vertical_stripes() -> [u8; 1000] {
+    std::array::from_fn(|offset| (offset % 40 * 3) as u8)
+}
+
+/// Screen with tiles of 8x8 of the same screen char, a different one for each tile.
+fn tiles() -> [u8; 1000] {
+    std::array::from_fn(|offset| ((offset / 320 * 5 + offset % 40 / 8) * 7) as u8)
+}
+
+#[test]
+fn scan_order_offsets_visit_every_screen_char() {
+    for scan_order in ScanOrder::ALL {
+        let mut offsets = scan_order.offsets();
+        offsets.sort();
+
+        assert_eq!((0..1000).collect::<Vec<usize>>(), offsets);
+    }
+}
+
+#[test]
+fn scan_order_offsets() {
+    assert_eq!(vec![0, 1, 2], ScanOrder::RowMajor.offsets()[..3]);
+    assert_eq!(vec![0, 40, 80], ScanOrder::ColumnMajor.offsets()[..3]);
+    assert_eq!(vec![960, 1, 41], ScanOrder::ColumnMajor.offsets()[24..27]);
+    assert_eq!(vec![7, 40, 41], ScanOrder::Tiles8x8.offsets()[7..10]);
+    assert_eq!(vec![287, 8, 9], ScanOrder::Tiles8x8.offsets()[63..66]);
+    // The tiles at the bottom of the screen are a single row high.
+    assert_eq!(vec![967, 968, 969], ScanOrder::Tiles8x8.offsets()[967..970]);
+}
+
+#[test]
+fn scan_order_gather_and_scatter() {
+    let screen_chars = std::array::from_fn::<u8, 1000, _>(|offset| offset as u8);
+    for scan_order in ScanOrder::ALL {
+        let mut scattered = [0x00; 1000];
+
+        scan_order.scatter(&scan_order.gather(&screen_chars), &mut scattered);
+
+        assert_eq!(screen_chars, scattered);
+    }
+}
+
+#[test]
+fn column_major_transition_of_vertical_stripes() {
+    let from = [0x00; 1000];
+    let to = vertical_stripes();
+
+    let row_major = UpdateScreenCharsRLEExtended::transition(&from, &to, ScanOrder::RowMajor);
+    let column_major = UpdateScreenCharsRLEExtended::transition(&from, &to, ScanOrder::ColumnMajor);
+
+    // Skip the first column that doesn't change, a packet for each other column and the terminator.
+    assert_eq!(1 + 39 * 2 + 1, column_major.byte_size());
+    assert!(column_major.byte_size() < row_major.byte_size());
+    let mut decoded = from;
+    ScanOrder::ColumnMajor.scatter(
+        &apply(&ScanOrder::ColumnMajor.gather(&from), &column_major.rle_packets),
+        &mut decoded,
+    );
+    assert_eq!(to, decoded);
+}
+
+#[test]
+fn tiles_transition_of_tiles() {
+    let from = [0xFF; 1000];
+    let to = tiles();
+
+    let row_major = UpdateScreenCharsRLEExtended::transition(&from, &to, ScanOrder::RowMajor);
+    let tiles_8x8 = UpdateScreenCharsRLEExtended::transition(&from, &to, ScanOrder::Tiles8x8);
+
+    // A packet for each tile with an extended header, except for the tiles at the bottom, and the terminator.
+    assert_eq!(20, tiles_8x8.rle_packets.len());
+    assert_eq!(15 * 3 + 5 * 2 + 1, tiles_8x8.byte_size());
+    assert!(tiles_8x8.byte_size() < row_major.byte_size());
+    let mut decoded = from;
+    ScanOrder::Tiles8x8.scatter(
+        &apply(&ScanOrder::Tiles8x8.gather(&from), &tiles_8x8.rle_packets),
+        &mut decoded,
+    );
+    assert_eq!(to, decoded);
+}
+
+#[test]
+fn scan_order_round_trip() {
+    let from = [0x00; 1000];
+    for scan_order in [ScanOrder::ColumnMajor, ScanOrder::Tiles8x8] {
+        let command = Command::UpdateScreenCharsRLEExtended(UpdateScreenCharsRLEExtended::transition(
+            &from,
+            &tiles(),
+            scan_order,
+        ));
+        let mut encoded = vec![0; command.byte_size()];
+        command.encode(&mut encoded);
+
+        let (decoded, remaining) = Command::decode(&encoded).unwrap();
+
+        assert!(remaining.is_empty());
+        assert_eq!(command, decoded);
+    }
+}
@@ -0,0 +1,61 @@
+use c64_assembler::Module;
+
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{update_text_mode_screen::copy_screen_module, DecoderModule};
+
+/// Update all 1000 colors in color RAM. Only the lower 4 bits of each color are used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateScreenColors {
+    pub colors: [u8; 1000],
+}
+
+impl Default for UpdateScreenColors {
+    fn default() -> Self {
+        Self { colors: [0; 1000] }
+    }
+}
+
+impl UpdateScreenColors {
+    pub fn filled(color: u8) -> UpdateScreenColors {
+        UpdateScreenColors { colors: [color; 1000] }
+    }
+}
+
+impl Encoder for UpdateScreenColors {
+    fn byte_size(&self) -> usize {
+        1000
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        let mut encoded_data = encoded_data;
+        for color in &self.colors {
+            encoded_data = encoded_data.add(color);
+        }
+        encoded_data
+    }
+}
+
+impl Decoder for UpdateScreenColors {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (colors, encoded_data) = encoded_data.read()?;
+        Ok((UpdateScreenColors { colors }, encoded_data))
+    }
+}
+
+impl DecoderModule for UpdateScreenColors {
+    fn module() -> Module {
+        copy_screen_module(
+            "update_screen_colors",
+            [
+                "SCREEN_COLORS_PAGE0",
+                "SCREEN_COLORS_PAGE1",
+                "SCREEN_COLORS_PAGE2",
+                "SCREEN_COLORS_PAGE3",
+            ],
+        )
+    }
+}
@@ -0,0 +1,58 @@
+//! Update color RAM using a run-length encoding.
+//!
+//! Uses the same RLE packets as [super::update_screen_chars_rle], but the packets are written to color RAM.
+
+use c64_assembler::Module;
+
+use crate::{
+    decoder::{DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{
+    update_screen_chars_rle::{read_rle_packets, rle_decoder_module, RLEPacket, UpdateScreenCharsRLE},
+    DecoderModule,
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpdateScreenColorsRLE {
+    pub rle_packets: Vec<RLEPacket>,
+}
+
+impl UpdateScreenColorsRLE {
+    pub fn transition(from_screen_colors: &[u8], to_screen_colors: &[u8]) -> UpdateScreenColorsRLE {
+        UpdateScreenColorsRLE {
+            rle_packets: UpdateScreenCharsRLE::transition(from_screen_colors, to_screen_colors).rle_packets,
+        }
+    }
+}
+
+impl Encoder for UpdateScreenColorsRLE {
+    fn byte_size(&self) -> usize {
+        self.rle_packets.iter().map(|rle| rle.byte_size()).sum::<usize>() + 1
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        assert!(self.rle_packets.len() < 256);
+        let mut encoded_data = encoded_data;
+        let num_rle_packets = self.rle_packets.len() as u8;
+        encoded_data = encoded_data.add(&num_rle_packets);
+        for rle_packet in &self.rle_packets {
+            encoded_data = encoded_data.add(rle_packet);
+        }
+        encoded_data
+    }
+}
+
+impl Decoder for UpdateScreenColorsRLE {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (rle_packets, encoded_data) = read_rle_packets(encoded_data)?;
+        Ok((UpdateScreenColorsRLE { rle_packets }, encoded_data))
+    }
+}
+
+impl DecoderModule for UpdateScreenColorsRLE {
+    fn module() -> Module {
+        rle_decoder_module("update_screen_colors_rle", "SCREEN_COLORS_PAGE0")
+    }
+}
@@ -1,6 +1,16 @@
-use crate::encoder::{writer::Writer, Encoder};
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
 
-#[derive(Debug, Clone)]
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{modules::CurrentPtrMacros, DecoderModule};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct UpdateTextModeScreen {
     pub chars: [u8; 1000],
 }
@@ -30,3 +40,80 @@ impl Encoder for UpdateTextModeScreen {
         encoded_data
     }
 }
+
+impl Decoder for UpdateTextModeScreen {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (chars, encoded_data) = encoded_data.read()?;
+        Ok((UpdateTextModeScreen { chars }, encoded_data))
+    }
+}
+
+impl DecoderModule for UpdateTextModeScreen {
+    fn module() -> Module {
+        copy_screen_module(
+            "update_text_mode_screen",
+            [
+                "SCREEN_CHARS_PAGE0",
+                "SCREEN_CHARS_PAGE1",
+                "SCREEN_CHARS_PAGE2",
+                "SCREEN_CHARS_PAGE3",
+            ],
+        )
+    }
+}
+
+/// Build the decoder that copies 1000 bytes following the command byte to the given destination pages.
+///
+/// All labels are prefixed with `name`, the entry point is `{name}__process`. The store to each destination page is
+/// labeled `{name}__store0` to `{name}__store3`.
+pub(crate) fn copy_screen_module(name: &str, destination_pages: [&str; 4]) -> Module {
+    ModuleBuilder::default()
+        .name(name)
+        .function(
+            FunctionBuilder::default()
+                .name(format!("{name}__process").as_str())
+                .doc(&[
+                    "Copy the 1000 bytes that follow the command byte to the screen.",
+                    "",
+                    "The first 3 pages are copied completely, the last page only contains 232 bytes.",
+                ])
+                .instructions(
+                    InstructionBuilder::default()
+                        .inc_current_ptr(1)
+                        .ldy_imm(0x00)
+                        .label(format!("{name}__page0").as_str())
+                        .lda_ind_y("CURRENT_PTR")
+                        .label(format!("{name}__store0").as_str())
+                        .sta_addr_y(destination_pages[0])
+                        .iny()
+                        .bne_addr(format!("{name}__page0").as_str())
+                        .inc_addr_offs("CURRENT_PTR", 1)
+                        .label(format!("{name}__page1").as_str())
+                        .lda_ind_y("CURRENT_PTR")
+                        .label(format!("{name}__store1").as_str())
+                        .sta_addr_y(destination_pages[1])
+                        .iny()
+                        .bne_addr(format!("{name}__page1").as_str())
+                        .inc_addr_offs("CURRENT_PTR", 1)
+                        .label(format!("{name}__page2").as_str())
+                        .lda_ind_y("CURRENT_PTR")
+                        .label(format!("{name}__store2").as_str())
+                        .sta_addr_y(destination_pages[2])
+                        .iny()
+                        .bne_addr(format!("{name}__page2").as_str())
+                        .inc_addr_offs("CURRENT_PTR", 1)
+                        .label(format!("{name}__page3").as_str())
+                        .lda_ind_y("CURRENT_PTR")
+                        .label(format!("{name}__store3").as_str())
+                        .sta_addr_y(destination_pages[3])
+                        .iny()
+                        .cpy_imm(232)
+                        .bne_addr(format!("{name}__page3").as_str())
+                        .inc_current_ptr(232)
+                        .rts()
+                        .build(),
+                )
+                .build(),
+        )
+        .build()
+}
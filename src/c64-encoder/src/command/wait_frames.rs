@@ -0,0 +1,87 @@
+use c64_assembler::{
+    builder::{FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    Module,
+};
+
+use crate::{
+    decoder::{reader::Reader, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+use super::{
+    modules::{CurrentPtrMacros, DEFAULT_RASTER_LINE},
+    DecoderModule,
+};
+
+/// Keep the current screen for a number of video frames.
+///
+/// Replaces a sequence of identical frames, which would each cost a frame header.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct WaitFrames {
+    pub num_frames: u8,
+}
+
+impl Encoder for WaitFrames {
+    fn byte_size(&self) -> usize {
+        size_of::<u8>()
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        encoded_data.add(&self.num_frames)
+    }
+}
+
+impl Decoder for WaitFrames {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (num_frames, encoded_data) = encoded_data.read()?;
+        Ok((WaitFrames { num_frames }, encoded_data))
+    }
+}
+
+impl DecoderModule for WaitFrames {
+    fn module() -> Module {
+        ModuleBuilder::default()
+            .name("wait_frames")
+            .instructions(
+                InstructionBuilder::default()
+                    .label("wait_frames__deferred")
+                    .comment("Not 0 when the IRQ player counts down the frames instead of busy waiting.")
+                    .raw(&[0x00])
+                    .label("wait_frames__countdown")
+                    .comment("Number of interrupts the IRQ player skips before processing the next frame.")
+                    .raw(&[0x00])
+                    .build(),
+            )
+            .function(
+                FunctionBuilder::default()
+                    .name("wait_frames__process")
+                    .doc(&[
+                        "Wait a number of video frames.",
+                        "",
+                        "When played from the raster interrupt, the wait is handed over to the interrupt handler so",
+                        "the interrupt returns and main code and the hook keep running during the wait.",
+                    ])
+                    .instructions(
+                        InstructionBuilder::default()
+                            .lda_current_ptr_offs(1, "Load number of frames to wait into X")
+                            .tax()
+                            .beq_addr("wait_frames__exit")
+                            .lda_addr("wait_frames__deferred")
+                            .beq_addr("wait_frames__next")
+                            .stx_addr("wait_frames__countdown")
+                            .jmp_addr("wait_frames__exit")
+                            .label("wait_frames__next")
+                            .lda_imm(DEFAULT_RASTER_LINE)
+                            .jsr_addr("engine__raster__wait")
+                            .dex()
+                            .bne_addr("wait_frames__next")
+                            .label("wait_frames__exit")
+                            .inc_current_ptr(2)
+                            .rts()
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+}
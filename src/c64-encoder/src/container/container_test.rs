@@ -0,0 +1,282 @@
+use c64_colors::colors::Color;
+
+use crate::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder},
+    command::{
+        modules::{EngineConfig, EngineConfigError},
+        packed_commands::PackedCommands,
+        set_sprite_data::{SetSpriteData, SPRITE_DATA_SIZE},
+        Command, CLEAR_SCREEN_CHAR, GOTO_FRAME, PACKED_COMMANDS, PACKED_COMMANDS_END, SET_BORDER_COLOR, SET_PALETTE4,
+        WAIT_FRAMES,
+    },
+    container::{crc32, ContainerError, DemoContainer, MemoryLayout, MAGIC},
+    decoder::DecodeError,
+    evaluator::{evaluate_container, EvaluateErrorReason},
+};
+
+fn demo() -> DemoBuilder {
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .set_palette4([Color::Black, Color::White, Color::Black, Color::Black])
+            .clear_screen_chars(1)
+            .build(),
+    )
+    .frame(FrameBuilder::default().set_border_color(Color::Red).build())
+    .goto_frame(0);
+    demo
+}
+
+/// Recalculate the checksum after changing the container.
+fn update_checksum(container_bytes: &mut [u8]) {
+    let checksum_offset = container_bytes.len() - 4;
+    let checksum = crc32(&container_bytes[..checksum_offset]);
+    container_bytes[checksum_offset..].copy_from_slice(&checksum.to_le_bytes());
+}
+
+#[test]
+fn crc32_check_value() {
+    assert_eq!(0xCBF43926, crc32(b"123456789"));
+}
+
+#[test]
+fn container_round_trip() {
+    let demo = demo();
+    let memory_layout = MemoryLayout {
+        load_address: 0x2000,
+        ..MemoryLayout::default()
+    };
+    let container_bytes = demo.build_container(memory_layout);
+    assert_eq!(MAGIC, container_bytes[0..4]);
+
+    let container = DemoContainer::read(&container_bytes).unwrap();
+    assert_eq!(memory_layout, container.memory_layout);
+    assert_eq!(
+        vec![CLEAR_SCREEN_CHAR, SET_PALETTE4, SET_BORDER_COLOR, GOTO_FRAME],
+        container.command_types
+    );
+    assert_eq!(vec![2, 9], container.frame_offsets);
+    assert_eq!(demo.build(), container.demo_bytes);
+    assert_eq!(Ok(demo), DemoBuilder::from_container(&container_bytes));
+
+    assert_eq!(3, evaluate_container(&container_bytes).unwrap().len());
+}
+
+#[test]
+fn container_declares_packed_command_types() {
+    let mut demo = demo();
+    let packed_commands = FrameBuilder::default().wait_frames(2).build().commands;
+    demo.frame(
+        FrameBuilder::default()
+            .push(Command::PackedCommands(PackedCommands::new(packed_commands)))
+            .build(),
+    );
+
+    let container_bytes = demo.build_container(MemoryLayout::default());
+    let container = DemoContainer::read(&container_bytes).unwrap();
+    assert_eq!(
+        vec![
+            CLEAR_SCREEN_CHAR,
+            SET_PALETTE4,
+            SET_BORDER_COLOR,
+            WAIT_FRAMES,
+            GOTO_FRAME,
+            PACKED_COMMANDS,
+            PACKED_COMMANDS_END
+        ],
+        container.command_types
+    );
+    assert!(evaluate_container(&container_bytes).is_ok());
+}
+
+#[test]
+fn container_rejects_invalid_header() {
+    let container_bytes = demo().build_container(MemoryLayout::default());
+
+    let mut invalid = container_bytes.clone();
+    invalid[0] = b'X';
+    assert_eq!(Err(ContainerError::InvalidMagic), DemoContainer::read(&invalid));
+
+    let mut invalid = container_bytes.clone();
+    invalid[4] = 1;
+    assert_eq!(
+        Err(ContainerError::UnsupportedVersion(1)),
+        DemoContainer::read(&invalid)
+    );
+
+    let mut invalid = container_bytes.clone();
+    invalid[5] = 7;
+    update_checksum(&mut invalid);
+    assert_eq!(
+        Err(ContainerError::UnsupportedTargetMode(7)),
+        DemoContainer::read(&invalid)
+    );
+
+    assert_eq!(
+        Err(ContainerError::Decode(DecodeError::UnexpectedEnd)),
+        DemoContainer::read(&container_bytes[..3])
+    );
+}
+
+#[test]
+fn container_rejects_corrupted_data() {
+    let container_bytes = demo().build_container(MemoryLayout::default());
+    let demo_offset = container_bytes.len() - 4 - demo().build().len();
+
+    let mut invalid = container_bytes.clone();
+    invalid[demo_offset + 5] ^= 0xFF;
+    let result = DemoContainer::read(&invalid);
+    assert!(matches!(result, Err(ContainerError::ChecksumMismatch { .. })));
+    let error = evaluate_container(&invalid).unwrap_err();
+    assert!(matches!(
+        error.reason,
+        EvaluateErrorReason::Container(ContainerError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn container_rejects_mismatching_header() {
+    let container = DemoContainer::new(&demo(), MemoryLayout::default());
+
+    let mut invalid = container.clone();
+    invalid.command_types.retain(|command_type| *command_type != GOTO_FRAME);
+    assert_eq!(
+        Err(ContainerError::UndeclaredCommandType(GOTO_FRAME)),
+        DemoContainer::read(&invalid.write())
+    );
+
+    let mut invalid = container.clone();
+    invalid.command_types.push(0xEE);
+    assert_eq!(
+        Err(ContainerError::UnsupportedCommandType(0xEE)),
+        DemoContainer::read(&invalid.write())
+    );
+
+    let mut invalid = container.clone();
+    invalid.frame_offsets[1] = 12;
+    assert_eq!(
+        Err(ContainerError::FrameOffsetMismatch {
+            frame: 1,
+            stored: 12,
+            decoded: 9
+        }),
+        DemoContainer::read(&invalid.write())
+    );
+
+    let mut invalid = container.clone();
+    invalid.frame_offsets.pop();
+    assert_eq!(
+        Err(ContainerError::FrameCountMismatch { stored: 1, decoded: 2 }),
+        DemoContainer::read(&invalid.write())
+    );
+
+    let mut invalid = container.clone();
+    invalid.demo_bytes.push(0);
+    assert_eq!(
+        Err(ContainerError::DemoSizeMismatch {
+            stored: container.demo_bytes.len() + 1,
+            decoded: container.demo_bytes.len()
+        }),
+        DemoContainer::read(&invalid.write())
+    );
+    assert!(matches!(
+        DemoBuilder::from_container(&invalid.write()),
+        Err(ContainerError::DemoSizeMismatch { .. })
+    ));
+}
+
+#[test]
+fn container_rejects_invalid_memory_layout() {
+    let engine_config = EngineConfig {
+        vic_bank: 1,
+        screen_slot: 2,
+        charset_slot: 3,
+        ..EngineConfig::default()
+    };
+    let memory_layout = MemoryLayout::new(&engine_config, 0x1000);
+    assert_eq!(0x4800, memory_layout.screen_chars_address);
+    assert_eq!(0x5800, memory_layout.charset_address);
+    let container = DemoContainer::read(&demo().build_container(memory_layout)).unwrap();
+    assert_eq!(Ok(engine_config), container.memory_layout.engine_config());
+
+    let engine_config = EngineConfig {
+        back_screen_slot: Some(1),
+        ..EngineConfig::default()
+    };
+    let memory_layout = MemoryLayout::new(&engine_config, 0x1000);
+    assert_eq!(Some(0xC400), memory_layout.back_screen_chars_address);
+    let container_bytes = demo().build_container(memory_layout);
+    let container = DemoContainer::read(&container_bytes).unwrap();
+    assert_eq!(Ok(engine_config), container.memory_layout.engine_config());
+    assert!(evaluate_container(&container_bytes).unwrap()[0].back_buffer.is_some());
+
+    let invalid = MemoryLayout {
+        load_address: 0x7FF8,
+        ..MemoryLayout::default()
+    };
+    assert_eq!(
+        Err(ContainerError::InvalidMemoryLayout(EngineConfigError::ProgramOverlap(
+            0x8000
+        ))),
+        DemoContainer::read(&demo().build_container(invalid))
+    );
+    let invalid = MemoryLayout {
+        charset_address: 0x1000,
+        ..MemoryLayout::default()
+    };
+    assert_eq!(
+        Err(ContainerError::InvalidMemoryLayout(EngineConfigError::VicBankMismatch)),
+        DemoContainer::read(&demo().build_container(invalid))
+    );
+    let invalid = MemoryLayout {
+        screen_chars_address: 0x0400,
+        charset_address: 0x1000,
+        ..MemoryLayout::default()
+    };
+    assert_eq!(
+        Err(ContainerError::InvalidMemoryLayout(EngineConfigError::CharRomShadow(
+            0x1000
+        ))),
+        DemoContainer::read(&demo().build_container(invalid))
+    );
+    let invalid = MemoryLayout {
+        back_screen_chars_address: Some(0xC800),
+        ..MemoryLayout::default()
+    };
+    assert_eq!(
+        Err(ContainerError::InvalidMemoryLayout(
+            EngineConfigError::ScreenOverlapsCharset
+        )),
+        DemoContainer::read(&demo().build_container(invalid))
+    );
+}
+
+#[test]
+fn container_rejects_invalid_sprite_pointer() {
+    let mut demo = demo();
+    demo.frame(
+        FrameBuilder::default()
+            .push(Command::SetSpriteData(SetSpriteData {
+                pointer: 0,
+                data: [0xAA; SPRITE_DATA_SIZE],
+            }))
+            .build(),
+    );
+    // Sprite block 0 is at $4000, before the screen chars at $4800.
+    let engine_config = EngineConfig {
+        vic_bank: 1,
+        screen_slot: 2,
+        charset_slot: 3,
+        ..EngineConfig::default()
+    };
+    let container = DemoContainer::new(&demo, MemoryLayout::new(&engine_config, 0x1000));
+    assert_eq!(Ok(container.clone()), DemoContainer::read(&container.write()));
+
+    // Sprite block 0 is at $C000, where the default config stores the screen chars.
+    let mut invalid = container.clone();
+    invalid.memory_layout = MemoryLayout::default();
+    assert_eq!(
+        Err(ContainerError::InvalidSpritePointer(0)),
+        DemoContainer::read(&invalid.write())
+    );
+}
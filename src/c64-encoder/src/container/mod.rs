@@ -0,0 +1,434 @@
+//! Demo file container.
+//!
+//! A container wraps the encoded demo with the information a player needs to check that it can play it. All values
+//! are stored in 6502 endian (LOW,HIGH).
+//!
+//! | Size             | Content                                                       |
+//! |------------------|---------------------------------------------------------------|
+//! | 4                | Magic `C64E`                                                  |
+//! | 1                | Version of the container format                               |
+//! | 1                | [TargetMode]                                                  |
+//! | 10               | [MemoryLayout]                                                |
+//! | 32               | Bitmap of the command types used by the demo                  |
+//! | 2                | Number of frames                                              |
+//! | 4 * frames       | Byte offset of each frame in the encoded demo                 |
+//! | 4                | Size of the encoded demo                                      |
+//! | size             | Encoded demo, as created by [DemoBuilder::build]             |
+//! | 4                | CRC-32 of all preceding bytes                                 |
+#[cfg(test)]
+mod container_test;
+
+use std::collections::BTreeSet;
+
+use crate::{
+    builder::demo::DemoBuilder,
+    command::{
+        all_decoder_modules,
+        modules::{EngineConfig, EngineConfigError},
+        Command, PACKED_COMMANDS, PACKED_COMMANDS_END,
+    },
+    decoder::{reader::Reader, DecodeError, DecodeResult, Decoder},
+    encoder::{writer::Writer, Encoder},
+};
+
+pub const MAGIC: [u8; 4] = *b"C64E";
+pub const VERSION: u8 = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContainerError {
+    /// Data doesn't start with the container magic.
+    InvalidMagic,
+    /// Container was written with a version of the format that isn't supported.
+    UnsupportedVersion(u8),
+    /// Container targets a video mode that isn't supported.
+    UnsupportedTargetMode(u8),
+    /// Content of the container doesn't match its checksum.
+    ChecksumMismatch { stored: u32, calculated: u32 },
+    /// Container or the demo inside it couldn't be decoded.
+    Decode(DecodeError),
+    /// Size of the demo doesn't match the size of the decoded demo.
+    DemoSizeMismatch { stored: usize, decoded: usize },
+    /// Number of entries in the frame offset table doesn't match the number of frames in the demo.
+    FrameCountMismatch { stored: usize, decoded: usize },
+    /// Frame offset table doesn't match the position of the frame in the demo.
+    FrameOffsetMismatch {
+        frame: usize,
+        stored: usize,
+        decoded: usize,
+    },
+    /// Demo uses a command type that isn't listed in the header.
+    UndeclaredCommandType(u8),
+    /// Header lists a command type that isn't supported by the engine.
+    UnsupportedCommandType(u8),
+    /// Addresses of the memory layout cannot be used by the engine, or the demo loaded at the load address overlaps
+    /// them.
+    InvalidMemoryLayout(EngineConfigError),
+    /// Demo writes sprite data to a block that cannot be used with the memory layout, see
+    /// [EngineConfig::is_valid_sprite_pointer].
+    InvalidSpritePointer(u8),
+}
+
+impl From<DecodeError> for ContainerError {
+    fn from(error: DecodeError) -> Self {
+        ContainerError::Decode(error)
+    }
+}
+
+pub type ContainerResult<T> = Result<T, ContainerError>;
+
+/// Video mode the demo is encoded for.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum TargetMode {
+    #[default]
+    TextMode,
+}
+
+impl From<TargetMode> for u8 {
+    fn from(target_mode: TargetMode) -> u8 {
+        match target_mode {
+            TargetMode::TextMode => 0,
+        }
+    }
+}
+
+/// Memory the engine expects the demo and the VIC-II data at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryLayout {
+    /// Address the encoded demo is loaded at. 0 when the demo is linked with the engine at the `engine_data` label.
+    pub load_address: u16,
+    pub screen_chars_address: u16,
+    /// Screen chars address of the second screen buffer when the engine is double buffered. Stored as 0 when not
+    /// double buffered.
+    pub back_screen_chars_address: Option<u16>,
+    pub charset_address: u16,
+    /// Start of the buffer packed commands are unpacked in.
+    pub depack_buffer_address: u16,
+}
+
+impl MemoryLayout {
+    /// Memory layout of the engine built with the given config.
+    pub fn new(config: &EngineConfig, load_address: u16) -> Self {
+        Self {
+            load_address,
+            screen_chars_address: config.screen_chars_address(),
+            back_screen_chars_address: config.is_double_buffered().then(|| config.back_screen_chars_address()),
+            charset_address: config.charset_address(),
+            depack_buffer_address: config.depack_buffer_address,
+        }
+    }
+
+    /// Engine config that places screen chars and charset at the addresses of the layout, using the default zero page
+    /// bases.
+    pub fn engine_config(&self) -> ContainerResult<EngineConfig> {
+        EngineConfig::from_addresses(
+            self.screen_chars_address,
+            self.back_screen_chars_address,
+            self.charset_address,
+            EngineConfig::default().zero_page_base,
+            EngineConfig::default().pointer_zero_page_base,
+            self.depack_buffer_address,
+        )
+        .map_err(ContainerError::InvalidMemoryLayout)
+    }
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        Self::new(&EngineConfig::default(), 0x0000)
+    }
+}
+
+impl Encoder for MemoryLayout {
+    fn byte_size(&self) -> usize {
+        size_of::<u16>() * 5
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        encoded_data
+            .add(&self.load_address)
+            .add(&self.screen_chars_address)
+            .add(&self.back_screen_chars_address.unwrap_or(0))
+            .add(&self.charset_address)
+            .add(&self.depack_buffer_address)
+    }
+}
+
+impl Decoder for MemoryLayout {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (load_address, encoded_data) = encoded_data.read()?;
+        let (screen_chars_address, encoded_data) = encoded_data.read()?;
+        let (back_screen_chars_address, encoded_data) = encoded_data.read::<u16>()?;
+        let (charset_address, encoded_data) = encoded_data.read()?;
+        let (depack_buffer_address, encoded_data) = encoded_data.read()?;
+        Ok((
+            MemoryLayout {
+                load_address,
+                screen_chars_address,
+                back_screen_chars_address: (back_screen_chars_address != 0).then_some(back_screen_chars_address),
+                charset_address,
+                depack_buffer_address,
+            },
+            encoded_data,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DemoContainer {
+    pub target_mode: TargetMode,
+    pub memory_layout: MemoryLayout,
+    /// Command types used by the demo, in ascending order.
+    pub command_types: Vec<u8>,
+    /// Byte offset of each frame in the encoded demo.
+    pub frame_offsets: Vec<u32>,
+    /// Encoded demo, as created by [DemoBuilder::build].
+    pub demo_bytes: Vec<u8>,
+}
+
+impl DemoContainer {
+    /// Panics when the demo writes sprite data to a block that cannot be used with a valid memory layout.
+    pub fn new(demo: &DemoBuilder, memory_layout: MemoryLayout) -> Self {
+        if let Some(pointer) = memory_layout
+            .engine_config()
+            .ok()
+            .and_then(|config| invalid_sprite_pointer(demo, &config))
+        {
+            panic!("sprite pointer {pointer} cannot be used with the memory layout");
+        }
+        Self {
+            target_mode: TargetMode::TextMode,
+            memory_layout,
+            command_types: command_types(demo),
+            frame_offsets: frame_offsets(demo),
+            demo_bytes: demo.build(),
+        }
+    }
+
+    /// Encode the container.
+    pub fn write(&self) -> Vec<u8> {
+        let mut result = vec![0; self.byte_size()];
+        self.encode(&mut result);
+        result
+    }
+
+    /// Decode a container and check that the header matches the demo inside it.
+    pub fn read(container_bytes: &[u8]) -> ContainerResult<DemoContainer> {
+        let (magic, encoded_data) = container_bytes.read::<[u8; 4]>()?;
+        if magic != MAGIC {
+            return Err(ContainerError::InvalidMagic);
+        }
+        let (version, encoded_data) = encoded_data.read::<u8>()?;
+        if version != VERSION {
+            return Err(ContainerError::UnsupportedVersion(version));
+        }
+        let checksum_offset = container_bytes
+            .len()
+            .checked_sub(size_of::<u32>())
+            .ok_or(DecodeError::UnexpectedEnd)?;
+        let (stored, _) = container_bytes[checksum_offset..].read::<u32>()?;
+        let calculated = crc32(&container_bytes[..checksum_offset]);
+        if stored != calculated {
+            return Err(ContainerError::ChecksumMismatch { stored, calculated });
+        }
+
+        let (target_mode, encoded_data) = encoded_data.read::<u8>()?;
+        let target_mode = match target_mode {
+            0 => TargetMode::TextMode,
+            _ => return Err(ContainerError::UnsupportedTargetMode(target_mode)),
+        };
+        let (memory_layout, encoded_data) = encoded_data.read::<MemoryLayout>()?;
+        memory_layout.engine_config()?;
+        let (command_type_bitmap, encoded_data) = encoded_data.read::<[u8; 32]>()?;
+        let command_types = (0..=255_u8)
+            .filter(|command_type| command_type_bitmap[*command_type as usize / 8] & (1 << (command_type % 8)) != 0)
+            .collect::<Vec<u8>>();
+        let (num_frames, mut encoded_data) = encoded_data.read::<u16>()?;
+        let mut frame_offsets = Vec::with_capacity(num_frames as usize);
+        for _ in 0..num_frames {
+            let frame_offset;
+            (frame_offset, encoded_data) = encoded_data.read::<u32>()?;
+            frame_offsets.push(frame_offset);
+        }
+        let (demo_size, encoded_data) = encoded_data.read::<u32>()?;
+        let demo_size = demo_size as usize;
+        if encoded_data.len() < demo_size + size_of::<u32>() {
+            return Err(DecodeError::UnexpectedEnd.into());
+        }
+
+        let container = DemoContainer {
+            target_mode,
+            memory_layout,
+            command_types,
+            frame_offsets,
+            demo_bytes: encoded_data[..demo_size].to_vec(),
+        };
+        container.check_demo()?;
+        Ok(container)
+    }
+
+    /// Decode the demo inside the container.
+    pub fn demo(&self) -> ContainerResult<DemoBuilder> {
+        let (demo, remaining) = self.demo_bytes.read::<DemoBuilder>()?;
+        if !remaining.is_empty() {
+            return Err(ContainerError::DemoSizeMismatch {
+                stored: self.demo_bytes.len(),
+                decoded: self.demo_bytes.len() - remaining.len(),
+            });
+        }
+        Ok(demo)
+    }
+
+    /// Check that the header matches the demo and that the engine supports all used command types.
+    fn check_demo(&self) -> ContainerResult<()> {
+        let supported_command_types = all_decoder_modules()
+            .into_iter()
+            .map(|(command_type, _)| command_type)
+            .collect::<BTreeSet<u8>>();
+        if let Some(command_type) = self
+            .command_types
+            .iter()
+            .find(|command_type| !supported_command_types.contains(command_type))
+        {
+            return Err(ContainerError::UnsupportedCommandType(*command_type));
+        }
+
+        let config = self.memory_layout.engine_config()?;
+        if self.memory_layout.load_address != 0 {
+            config
+                .check_program(self.memory_layout.load_address, self.demo_bytes.len())
+                .map_err(ContainerError::InvalidMemoryLayout)?;
+        }
+        let demo = self.demo()?;
+        if let Some(pointer) = invalid_sprite_pointer(&demo, &config) {
+            return Err(ContainerError::InvalidSpritePointer(pointer));
+        }
+        if let Some(command_type) = command_types(&demo)
+            .into_iter()
+            .find(|command_type| !self.command_types.contains(command_type))
+        {
+            return Err(ContainerError::UndeclaredCommandType(command_type));
+        }
+        let decoded_frame_offsets = frame_offsets(&demo);
+        if decoded_frame_offsets.len() != self.frame_offsets.len() {
+            return Err(ContainerError::FrameCountMismatch {
+                stored: self.frame_offsets.len(),
+                decoded: decoded_frame_offsets.len(),
+            });
+        }
+        if let Some((frame, (stored, decoded))) = self
+            .frame_offsets
+            .iter()
+            .zip(decoded_frame_offsets)
+            .enumerate()
+            .find(|(_, (stored, decoded))| **stored != *decoded)
+        {
+            return Err(ContainerError::FrameOffsetMismatch {
+                frame,
+                stored: *stored as usize,
+                decoded: decoded as usize,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Encoder for DemoContainer {
+    fn byte_size(&self) -> usize {
+        MAGIC.len()
+            + size_of::<u8>() * 2
+            + self.memory_layout.byte_size()
+            + 32
+            + size_of::<u16>()
+            + self.frame_offsets.len() * size_of::<u32>()
+            + size_of::<u32>()
+            + self.demo_bytes.len()
+            + size_of::<u32>()
+    }
+
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        let byte_size = self.byte_size();
+        let mut command_type_bitmap = [0_u8; 32];
+        for command_type in &self.command_types {
+            command_type_bitmap[*command_type as usize / 8] |= 1 << (command_type % 8);
+        }
+
+        let (container_bytes, remaining) = encoded_data.split_at_mut(byte_size);
+        container_bytes[..MAGIC.len()].copy_from_slice(&MAGIC);
+        let mut content = container_bytes[MAGIC.len()..]
+            .add(&VERSION)
+            .add(&u8::from(self.target_mode))
+            .add(&self.memory_layout);
+        content[..command_type_bitmap.len()].copy_from_slice(&command_type_bitmap);
+        content = content[command_type_bitmap.len()..].add(&(self.frame_offsets.len() as u16));
+        for frame_offset in &self.frame_offsets {
+            content = content.add(frame_offset);
+        }
+        content = content.add(&(self.demo_bytes.len() as u32));
+        content[..self.demo_bytes.len()].copy_from_slice(&self.demo_bytes);
+
+        let checksum_offset = byte_size - size_of::<u32>();
+        let checksum = crc32(&container_bytes[..checksum_offset]);
+        container_bytes[checksum_offset..].add(&checksum);
+        remaining
+    }
+}
+
+/// Command types used by the demo, in ascending order.
+///
+/// Includes the command types inside packed commands, and [PACKED_COMMANDS_END] that terminates them.
+fn command_types(demo: &DemoBuilder) -> Vec<u8> {
+    demo.frames
+        .iter()
+        .flat_map(|frame| &frame.commands)
+        .flat_map(|command| match command {
+            Command::PackedCommands(packed_commands) => packed_commands
+                .commands()
+                .iter()
+                .map(Command::command_type)
+                .chain([PACKED_COMMANDS, PACKED_COMMANDS_END])
+                .collect(),
+            command => vec![command.command_type()],
+        })
+        .collect::<BTreeSet<u8>>()
+        .into_iter()
+        .collect()
+}
+
+/// First sprite pointer the demo writes sprite data to that cannot be used with the engine config.
+fn invalid_sprite_pointer(demo: &DemoBuilder, config: &EngineConfig) -> Option<u8> {
+    demo.frames
+        .iter()
+        .flat_map(|frame| &frame.commands)
+        .flat_map(|command| match command {
+            Command::PackedCommands(packed_commands) => packed_commands.commands().iter().collect(),
+            command => vec![command],
+        })
+        .find_map(|command| match command {
+            Command::SetSpriteData(set_sprite_data) if !config.is_valid_sprite_pointer(set_sprite_data.pointer) => {
+                Some(set_sprite_data.pointer)
+            }
+            _ => None,
+        })
+}
+
+fn frame_offsets(demo: &DemoBuilder) -> Vec<u32> {
+    (0..demo.frames.len())
+        .map(|frame| demo.frame_offset(frame) as u32)
+        .collect()
+}
+
+/// CRC-32 (ISO-HDLC), as used by zip and png.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFF_u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,250 @@
+use std::ops::RangeInclusive;
+
+use c64_colors::colors::Color;
+use proptest::{collection::vec, prelude::*};
+
+use crate::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder},
+    charmap::encoding::decode_char,
+    command::{
+        clear_screen_chars::ClearScreenChars,
+        clear_screen_colors::ClearScreenColors,
+        frame_loop::{LoopEnd, LoopStart},
+        goto_frame::GotoFrame,
+        modules::EngineConfig,
+        packed_commands::PackedCommands,
+        partial_update_text_mode::{PartialUpdateTextModeScreen, UpdateSingleChar},
+        scan_order::ScanOrder,
+        set_border_color::SetBorderColor,
+        set_palette4::SetPalette4,
+        set_sprite_colors::SetSpriteColors,
+        set_sprite_data::{SetSpriteData, SPRITE_DATA_SIZE},
+        set_sprite_enable::SetSpriteEnable,
+        set_sprite_pointers::SetSpritePointers,
+        set_sprite_positions::{SetSpritePositions, SpritePosition},
+        update_chars::{UpdateChar, UpdateCharsU16Encoded},
+        update_chars_ranged::{UpdateCharRanged, UpdateCharsRangedU16Encoded},
+        update_chars_ranged_raw::UpdateCharsRangedRaw,
+        update_chars_raw::UpdateCharsRaw,
+        update_screen_chars_rle::{RLECommand, RLEPacket, UpdateScreenCharsRLE},
+        update_screen_chars_rle_extended::UpdateScreenCharsRLEExtended,
+        update_screen_colors::UpdateScreenColors,
+        update_screen_colors_rle::UpdateScreenColorsRLE,
+        update_text_mode_screen::UpdateTextModeScreen,
+        wait_frames::WaitFrames,
+        Command, PACKED_COMMANDS, PACKED_COMMANDS_END, UPDATE_SCREEN_CHARS_RLE_EXTENDED,
+    },
+    decoder::{reader::Reader, DecodeError},
+    encoder::Encoder,
+    lz,
+};
+
+fn encode(encoder: &impl Encoder) -> Vec<u8> {
+    let mut encoded_data = vec![0; encoder.byte_size()];
+    encoder.encode(&mut encoded_data);
+    encoded_data
+}
+
+fn color() -> impl Strategy<Value = Color> {
+    (0_u8..16).prop_map(Color::from)
+}
+
+/// Chars that can be stored losslessly using the u16 encoding.
+fn u16_char() -> impl Strategy<Value = u64> {
+    any::<u16>().prop_map(decode_char)
+}
+
+fn bytes<const N: usize>() -> impl Strategy<Value = [u8; N]> {
+    vec(any::<u8>(), N).prop_map(|bytes| bytes.try_into().unwrap())
+}
+
+fn rle_packet(num_screen_chars: RangeInclusive<u8>) -> impl Strategy<Value = RLEPacket> {
+    num_screen_chars.prop_flat_map(|num_screen_chars| {
+        prop_oneof![
+            any::<u8>().prop_map(RLECommand::UpdateWithSingleValue),
+            vec(any::<u8>(), num_screen_chars as usize).prop_map(RLECommand::UpdateValues),
+            Just(RLECommand::SkipValues),
+            any::<u8>().prop_map(RLECommand::AutoIncrement),
+        ]
+        .prop_map(move |command| RLEPacket {
+            num_screen_chars,
+            command,
+        })
+    })
+}
+
+/// Commands that can be packed.
+fn unpacked_command() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        any::<u8>().prop_map(|screen_char| Command::ClearScreenChars(ClearScreenChars { screen_char })),
+        prop::array::uniform4(color()).prop_map(|palette| Command::SetPalette4(SetPalette4 { palette })),
+        color().prop_map(|color| Command::SetBorderColor(SetBorderColor { color })),
+        color().prop_map(|color| Command::ClearScreenColors(ClearScreenColors { color })),
+        any::<u8>().prop_map(|num_frames| Command::WaitFrames(WaitFrames { num_frames })),
+        any::<u16>().prop_map(|offset| Command::GotoFrame(GotoFrame { offset })),
+        Just(Command::LoopStart(LoopStart {})),
+        any::<u8>().prop_map(|count| Command::LoopEnd(LoopEnd { count })),
+        vec((any::<u8>(), u16_char()), 1..=256).prop_map(|chars| {
+            Command::UpdateCharsU16Encoded(UpdateCharsU16Encoded {
+                chars: chars
+                    .into_iter()
+                    .map(|(char, data)| UpdateChar { char, data })
+                    .collect(),
+            })
+        }),
+        (any::<u8>(), vec(u16_char(), 1..=256)).prop_map(|(offset, chars)| {
+            Command::UpdateCharsRangedU16Encoded(UpdateCharsRangedU16Encoded {
+                offset,
+                chars: chars.into_iter().map(|data| UpdateCharRanged { data }).collect(),
+            })
+        }),
+        vec((any::<u8>(), any::<u64>()), 1..=256).prop_map(|chars| {
+            Command::UpdateCharsRaw(UpdateCharsRaw {
+                chars: chars
+                    .into_iter()
+                    .map(|(char, data)| UpdateChar { char, data })
+                    .collect(),
+            })
+        }),
+        any::<u8>()
+            .prop_flat_map(|offset| (Just(offset), vec(any::<u64>(), 1..=256 - offset as usize)))
+            .prop_map(|(offset, chars)| {
+                Command::UpdateCharsRangedRaw(UpdateCharsRangedRaw {
+                    offset,
+                    chars: chars.into_iter().map(|data| UpdateCharRanged { data }).collect(),
+                })
+            }),
+        bytes::<1000>().prop_map(|chars| Command::UpdateTextModeScreen(UpdateTextModeScreen { chars })),
+        vec((0_u16..1000, any::<u8>()), 0..300).prop_map(|changes| {
+            Command::PartialUpdateTextModeScreen(PartialUpdateTextModeScreen {
+                changes: changes
+                    .into_iter()
+                    .map(|(offset, char)| UpdateSingleChar { offset, char })
+                    .collect(),
+            })
+        }),
+        vec(rle_packet(0..=63), 0..32)
+            .prop_map(|rle_packets| Command::UpdateScreenCharsRLE(UpdateScreenCharsRLE { rle_packets })),
+        (
+            prop::sample::select(ScanOrder::ALL.to_vec()),
+            vec(rle_packet(1..=255), 0..32)
+        )
+            .prop_map(|(scan_order, rle_packets)| {
+                Command::UpdateScreenCharsRLEExtended(UpdateScreenCharsRLEExtended {
+                    scan_order,
+                    rle_packets,
+                })
+            }),
+        bytes::<1000>().prop_map(|colors| Command::UpdateScreenColors(UpdateScreenColors { colors })),
+        vec(rle_packet(0..=63), 0..32)
+            .prop_map(|rle_packets| Command::UpdateScreenColorsRLE(UpdateScreenColorsRLE { rle_packets })),
+        (
+            prop::sample::select(EngineConfig::default().valid_sprite_pointers()),
+            bytes::<SPRITE_DATA_SIZE>()
+        )
+            .prop_map(|(pointer, data)| Command::SetSpriteData(SetSpriteData { pointer, data })),
+        bytes::<8>().prop_map(|pointers| Command::SetSpritePointers(SetSpritePointers { pointers })),
+        prop::array::uniform8((0_u16..512, any::<u8>())).prop_map(|positions| Command::SetSpritePositions(
+            SetSpritePositions {
+                positions: positions.map(|(x, y)| SpritePosition { x, y }),
+            }
+        )),
+        prop::array::uniform8(color()).prop_map(|colors| Command::SetSpriteColors(SetSpriteColors { colors })),
+        any::<u8>().prop_map(|mask| Command::SetSpriteEnable(SetSpriteEnable { mask })),
+    ]
+}
+
+fn command() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        4 => unpacked_command(),
+        1 => vec(unpacked_command(), 0..4).prop_map(|commands| Command::PackedCommands(PackedCommands::new(commands))),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn decode_command(command in command()) {
+        let encoded_data = encode(&command);
+
+        let (decoded, remaining) = encoded_data.read::<Command>().unwrap();
+
+        prop_assert_eq!(command, decoded);
+        prop_assert!(remaining.is_empty());
+    }
+
+    #[test]
+    fn decode_demo(frames in vec(vec(command(), 0..4), 0..4)) {
+        let mut demo = DemoBuilder::default();
+        for commands in frames {
+            demo.frame(FrameBuilder { commands });
+        }
+        let encoded_data = demo.build();
+
+        let (decoded, remaining) = encoded_data.read::<DemoBuilder>().unwrap();
+
+        prop_assert_eq!(demo, decoded);
+        prop_assert!(remaining.is_empty());
+    }
+}
+
+#[test]
+fn decode_unknown_command_type() {
+    assert_eq!(
+        Err(DecodeError::UnknownCommandType(0xFF)),
+        [0xFF_u8, 0x00].read::<Command>()
+    );
+}
+
+#[test]
+fn decode_unexpected_end() {
+    let encoded_data = encode(&Command::SetSpritePointers(SetSpritePointers::default()));
+
+    assert_eq!(
+        Err(DecodeError::UnexpectedEnd),
+        encoded_data[..encoded_data.len() - 1].read::<Command>()
+    );
+}
+
+#[test]
+fn decode_invalid_extended_count() {
+    for num_screen_chars in [0, 1, 62] {
+        assert_eq!(
+            Err(DecodeError::InvalidExtendedCount(num_screen_chars)),
+            [UPDATE_SCREEN_CHARS_RLE_EXTENDED, 0x3F, num_screen_chars, 0x20, 0x00].read::<Command>()
+        );
+    }
+    assert!([UPDATE_SCREEN_CHARS_RLE_EXTENDED, 0x3F, 63, 0x20, 0x00]
+        .read::<Command>()
+        .is_ok());
+}
+
+#[test]
+fn decode_nested_packed_commands() {
+    let packed = Command::PackedCommands(PackedCommands::new(vec![Command::LoopStart(LoopStart {})]));
+    let mut unpacked_data = encode(&packed);
+    unpacked_data.push(PACKED_COMMANDS_END);
+    let mut encoded_data = vec![PACKED_COMMANDS];
+    encoded_data.extend(lz::pack(&unpacked_data));
+
+    assert_eq!(Err(DecodeError::InvalidPackedData), encoded_data.read::<Command>());
+}
+
+#[test]
+fn decode_frame_with_packed_commands() {
+    let frame = FrameBuilder::default()
+        .clear_screen_chars(0x20)
+        .update_text_mode_screen(UpdateTextModeScreen::filled(0x20))
+        .pack()
+        .wait_frames(1)
+        .build();
+    assert!(matches!(frame.commands[0], Command::PackedCommands(_)));
+    assert_eq!(5, frame.engine_command_count());
+    let encoded_data = encode(&frame);
+    assert_eq!(5, encoded_data[0]);
+
+    assert_eq!(frame, encoded_data.read::<FrameBuilder>().unwrap().0);
+    assert_eq!(
+        Err(DecodeError::InvalidPackedData),
+        [&[3, 0], &encoded_data[2..]].concat().read::<FrameBuilder>()
+    );
+}
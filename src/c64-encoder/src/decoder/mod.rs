@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod decoder_test;
+pub mod reader;
+
+use reader::Reader;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Encoded data ended before the value was completely decoded.
+    UnexpectedEnd,
+    /// Encoded data contains a command type that isn't known.
+    UnknownCommandType(u8),
+    /// Packed data is malformed: a match refers to data that hasn't been unpacked yet, or the unpacked commands
+    /// don't match the packed block.
+    InvalidPackedData,
+    /// Extended header of a run-length packet stores a number of screen chars that fits in the header byte.
+    InvalidExtendedCount(u8),
+}
+
+pub type DecodeResult<T> = Result<T, DecodeError>;
+
+/// Counterpart of [crate::encoder::Encoder].
+pub trait Decoder: Sized {
+    /// Decode a value from the start of the encoded data. Returns the value and the data after the value.
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])>;
+}
+
+impl Decoder for u16 {
+    // NOTE: stored in 6502 endian (LOW,HIGH)
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (bytes, encoded_data) = encoded_data.read::<[u8; 2]>()?;
+        Ok((u16::from_le_bytes(bytes), encoded_data))
+    }
+}
+
+impl Decoder for u32 {
+    // NOTE: stored in 6502 endian (LOW,...,HIGH)
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (bytes, encoded_data) = encoded_data.read::<[u8; 4]>()?;
+        Ok((u32::from_le_bytes(bytes), encoded_data))
+    }
+}
+
+impl Decoder for u8 {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        let (byte, encoded_data) = encoded_data.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+        Ok((*byte, encoded_data))
+    }
+}
+
+impl<const N: usize> Decoder for [u8; N] {
+    fn decode(encoded_data: &[u8]) -> DecodeResult<(Self, &[u8])> {
+        if encoded_data.len() < N {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (bytes, encoded_data) = encoded_data.split_at(N);
+        Ok((bytes.try_into().unwrap(), encoded_data))
+    }
+}
+
+/// Read the number of chars of a char update command. Updating all 256 chars is stored as 0.
+pub(crate) fn read_num_chars(encoded_data: &[u8]) -> DecodeResult<(usize, &[u8])> {
+    let (num_chars, encoded_data) = encoded_data.read::<u8>()?;
+    let num_chars = if num_chars == 0 { 256 } else { num_chars as usize };
+    Ok((num_chars, encoded_data))
+}
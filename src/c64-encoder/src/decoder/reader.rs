@@ -0,0 +1,11 @@
+use super::{DecodeResult, Decoder};
+
+pub trait Reader {
+    fn read<T: Decoder>(&self) -> DecodeResult<(T, &Self)>;
+}
+
+impl Reader for [u8] {
+    fn read<T: Decoder>(&self) -> DecodeResult<(T, &Self)> {
+        T::decode(self)
+    }
+}
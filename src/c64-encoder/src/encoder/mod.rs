@@ -23,6 +23,18 @@ impl Encoder for u16 {
     }
 }
 
+impl Encoder for u32 {
+    fn byte_size(&self) -> usize {
+        size_of::<u32>()
+    }
+
+    // NOTE: stores in 6502 endian (LOW,...,HIGH)
+    fn encode<'a>(&self, encoded_data: &'a mut [u8]) -> &'a mut [u8] {
+        encoded_data[..size_of::<u32>()].copy_from_slice(&self.to_le_bytes());
+        &mut encoded_data[size_of::<u32>()..]
+    }
+}
+
 impl Encoder for u8 {
     fn byte_size(&self) -> usize {
         size_of::<u8>()
@@ -1,161 +1,257 @@
+use std::{collections::HashSet, fmt::Arguments};
+
 use state::State;
 
 use crate::{
-    charmap::encoding::decode_char,
-    command::{
-        update_screen_chars_rle::{
-            RLE_MASK_AUTO_INCREMENT, RLE_MASK_BITS, RLE_MASK_SKIP_VALUES, RLE_MASK_UPDATE_VALUES,
-            RLE_MASK_UPDATE_WITH_SINGLE_VALUE,
-        },
-        CLEAR_SCREEN_CHAR, PARTIAL_UPDATE_TEXT_MODE_SCREEN, SET_PALETTE4, UPDATE_CHARS_RANGED_U16, UPDATE_CHARS_U16,
-        UPDATE_SCREEN_CHARS_RLE, UPDATE_TEXT_MODE_SCREEN,
-    },
+    command::{update_screen_chars_rle::RLEPacket, Command},
+    container::{ContainerError, DemoContainer},
+    decoder::{reader::Reader, DecodeError},
 };
 
 pub mod state;
 
-pub fn evaluate(demo_bytes: &[u8]) -> Vec<State> {
-    let mut current_ptr = 0;
-    println!("decoding {} demo-bytes", demo_bytes.len());
-    let num_frames = read_u16(demo_bytes, &mut current_ptr);
-    println!(" num_frames={num_frames}");
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EvaluateErrorReason {
+    /// Demo bytes could not be decoded.
+    Decode(DecodeError),
+    /// A goto frame command targets an offset that isn't the start of a frame.
+    UnknownFrameOffset(u16),
+    /// Demo container is invalid or doesn't match the demo inside it. The offset of the error is always 0.
+    Container(ContainerError),
+    /// A command writes outside the 1000 screen chars or colors, contains the largest screen offset it writes to.
+    ScreenOffsetOutOfRange(usize),
+}
+
+/// Error detected while evaluating a demo.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EvaluateError {
+    /// Byte offset in the demo bytes of the data that couldn't be evaluated.
+    pub offset: usize,
+    /// Index of the frame, `None` when the error is in the demo header.
+    pub frame: Option<usize>,
+    /// Index of the command inside the frame, `None` when the error is in the frame header.
+    pub command: Option<usize>,
+    pub reason: EvaluateErrorReason,
+}
+
+pub type EvaluateResult<T> = Result<T, EvaluateError>;
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct EvaluateOptions {
+    /// Model an engine that is double buffered, see [crate::command::modules::EngineConfig::back_screen_slot].
+    pub double_buffered: bool,
+}
+
+/// Number of screen chars and colors.
+const SCREEN_SIZE: usize = 1000;
+
+/// Frame with the byte offset of the frame and each of its commands.
+struct DecodedFrame {
+    offset: usize,
+    commands: Vec<(usize, Command)>,
+}
+
+/// Evaluate the demo and return the state after each frame. The first state is the state before the first frame.
+pub fn evaluate(demo_bytes: &[u8]) -> EvaluateResult<Vec<State>> {
+    evaluate_with_trace(demo_bytes, |_| {})
+}
+
+/// Evaluate the demo inside a [DemoContainer], double buffered when the memory layout of the container is.
+pub fn evaluate_container(container_bytes: &[u8]) -> EvaluateResult<Vec<State>> {
+    let container = DemoContainer::read(container_bytes).map_err(|error| EvaluateError {
+        offset: 0,
+        frame: None,
+        command: None,
+        reason: EvaluateErrorReason::Container(error),
+    })?;
+    let options = EvaluateOptions {
+        double_buffered: container.memory_layout.back_screen_chars_address.is_some(),
+    };
+    evaluate_with_options(&container.demo_bytes, options, |_| {})
+}
+
+/// Evaluate the demo like [evaluate], and send a line to the trace for each decoded frame and command.
+///
+/// `evaluate_with_trace(demo_bytes, |line| println!("{line}"))` prints the trace to stdout.
+pub fn evaluate_with_trace(demo_bytes: &[u8], trace: impl FnMut(Arguments)) -> EvaluateResult<Vec<State>> {
+    evaluate_with_options(demo_bytes, EvaluateOptions::default(), trace)
+}
+
+/// Evaluate the demo like [evaluate_with_trace], using the given options.
+///
+/// When double buffered, the text screen and sprite pointers of each state are the ones shown after the frame and
+/// [State::back_buffer] contains the buffer the next frame is written to.
+pub fn evaluate_with_options(
+    demo_bytes: &[u8],
+    options: EvaluateOptions,
+    mut trace: impl FnMut(Arguments),
+) -> EvaluateResult<Vec<State>> {
+    trace(format_args!("decoding {} demo-bytes", demo_bytes.len()));
+    let frames = decode_frames(demo_bytes)?;
+    trace(format_args!(" num_frames={}", frames.len()));
 
     let mut frame_states = vec![];
-    let mut state = State::default();
+    let mut state = if options.double_buffered {
+        State::double_buffered()
+    } else {
+        State::default()
+    };
     frame_states.push(state.clone());
 
-    for frame in 1..=num_frames {
-        println!("decoding frame={frame}");
-        let num_commands = read_u16(demo_bytes, &mut current_ptr);
-        println!(" num_commands={num_commands}");
+    let mut loop_start = 0;
+    let mut loop_iterations_left = 0_u8;
+    // Playback is deterministic, returning to a frame with the same loop state will repeat forever.
+    let mut visited = HashSet::new();
+    let mut frame_index = 0;
+
+    while frame_index < frames.len() {
+        if !visited.insert((frame_index, loop_start, loop_iterations_left)) {
+            trace(format_args!("detected endless loop at frame={}", frame_index + 1));
+            break;
+        }
+        let frame = frame_index + 1;
+        let commands = &frames[frame_index].commands;
+        trace(format_args!("decoding frame={frame}"));
+        trace(format_args!(" num_commands={}", commands.len()));
         state.reset();
-        for command_index in 1..=num_commands {
-            println!("decoding command={frame}.{command_index}");
-            let command_type = read_u8(demo_bytes, &mut current_ptr);
-            println!(" command_type={command_type}");
-            if command_type == UPDATE_CHARS_U16 {
-                println!(" command=UpdateCharsU16Encoded");
-                let num_chars = read_u8(demo_bytes, &mut current_ptr);
-                println!(" num_chars={num_chars}");
-                for _ in 0..num_chars {
-                    let char = read_u8(demo_bytes, &mut current_ptr);
-                    let encoded_char = read_u16(demo_bytes, &mut current_ptr);
-                    let decoded_char = decode_char(encoded_char);
-                    println!("  char={char:02X}, encoded={encoded_char:016b}, decoded={decoded_char:064b}");
-                    state.charset.update_char(char, decoded_char);
+        state.swap_screen_buffers();
+        state.frame = frame_index;
+        let mut next_frame_index = frame_index + 1;
+
+        // Packed commands are evaluated one by one, reported as the command index of the packed commands.
+        let unpacked_commands = commands
+            .iter()
+            .enumerate()
+            .flat_map(|(command_index, (command_offset, command))| match command {
+                Command::PackedCommands(packed_commands) => packed_commands
+                    .commands()
+                    .iter()
+                    .map(move |command| (command_index, (command_offset, command)))
+                    .collect::<Vec<_>>(),
+                command => vec![(command_index, (command_offset, command))],
+            });
+        for (command_index, (command_offset, command)) in unpacked_commands {
+            trace(format_args!("decoding command={frame}.{}", command_index + 1));
+            match command {
+                Command::GotoFrame(goto_frame) => {
+                    trace(format_args!(" command=GotoFrame, offset={}", goto_frame.offset));
+                    next_frame_index = frames
+                        .iter()
+                        .position(|frame| frame.offset == goto_frame.offset as usize)
+                        .ok_or(EvaluateError {
+                            offset: *command_offset,
+                            frame: Some(frame_index),
+                            command: Some(command_index),
+                            reason: EvaluateErrorReason::UnknownFrameOffset(goto_frame.offset),
+                        })?;
+                    break;
                 }
-            } else if command_type == UPDATE_CHARS_RANGED_U16 {
-                println!(" command=UpdateCharsRangedU16Encoded");
-                let num_chars = read_u8(demo_bytes, &mut current_ptr);
-                let offset = read_u8(demo_bytes, &mut current_ptr);
-                println!(" offset={offset}");
-                println!(" num_chars={num_chars}");
-                for char_index in 0..num_chars {
-                    let char = offset + char_index;
-                    let encoded_char = read_u16(demo_bytes, &mut current_ptr);
-                    let decoded_char = decode_char(encoded_char);
-                    println!("  char={char:02X}, encoded={encoded_char:016b}, decoded={decoded_char:064b}");
-                    state.charset.update_char(char, decoded_char);
+                Command::LoopStart(_) => {
+                    trace(format_args!(" command=LoopStart"));
+                    loop_start = frame_index;
                 }
-            } else if command_type == UPDATE_TEXT_MODE_SCREEN {
-                println!(" command=UpdateTextModeScreen");
-                println!("");
-                for y in 0..25 {
-                    for x in 0..40 {
-                        let char = read_u8(demo_bytes, &mut current_ptr);
-                        print!("{char:02X}");
-                        state.text_screen.screen_chars[y * 40 + x] = char;
+                Command::LoopEnd(loop_end) => {
+                    trace(format_args!(" command=LoopEnd, count={}", loop_end.count));
+                    if loop_iterations_left == 0 {
+                        loop_iterations_left = loop_end.count;
                     }
-                    println!("");
-                }
-            } else if command_type == PARTIAL_UPDATE_TEXT_MODE_SCREEN {
-                println!(" command=PartialUpdateTextModeScreen");
-                let num_changes = read_u16(demo_bytes, &mut current_ptr);
-                println!(" num_changes={num_changes}");
-                for _ in 0..num_changes {
-                    let offset = read_u16(demo_bytes, &mut current_ptr);
-                    let char = read_u8(demo_bytes, &mut current_ptr);
-                    println!("  offset={offset}, char={char:02X}");
-                    state.text_screen.screen_chars[offset as usize] = char;
-                }
-            } else if command_type == UPDATE_SCREEN_CHARS_RLE {
-                println!(" command=UpdateScreenCharsRLE");
-                let num_packets = read_u8(demo_bytes, &mut current_ptr);
-                println!(" num_packets={num_packets:03}");
-                let mut offset = 0;
-                for packet_number in 0..num_packets {
-                    let header = read_u8(demo_bytes, &mut current_ptr);
-                    let command_mask = header & RLE_MASK_BITS;
-                    let num_screen_chars = header - command_mask;
-                    print!("  packet={packet_number:03}, num_screen_chars={num_screen_chars:02}");
-                    match command_mask {
-                        RLE_MASK_UPDATE_WITH_SINGLE_VALUE => {
-                            let screen_char = read_u8(demo_bytes, &mut current_ptr);
-                            print!(", rle_command=RLECommand::UpdateWithSingleValue, screen_char={screen_char:02X}");
-                            for _ in 0..num_screen_chars {
-                                state.text_screen.screen_chars[offset] = screen_char;
-                                offset += 1;
-                            }
-                        }
-                        RLE_MASK_UPDATE_VALUES => {
-                            print!(", rle_command=RLECommand::UpdateValues, screen_chars=[");
-                            for _ in 0..num_screen_chars {
-                                let screen_char = read_u8(demo_bytes, &mut current_ptr);
-                                print!("{screen_char:02X},");
-
-                                state.text_screen.screen_chars[offset] = screen_char;
-                                offset += 1;
-                            }
-                            print!("]");
-                        }
-                        RLE_MASK_SKIP_VALUES => {
-                            print!(", rle_command=RLECommand::SkipValues");
-                            offset += num_screen_chars as usize;
-                        }
-                        RLE_MASK_AUTO_INCREMENT => {
-                            let mut screen_char = read_u8(demo_bytes, &mut current_ptr);
-                            print!(", rle_command=RLECommand::AutoIncrement, screen_char={screen_char:02X}");
-                            for _ in 0..num_screen_chars {
-                                state.text_screen.screen_chars[offset] = screen_char;
-                                offset += 1;
-                                screen_char += 1;
-                            }
-                        }
-                        _ => panic!(),
+                    loop_iterations_left = loop_iterations_left.wrapping_sub(1);
+                    if loop_iterations_left != 0 {
+                        next_frame_index = loop_start;
+                        break;
                     }
-                    println!();
                 }
-            } else if command_type == CLEAR_SCREEN_CHAR {
-                println!(" command=ClearScreenChar");
-                let char = read_u8(demo_bytes, &mut current_ptr);
-                println!(" screen_char={char:02X}");
-                state.text_screen.screen_chars = [char; 1000];
-            } else if command_type == SET_PALETTE4 {
-                println!(" command=SetPalette4");
-                let color13 = read_u8(demo_bytes, &mut current_ptr);
-                let color24 = read_u8(demo_bytes, &mut current_ptr);
-                println!(" color_0={:02X}", color13 & 0x0F);
-                println!(" color_1={:02X}", color24 & 0x0F);
-                println!(" color_2={:02X}", (color13 & 0xF0) >> 4);
-                println!(" color_3={:02X}", (color24 & 0xF0) >> 4);
-            } else {
-                panic!("detected an not implemented command type {command_type}");
+                command => {
+                    trace(format_args!(" command={command:?}"));
+                    state.apply(command);
+                }
             }
         }
         state.mark_used();
         frame_states.push(state.clone());
+        frame_index = next_frame_index;
     }
 
-    frame_states
+    Ok(frame_states)
 }
 
-fn read_u8(demo_bytes: &[u8], current_ptr: &mut usize) -> u8 {
-    let result = demo_bytes[*current_ptr];
-    *current_ptr += 1;
-    result
+/// Decode all frames of the demo, keeping track of the byte offsets for error reporting and goto frame commands.
+fn decode_frames(demo_bytes: &[u8]) -> EvaluateResult<Vec<DecodedFrame>> {
+    let offset_of = |encoded_data: &[u8]| demo_bytes.len() - encoded_data.len();
+    let error = |encoded_data: &[u8], frame, command, error| EvaluateError {
+        offset: offset_of(encoded_data),
+        frame,
+        command,
+        reason: EvaluateErrorReason::Decode(error),
+    };
+
+    let (num_frames, mut encoded_data) = demo_bytes.read::<u16>().map_err(|e| error(demo_bytes, None, None, e))?;
+    let mut frames = Vec::with_capacity(num_frames as usize);
+    for frame_index in 0..num_frames as usize {
+        let offset = offset_of(encoded_data);
+        let num_commands;
+        (num_commands, encoded_data) = encoded_data
+            .read::<u16>()
+            .map_err(|e| error(encoded_data, Some(frame_index), None, e))?;
+        let mut commands_left = num_commands as usize;
+        let mut commands = Vec::with_capacity(commands_left);
+        while commands_left > 0 {
+            let command_index = commands.len();
+            let command_offset = offset_of(encoded_data);
+            let command;
+            (command, encoded_data) = encoded_data
+                .read::<Command>()
+                .map_err(|e| error(encoded_data, Some(frame_index), Some(command_index), e))?;
+            commands_left = commands_left
+                .checked_sub(command.engine_command_count())
+                .ok_or_else(|| {
+                    error(
+                        &demo_bytes[command_offset..],
+                        Some(frame_index),
+                        Some(command_index),
+                        DecodeError::InvalidPackedData,
+                    )
+                })?;
+            if let Some(screen_offset) = screen_offset_out_of_range(&command) {
+                return Err(EvaluateError {
+                    offset: command_offset,
+                    frame: Some(frame_index),
+                    command: Some(command_index),
+                    reason: EvaluateErrorReason::ScreenOffsetOutOfRange(screen_offset),
+                });
+            }
+            commands.push((command_offset, command));
+        }
+        frames.push(DecodedFrame { offset, commands });
+    }
+    Ok(frames)
+}
+
+/// Largest screen offset the command writes to when it is outside the screen, for commands that write to the screen
+/// chars or colors at an offset.
+fn screen_offset_out_of_range(command: &Command) -> Option<usize> {
+    let last_offset = match command {
+        Command::PartialUpdateTextModeScreen(partial_update) => partial_update
+            .changes
+            .iter()
+            .map(|change| change.offset as usize)
+            .max()?,
+        Command::UpdateScreenCharsRLE(update) => last_rle_offset(&update.rle_packets)?,
+        Command::UpdateScreenCharsRLEExtended(update) => last_rle_offset(&update.rle_packets)?,
+        Command::UpdateScreenColorsRLE(update) => last_rle_offset(&update.rle_packets)?,
+        Command::PackedCommands(packed_commands) => {
+            return packed_commands.commands().iter().find_map(screen_offset_out_of_range)
+        }
+        _ => return None,
+    };
+    (last_offset >= SCREEN_SIZE).then_some(last_offset)
 }
 
-fn read_u16(demo_bytes: &[u8], current_ptr: &mut usize) -> u16 {
-    let result = read_u8(demo_bytes, current_ptr) as u16 | read_u8(demo_bytes, current_ptr) as u16 * 256;
-    result
+/// Largest screen offset covered by the packets, `None` when they don't cover any screen char.
+fn last_rle_offset(rle_packets: &[RLEPacket]) -> Option<usize> {
+    rle_packets
+        .iter()
+        .map(|rle_packet| rle_packet.num_screen_chars as usize)
+        .sum::<usize>()
+        .checked_sub(1)
 }
@@ -1,10 +1,27 @@
 use c64::image_container::Image;
 use c64_colors::colors::{Color, SRGB};
 
+use crate::command::{
+    set_sprite_data::SPRITE_DATA_SIZE,
+    set_sprite_positions::SpritePosition,
+    update_screen_chars_rle::{RLECommand, RLEPacket},
+    Command,
+};
+
 #[derive(Debug, Default, Clone)]
 pub struct State {
     pub charset: CharSet,
     pub text_screen: TextScreen,
+    pub color_ram: ColorRAM,
+    pub vic2_colors: VIC2Colors,
+    pub sprites: Sprites,
+    /// Screen buffer that isn't shown, only used when the engine is double buffered.
+    pub back_buffer: Option<ScreenBuffer>,
+    /// Number of video frames this state is displayed, assuming the engine processes one frame per video frame.
+    pub duration: usize,
+    /// Index of the demo frame that was processed last. Frames can be played more than once due to loops and goto
+    /// frame commands.
+    pub frame: usize,
 }
 
 impl State {
@@ -15,6 +32,139 @@ impl State {
     }
     pub fn reset(&mut self) {
         self.charset.reset();
+        self.duration = 1;
+    }
+
+    /// State before the first frame when the engine is double buffered.
+    pub fn double_buffered() -> Self {
+        Self {
+            back_buffer: Some(ScreenBuffer::default()),
+            ..Self::default()
+        }
+    }
+
+    /// Swap the shown screen chars and sprite pointers with the back buffer. Does nothing when not double buffered.
+    ///
+    /// Called before the commands of a frame are applied, so the commands update the screen that was shown two
+    /// frames earlier.
+    pub fn swap_screen_buffers(&mut self) {
+        if let Some(back_buffer) = &mut self.back_buffer {
+            std::mem::swap(&mut self.text_screen, &mut back_buffer.text_screen);
+            std::mem::swap(&mut self.sprites.pointers, &mut back_buffer.sprite_pointers);
+        }
+    }
+
+    /// Apply the effect of the given command to this state.
+    pub fn apply(&mut self, command: &Command) {
+        match command {
+            Command::ClearScreenChars(clear_screen_chars) => {
+                self.text_screen.screen_chars = [clear_screen_chars.screen_char; 1000];
+            }
+            Command::SetPalette4(set_palette4) => {
+                self.vic2_colors.border = u8::from(set_palette4.palette[0]);
+                self.vic2_colors.background = u8::from(set_palette4.palette[0]);
+                self.color_ram.colors = [u8::from(set_palette4.palette[1]); 1000];
+            }
+            Command::SetBorderColor(set_border_color) => {
+                self.vic2_colors.border = u8::from(set_border_color.color);
+            }
+            Command::ClearScreenColors(clear_screen_colors) => {
+                self.color_ram.colors = [u8::from(clear_screen_colors.color); 1000];
+            }
+            Command::WaitFrames(wait_frames) => {
+                self.duration += wait_frames.num_frames as usize;
+            }
+            Command::GotoFrame(_) | Command::LoopStart(_) | Command::LoopEnd(_) => {
+                // Changes the order of the frames and is handled by the evaluator.
+            }
+            Command::PackedCommands(packed_commands) => {
+                for command in packed_commands.commands() {
+                    self.apply(command);
+                }
+            }
+            Command::UpdateCharsU16Encoded(update_chars) => {
+                for char in &update_chars.chars {
+                    self.charset.update_char(char.char, char.data);
+                }
+            }
+            Command::UpdateCharsRangedU16Encoded(update_chars) => {
+                for (char_index, char) in update_chars.chars.iter().enumerate() {
+                    self.charset
+                        .update_char(update_chars.offset.wrapping_add(char_index as u8), char.data);
+                }
+            }
+            Command::UpdateCharsRaw(update_chars) => {
+                for char in &update_chars.chars {
+                    self.charset.update_char(char.char, char.data);
+                }
+            }
+            Command::UpdateCharsRangedRaw(update_chars) => {
+                for (char_index, char) in update_chars.chars.iter().enumerate() {
+                    self.charset
+                        .update_char(update_chars.offset.wrapping_add(char_index as u8), char.data);
+                }
+            }
+            Command::UpdateTextModeScreen(update_text_mode_screen) => {
+                self.text_screen.screen_chars = update_text_mode_screen.chars;
+            }
+            Command::PartialUpdateTextModeScreen(partial_update) => {
+                for change in &partial_update.changes {
+                    self.text_screen.screen_chars[change.offset as usize] = change.char;
+                }
+            }
+            Command::UpdateScreenCharsRLE(update_screen_chars_rle) => {
+                apply_rle(&update_screen_chars_rle.rle_packets, &mut self.text_screen.screen_chars);
+            }
+            Command::UpdateScreenCharsRLEExtended(update_screen_chars_rle) => {
+                let scan_order = update_screen_chars_rle.scan_order;
+                let mut screen_chars = scan_order.gather(&self.text_screen.screen_chars);
+                apply_rle(&update_screen_chars_rle.rle_packets, &mut screen_chars);
+                scan_order.scatter(&screen_chars, &mut self.text_screen.screen_chars);
+            }
+            Command::UpdateScreenColors(update_screen_colors) => {
+                self.color_ram.colors = update_screen_colors.colors.map(|color| color & 0x0F);
+            }
+            Command::UpdateScreenColorsRLE(update_screen_colors_rle) => {
+                apply_rle(&update_screen_colors_rle.rle_packets, &mut self.color_ram.colors);
+                for color in &mut self.color_ram.colors {
+                    *color &= 0x0F;
+                }
+            }
+            Command::SetSpriteData(set_sprite_data) => {
+                self.sprites.data[set_sprite_data.pointer as usize] = set_sprite_data.data;
+            }
+            Command::SetSpritePointers(set_sprite_pointers) => {
+                self.sprites.pointers = set_sprite_pointers.pointers;
+            }
+            Command::SetSpritePositions(set_sprite_positions) => {
+                self.sprites.positions = set_sprite_positions.positions;
+            }
+            Command::SetSpriteColors(set_sprite_colors) => {
+                self.sprites.colors = set_sprite_colors.colors.map(u8::from);
+            }
+            Command::SetSpriteEnable(set_sprite_enable) => {
+                self.sprites.enable = set_sprite_enable.mask;
+            }
+        }
+    }
+}
+
+fn apply_rle(rle_packets: &[RLEPacket], destination: &mut [u8]) {
+    let mut offset = 0;
+    for rle_packet in rle_packets {
+        let num_screen_chars = rle_packet.num_screen_chars as usize;
+        let range = offset..offset + num_screen_chars;
+        match &rle_packet.command {
+            RLECommand::UpdateWithSingleValue(value) => destination[range].fill(*value),
+            RLECommand::UpdateValues(values) => destination[range].copy_from_slice(values),
+            RLECommand::SkipValues => {}
+            RLECommand::AutoIncrement(start_value) => {
+                for (index, value) in destination[range].iter_mut().enumerate() {
+                    *value = start_value.wrapping_add(index as u8);
+                }
+            }
+        }
+        offset += num_screen_chars;
     }
 }
 
@@ -47,6 +197,9 @@ impl CharSet {
     pub fn mark_used(&mut self, index: u8) {
         self.is_used[index as usize] = true;
     }
+    pub fn char(&self, index: u8) -> u64 {
+        self.chars[index as usize]
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -61,34 +214,146 @@ impl Default for TextScreen {
     }
 }
 
+/// Screen RAM contents that are double buffered: the screen chars and the sprite pointers stored after them.
+#[derive(Debug, Default, Clone)]
+pub struct ScreenBuffer {
+    pub text_screen: TextScreen,
+    pub sprite_pointers: [u8; 8],
+}
+
+/// Foreground color of each screen char. Only the lower 4 bits of each color are used.
+///
+/// Defaults to the color the KERNAL uses to clear the screen.
+#[derive(Debug, Clone)]
+pub struct ColorRAM {
+    pub colors: [u8; 1000],
+}
+impl Default for ColorRAM {
+    fn default() -> Self {
+        Self {
+            colors: [u8::from(Color::LightBlue); 1000],
+        }
+    }
+}
+
+/// Color registers of the VIC-II.
+///
+/// Defaults to the colors set by the KERNAL at power on.
+#[derive(Debug, Clone)]
+pub struct VIC2Colors {
+    pub border: u8,
+    pub background: u8,
+}
+impl Default for VIC2Colors {
+    fn default() -> Self {
+        Self {
+            border: u8::from(Color::LightBlue),
+            background: u8::from(Color::Blue),
+        }
+    }
+}
+
+/// Horizontal sprite position of the first pixel of the text screen.
+const SPRITE_SCREEN_OFFSET_X: usize = 24;
+/// Vertical sprite position of the first pixel of the text screen.
+const SPRITE_SCREEN_OFFSET_Y: usize = 50;
+
+#[derive(Debug, Clone)]
+pub struct Sprites {
+    /// Sprite data of each sprite pointer.
+    pub data: Vec<[u8; SPRITE_DATA_SIZE]>,
+    pub pointers: [u8; 8],
+    pub positions: [SpritePosition; 8],
+    pub colors: [u8; 8],
+    pub enable: u8,
+}
+
+impl Default for Sprites {
+    fn default() -> Self {
+        Self {
+            data: vec![[0; SPRITE_DATA_SIZE]; 256],
+            pointers: [0; 8],
+            positions: [SpritePosition::default(); 8],
+            colors: [0; 8],
+            enable: 0,
+        }
+    }
+}
+
+impl Sprites {
+    /// Get the color of the sprite pixel at the given text screen position. Sprite 0 is drawn on top of the other
+    /// sprites.
+    fn get_pixel_color(&self, x: usize, y: usize) -> Option<SRGB> {
+        (0..8)
+            .filter(|sprite| self.enable & (1 << sprite) != 0)
+            .find_map(|sprite| {
+                let position = self.positions[sprite];
+                let sprite_x = (x + SPRITE_SCREEN_OFFSET_X).checked_sub(position.x as usize)?;
+                let sprite_y = (y + SPRITE_SCREEN_OFFSET_Y).checked_sub(position.y as usize)?;
+                if sprite_x >= 24 || sprite_y >= 21 {
+                    return None;
+                }
+                let byte = self.data[self.pointers[sprite] as usize][sprite_y * 3 + sprite_x / 8];
+                if byte & (0x80 >> (sprite_x % 8)) != 0 {
+                    Some(SRGB::from(Color::from(self.colors[sprite])))
+                } else {
+                    None
+                }
+            })
+    }
+}
+
+/// Width of the left and right border in the rendered image.
+pub const BORDER_WIDTH: usize = 32;
+/// Height of the top and bottom border in the rendered image.
+pub const BORDER_HEIGHT: usize = 36;
+const SCREEN_WIDTH: usize = 320 + 2 * BORDER_WIDTH;
+const SCREEN_HEIGHT: usize = 200 + 2 * BORDER_HEIGHT;
+
+/// Renders the screen including the border, with the charset next to it.
 impl Image for State {
     fn width(&self) -> usize {
-        320 + 200
+        SCREEN_WIDTH + 128 + 15 + 15
     }
 
     fn height(&self) -> usize {
-        200
+        SCREEN_HEIGHT
     }
 
     fn get_pixel_color(&self, x: usize, y: usize) -> c64_colors::colors::SRGB {
-        let mut result = SRGB::from(Color::Black);
-        if (0..320).contains(&x) && (0..200).contains(&y) {
-            result = self.text_screen.get_pixel_color(x, y, &self.charset);
+        if x >= SCREEN_WIDTH {
+            let x = x - SCREEN_WIDTH;
+            if y < self.charset.height() {
+                return self.charset.get_pixel_color(x, y);
+            }
+            return SRGB::from(Color::Black);
         }
-        if (320..(320 + 128 + 15 + 15)).contains(&x) && (0..(128 + 15 + 15)).contains(&y) {
-            result = self.charset.get_pixel_color(x - 320, y);
+        let screen_x = x.wrapping_sub(BORDER_WIDTH);
+        let screen_y = y.wrapping_sub(BORDER_HEIGHT);
+        if screen_x >= 320 || screen_y >= 200 {
+            return SRGB::from(Color::from(self.vic2_colors.border));
         }
-        result
+        self.sprites.get_pixel_color(screen_x, screen_y).unwrap_or_else(|| {
+            let color = self
+                .text_screen
+                .get_pixel_color(screen_x, screen_y, &self.charset, &self.color_ram);
+            SRGB::from(Color::from(color.unwrap_or(self.vic2_colors.background)))
+        })
     }
 }
 
 impl TextScreen {
-    fn get_pixel_color(&self, x: usize, y: usize, char_map: &CharSet) -> SRGB {
+    /// Get the foreground color of the text screen at the given position. Returns `None` when the pixel of the char
+    /// isn't set.
+    fn get_pixel_color(&self, x: usize, y: usize, char_map: &CharSet, color_ram: &ColorRAM) -> Option<u8> {
         let index = (x / 8) + (y / 8) * 40;
-        let charcode = self.screen_chars[index];
-        let charmap_x = (charcode as usize % 16) * 10 + (x % 8) + 1;
-        let charmap_y = (charcode as usize / 16) * 10 + (y % 8) + 1;
-        char_map.get_pixel_color(charmap_x, charmap_y)
+        let char_bits = char_map.char(self.screen_chars[index]);
+        let bit_mask = 1_u64 << ((7 - x % 8) + (7 - y % 8) * 8);
+        if char_bits & bit_mask != 0 {
+            Some(color_ram.colors[index] & 0x0F)
+        } else {
+            None
+        }
     }
 }
 
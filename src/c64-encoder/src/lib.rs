@@ -1,5 +1,12 @@
+pub mod animation;
 pub mod builder;
 pub mod charmap;
 pub mod command;
+pub mod container;
+pub mod decoder;
 pub mod encoder;
 pub mod evaluator;
+pub mod lz;
+pub mod optimizer;
+#[cfg(feature = "profiler")]
+pub mod profiler;
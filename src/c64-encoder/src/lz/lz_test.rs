@@ -0,0 +1,55 @@
+use proptest::{collection::vec, prelude::*};
+
+use crate::{
+    decoder::DecodeError,
+    lz::{pack, unpack, END_TOKEN, MATCH_TOKEN},
+};
+
+/// Data with repeating sequences, so matches are found.
+fn repetitive_data() -> impl Strategy<Value = Vec<u8>> {
+    vec((vec(0_u8..4, 1..16), 1_usize..20), 0..32)
+        .prop_map(|runs| runs.iter().flat_map(|(bytes, count)| bytes.repeat(*count)).collect())
+}
+
+proptest! {
+    #[test]
+    fn pack_round_trip(data in prop_oneof![vec(any::<u8>(), 0..2000), repetitive_data()]) {
+        let mut packed = pack(&data);
+        packed.extend_from_slice(&[0xAA, 0xBB]);
+
+        let (unpacked, remaining) = unpack(&packed).unwrap();
+
+        prop_assert_eq!(data, unpacked);
+        prop_assert_eq!(&[0xAA, 0xBB], remaining);
+    }
+}
+
+#[test]
+fn pack_repeated_bytes() {
+    let data = [0x20; 1000];
+    let packed = pack(&data);
+    assert!(packed.len() < 40, "packed size {}", packed.len());
+    assert_eq!(data.to_vec(), unpack(&packed).unwrap().0);
+}
+
+#[test]
+fn pack_empty() {
+    assert_eq!(vec![END_TOKEN], pack(&[]));
+}
+
+#[test]
+fn pack_overlapping_match() {
+    let data = [1, 2, 1, 2, 1, 2, 1, 2, 1, 2];
+    assert_eq!(vec![2, 1, 2, MATCH_TOKEN | 5, 0, 0, END_TOKEN], pack(&data));
+    assert_eq!(data.to_vec(), unpack(&pack(&data)).unwrap().0);
+}
+
+#[test]
+fn unpack_invalid() {
+    assert_eq!(Err(DecodeError::UnexpectedEnd), unpack(&[3, 1, 2]));
+    assert_eq!(Err(DecodeError::UnexpectedEnd), unpack(&[1, 1]));
+    assert_eq!(
+        Err(DecodeError::InvalidPackedData),
+        unpack(&[1, 1, MATCH_TOKEN, 1, 0, END_TOKEN])
+    );
+}
@@ -0,0 +1,138 @@
+//! LZ-style packing of engine data.
+//!
+//! The packed data is a sequence of tokens, ended by a 0 token. The format is designed to be unpacked by the 6502
+//! with little code: matches refer to an absolute offset in the unpacked data, so the depacker doesn't need to
+//! subtract pointers.
+//!
+//! | Token       | Followed by           | Content                                                    |
+//! |-------------|-----------------------|------------------------------------------------------------|
+//! | `0x00`      |                       | End of the packed data                                     |
+//! | `0x01-0x7F` | `token` bytes         | Literal bytes                                              |
+//! | `0x80-0xFF` | u16 offset (LOW,HIGH) | Copy `(token & 0x7F) + 3` bytes from the unpacked data     |
+//!
+//! Matches are copied one byte at a time, so a match can overlap the bytes it produces.
+#[cfg(test)]
+mod lz_test;
+
+use std::collections::HashMap;
+
+use crate::decoder::{reader::Reader, DecodeError, DecodeResult};
+
+pub const END_TOKEN: u8 = 0x00;
+pub const MATCH_TOKEN: u8 = 0x80;
+pub const MAX_LITERAL_LEN: usize = 0x7F;
+pub const MIN_MATCH_LEN: usize = 3;
+pub const MAX_MATCH_LEN: usize = 0x7F + MIN_MATCH_LEN;
+/// Largest number of bytes that can be packed; match offsets are stored as u16.
+pub const MAX_UNPACKED_LEN: usize = 0x10000;
+
+/// Matches shorter than this don't save bytes compared to extending a literal run.
+const MIN_USEFUL_MATCH_LEN: usize = 4;
+/// Number of earlier positions with the same prefix that are tried when searching a match.
+const MAX_MATCH_CANDIDATES: usize = 64;
+
+/// Pack the data. The result can be unpacked by [unpack] and by the `packed_commands` decoder module.
+pub fn pack(data: &[u8]) -> Vec<u8> {
+    assert!(
+        data.len() <= MAX_UNPACKED_LEN,
+        "data is too large to be packed ({} bytes)",
+        data.len()
+    );
+    let mut result = vec![];
+    let mut literal_start = 0;
+    // Positions in the data, most recent last, indexed by the 3 bytes starting at the position.
+    let mut positions = HashMap::<[u8; 3], Vec<usize>>::new();
+    let mut position = 0;
+    while position < data.len() {
+        let (match_offset, match_len) = find_match(data, position, &positions);
+        if match_len < MIN_USEFUL_MATCH_LEN {
+            register_positions(data, position, 1, &mut positions);
+            position += 1;
+            continue;
+        }
+        write_literals(&mut result, &data[literal_start..position]);
+        result.push(MATCH_TOKEN | (match_len - MIN_MATCH_LEN) as u8);
+        result.extend_from_slice(&(match_offset as u16).to_le_bytes());
+        register_positions(data, position, match_len, &mut positions);
+        position += match_len;
+        literal_start = position;
+    }
+    write_literals(&mut result, &data[literal_start..]);
+    result.push(END_TOKEN);
+    result
+}
+
+/// Unpack data created by [pack]. Returns the unpacked data and the data after the end token.
+pub fn unpack(packed_data: &[u8]) -> DecodeResult<(Vec<u8>, &[u8])> {
+    let mut result = vec![];
+    let mut packed_data = packed_data;
+    loop {
+        let token;
+        (token, packed_data) = packed_data.read::<u8>()?;
+        if token == END_TOKEN {
+            return Ok((result, packed_data));
+        }
+        if token & MATCH_TOKEN == 0 {
+            let len = token as usize;
+            if packed_data.len() < len {
+                return Err(DecodeError::UnexpectedEnd);
+            }
+            result.extend_from_slice(&packed_data[..len]);
+            packed_data = &packed_data[len..];
+        } else {
+            let offset;
+            (offset, packed_data) = packed_data.read::<u16>()?;
+            let offset = offset as usize;
+            if offset >= result.len() {
+                return Err(DecodeError::InvalidPackedData);
+            }
+            let len = (token & !MATCH_TOKEN) as usize + MIN_MATCH_LEN;
+            for index in offset..offset + len {
+                result.push(result[index]);
+            }
+        }
+    }
+}
+
+/// Find the longest earlier occurrence of the data at the position. Returns the offset and length of the match.
+fn find_match(data: &[u8], position: usize, positions: &HashMap<[u8; 3], Vec<usize>>) -> (usize, usize) {
+    let Some(prefix) = prefix(data, position) else {
+        return (0, 0);
+    };
+    let Some(candidates) = positions.get(&prefix) else {
+        return (0, 0);
+    };
+    let max_len = MAX_MATCH_LEN.min(data.len() - position);
+    let mut best = (0, 0);
+    for candidate in candidates.iter().rev().take(MAX_MATCH_CANDIDATES) {
+        let len = (0..max_len)
+            .take_while(|index| data[candidate + index] == data[position + index])
+            .count();
+        if len > best.1 {
+            best = (*candidate, len);
+            if len == max_len {
+                break;
+            }
+        }
+    }
+    best
+}
+
+fn register_positions(data: &[u8], position: usize, len: usize, positions: &mut HashMap<[u8; 3], Vec<usize>>) {
+    for position in position..position + len {
+        if let Some(prefix) = prefix(data, position) {
+            positions.entry(prefix).or_default().push(position);
+        }
+    }
+}
+
+fn prefix(data: &[u8], position: usize) -> Option<[u8; 3]> {
+    data.get(position..position + 3).map(|bytes| bytes.try_into().unwrap())
+}
+
+fn write_literals(result: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_LITERAL_LEN) {
+        result.push(chunk.len() as u8);
+        result.extend_from_slice(chunk);
+    }
+}
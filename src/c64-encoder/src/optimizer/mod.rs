@@ -0,0 +1,102 @@
+//! Choose the smallest commands to transition from one frame to the next.
+//!
+//! A [FrameOptimizer] asks each of its [Strategy]s to encode the transition and keeps the smallest result for the
+//! screen chars and for the charset.
+#[cfg(test)]
+mod optimizer_test;
+mod strategies;
+
+pub use strategies::*;
+
+use crate::{builder::frame::Commands, command::scan_order::ScanOrder, encoder::Encoder};
+
+/// Screen chars and charset before and after a frame.
+#[derive(Debug, Copy, Clone)]
+pub struct Transition<'a> {
+    /// Screen chars before the frame, `None` when unknown, for example for the first frame.
+    pub from_screen_chars: Option<&'a [u8; 1000]>,
+    pub to_screen_chars: &'a [u8; 1000],
+    /// Charset before the frame, `None` when unknown.
+    pub from_charset: Option<&'a [u64]>,
+    /// Chars to store at the start of the charset, up to 256.
+    pub to_charset: &'a [u64],
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OptimizerError {
+    /// None of the strategies can encode the part of the frame.
+    NoStrategyFits(FramePart),
+}
+
+pub type OptimizerResult<T> = Result<T, OptimizerError>;
+
+/// Part of the frame a [Strategy] updates.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FramePart {
+    ScreenChars,
+    Charset,
+}
+
+/// Way of encoding part of a transition.
+pub trait Strategy {
+    fn part(&self) -> FramePart;
+
+    /// Commands that update the part of the frame, `None` when the strategy cannot encode the transition.
+    fn encode(&self, transition: &Transition) -> Option<Commands>;
+}
+
+/// Find the smallest commands for a transition by trying every strategy.
+pub struct FrameOptimizer {
+    strategies: Vec<Box<dyn Strategy>>,
+}
+
+impl Default for FrameOptimizer {
+    /// Optimizer using all available strategies.
+    fn default() -> Self {
+        Self::new(vec![
+            Box::new(UnchangedScreenChars {}),
+            Box::new(FullScreenUpdate {}),
+            Box::new(ClearAndUpdateScreen {}),
+            Box::new(PartialScreenUpdate {}),
+            Box::new(ScreenCharsRLEUpdate {}),
+            Box::new(ScanOrderRLEUpdate {
+                scan_order: ScanOrder::ColumnMajor,
+            }),
+            Box::new(ScanOrderRLEUpdate {
+                scan_order: ScanOrder::Tiles8x8,
+            }),
+            Box::new(UnchangedCharset {}),
+            Box::new(FullCharsetUpdate {}),
+            Box::new(RangedCharsetUpdate {}),
+            Box::new(SparseCharsetUpdate {}),
+        ])
+    }
+}
+
+impl FrameOptimizer {
+    pub fn new(strategies: Vec<Box<dyn Strategy>>) -> Self {
+        Self { strategies }
+    }
+
+    pub fn add_strategy(&mut self, strategy: Box<dyn Strategy>) -> &mut Self {
+        self.strategies.push(strategy);
+        self
+    }
+
+    /// Smallest commands for the transition: the charset update followed by the screen chars update.
+    pub fn optimize(&self, transition: &Transition) -> OptimizerResult<Commands> {
+        let mut result = self.optimize_part(FramePart::Charset, transition)?;
+        result.extend(self.optimize_part(FramePart::ScreenChars, transition)?);
+        Ok(result)
+    }
+
+    /// Smallest commands that update the part of the frame.
+    pub fn optimize_part(&self, part: FramePart, transition: &Transition) -> OptimizerResult<Commands> {
+        self.strategies
+            .iter()
+            .filter(|strategy| strategy.part() == part)
+            .filter_map(|strategy| strategy.encode(transition))
+            .min_by_key(|commands| commands.byte_size())
+            .ok_or(OptimizerError::NoStrategyFits(part))
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    builder::frame::Commands,
+    command::{
+        clear_screen_chars::ClearScreenChars,
+        scan_order::ScanOrder,
+        update_screen_chars_rle::{RLECommand, RLEPacket, UpdateScreenCharsRLE},
+        Command,
+    },
+    encoder::Encoder,
+    optimizer::{
+        FrameOptimizer, FramePart, FullCharsetUpdate, FullScreenUpdate, OptimizerError, RangedCharsetUpdate,
+        ScreenCharsRLEUpdate, SparseCharsetUpdate, Strategy, Transition, UnchangedScreenChars,
+    },
+};
+
+fn transition<'a>(
+    from_screen_chars: Option<&'a [u8; 1000]>,
+    to_screen_chars: &'a [u8; 1000],
+    charset: &'a [u64],
+) -> Transition<'a> {
+    Transition {
+        from_screen_chars,
+        to_screen_chars,
+        from_charset: Some(charset),
+        to_charset: charset,
+    }
+}
+
+#[test]
+fn unchanged_frame() {
+    let screen_chars = [0x20; 1000];
+    let charset = [0x55; 16];
+
+    let commands = FrameOptimizer::default()
+        .optimize(&transition(Some(&screen_chars), &screen_chars, &charset))
+        .unwrap();
+
+    assert_eq!(Commands::new(), commands);
+}
+
+#[test]
+fn small_screen_change_is_partial() {
+    let from_screen_chars = [0x20; 1000];
+    let mut to_screen_chars = from_screen_chars;
+    to_screen_chars[10] = 0x01;
+    to_screen_chars[500] = 0x02;
+
+    let commands = FrameOptimizer::default()
+        .optimize(&transition(Some(&from_screen_chars), &to_screen_chars, &[]))
+        .unwrap();
+
+    assert_eq!(1, commands.len());
+    assert!(commands.byte_size() < 100);
+    assert!(matches!(
+        commands[0],
+        Command::PartialUpdateTextModeScreen(_) | Command::UpdateScreenCharsRLE(_)
+    ));
+}
+
+#[test]
+fn first_frame_clears_uniform_screen() {
+    let screen_chars = [0x20; 1000];
+
+    let commands = FrameOptimizer::default()
+        .optimize(&Transition {
+            from_screen_chars: None,
+            to_screen_chars: &screen_chars,
+            from_charset: None,
+            to_charset: &[],
+        })
+        .unwrap();
+
+    assert_eq!(
+        vec![Command::ClearScreenChars(ClearScreenChars { screen_char: 0x20 })],
+        commands
+    );
+}
+
+#[test]
+fn charset_update_is_smallest() {
+    let from_charset = [0x1111_2222_3333_4444; 64];
+    let mut to_charset = from_charset;
+    to_charset[3] = 0x0101_0101_0101_0101;
+    let screen_chars = [0x00; 1000];
+    let transition = Transition {
+        from_screen_chars: Some(&screen_chars),
+        to_screen_chars: &screen_chars,
+        from_charset: Some(&from_charset),
+        to_charset: &to_charset,
+    };
+    let optimizer = FrameOptimizer::default();
+
+    let commands = optimizer.optimize(&transition).unwrap();
+
+    for strategy in [
+        &FullCharsetUpdate {} as &dyn Strategy,
+        &RangedCharsetUpdate {},
+        &SparseCharsetUpdate {},
+    ] {
+        let strategy_commands = strategy.encode(&transition).unwrap();
+        assert!(commands.byte_size() <= strategy_commands.byte_size());
+    }
+    assert!(!commands.is_empty());
+}
+
+#[test]
+fn custom_strategies() {
+    let from_screen_chars = [0x20; 1000];
+    let mut to_screen_chars = from_screen_chars;
+    to_screen_chars[0] = 0x01;
+    let mut optimizer = FrameOptimizer::new(vec![]);
+    optimizer
+        .add_strategy(Box::new(UnchangedScreenChars {}))
+        .add_strategy(Box::new(FullScreenUpdate {}));
+
+    let commands = optimizer
+        .optimize_part(
+            FramePart::ScreenChars,
+            &transition(Some(&from_screen_chars), &to_screen_chars, &[]),
+        )
+        .unwrap();
+
+    assert!(matches!(commands[..], [Command::UpdateTextModeScreen(_)]));
+}
+
+#[test]
+fn no_applicable_strategy() {
+    let screen_chars = [0x20; 1000];
+    let optimizer = FrameOptimizer::new(vec![Box::new(UnchangedScreenChars {})]);
+
+    assert_eq!(
+        Err(OptimizerError::NoStrategyFits(FramePart::ScreenChars)),
+        optimizer.optimize_part(
+            FramePart::ScreenChars,
+            &Transition {
+                from_screen_chars: None,
+                to_screen_chars: &screen_chars,
+                from_charset: None,
+                to_charset: &[],
+            },
+        )
+    );
+}
+
+#[test]
+fn greedy_screen_chars_rle_restarts_auto_increment_runs() {
+    let from_screen_chars = [0x00; 1000];
+    let mut to_screen_chars = from_screen_chars;
+    to_screen_chars[..8].copy_from_slice(&[1, 2, 3, 5, 6, 7, 254, 255]);
+
+    let update = UpdateScreenCharsRLE::transition(&from_screen_chars, &to_screen_chars);
+
+    assert_eq!(
+        vec![
+            RLEPacket {
+                num_screen_chars: 3,
+                command: RLECommand::AutoIncrement(1)
+            },
+            RLEPacket {
+                num_screen_chars: 3,
+                command: RLECommand::AutoIncrement(5)
+            },
+            RLEPacket {
+                num_screen_chars: 2,
+                command: RLECommand::AutoIncrement(254)
+            },
+        ],
+        update.rle_packets
+    );
+}
+
+#[test]
+fn screen_chars_rle_splits_auto_increment_runs() {
+    let from_screen_chars = [0x00; 1000];
+    let mut to_screen_chars = from_screen_chars;
+    to_screen_chars[..8].copy_from_slice(&[1, 2, 3, 5, 6, 7, 254, 255]);
+
+    let commands = ScreenCharsRLEUpdate {}
+        .encode(&transition(Some(&from_screen_chars), &to_screen_chars, &[]))
+        .unwrap();
+
+    let [Command::UpdateScreenCharsRLE(update)] = &commands[..] else {
+        panic!("unexpected commands {commands:?}");
+    };
+    assert_eq!(
+        vec![
+            RLEPacket {
+                num_screen_chars: 3,
+                command: RLECommand::AutoIncrement(1)
+            },
+            RLEPacket {
+                num_screen_chars: 3,
+                command: RLECommand::AutoIncrement(5)
+            },
+            RLEPacket {
+                num_screen_chars: 2,
+                command: RLECommand::AutoIncrement(254)
+            },
+        ],
+        update.rle_packets
+    );
+}
+
+#[test]
+fn vertical_stripes_use_column_major_rle() {
+    let from_screen_chars = [0x00; 1000];
+    let to_screen_chars = std::array::from_fn::<u8, 1000, _>(|offset| (offset % 40 * 3) as u8);
+
+    let commands = FrameOptimizer::default()
+        .optimize(&transition(Some(&from_screen_chars), &to_screen_chars, &[]))
+        .unwrap();
+
+    let [Command::UpdateScreenCharsRLEExtended(update)] = &commands[..] else {
+        panic!("unexpected commands {commands:?}");
+    };
+    assert_eq!(ScanOrder::ColumnMajor, update.scan_order);
+}
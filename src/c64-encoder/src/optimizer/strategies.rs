@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use crate::{
+    builder::frame::Commands,
+    command::{
+        clear_screen_chars::ClearScreenChars, partial_update_text_mode::PartialUpdateTextModeScreen,
+        scan_order::ScanOrder, update_chars::UpdateChar,
+        update_screen_chars_rle_extended::UpdateScreenCharsRLEExtended, update_text_mode_screen::UpdateTextModeScreen,
+        Command,
+    },
+    encoder::Encoder,
+};
+
+use super::{FramePart, Strategy, Transition};
+
+/// No commands when the screen chars don't change.
+pub struct UnchangedScreenChars {}
+
+impl Strategy for UnchangedScreenChars {
+    fn part(&self) -> FramePart {
+        FramePart::ScreenChars
+    }
+
+    fn encode(&self, transition: &Transition) -> Option<Commands> {
+        (transition.from_screen_chars? == transition.to_screen_chars).then(Commands::new)
+    }
+}
+
+/// Replace all screen chars.
+pub struct FullScreenUpdate {}
+
+impl Strategy for FullScreenUpdate {
+    fn part(&self) -> FramePart {
+        FramePart::ScreenChars
+    }
+
+    fn encode(&self, transition: &Transition) -> Option<Commands> {
+        Some(vec![Command::UpdateTextModeScreen(UpdateTextModeScreen {
+            chars: *transition.to_screen_chars,
+        })])
+    }
+}
+
+/// Clear the screen with the most used screen char and update the other screen chars.
+pub struct ClearAndUpdateScreen {}
+
+impl Strategy for ClearAndUpdateScreen {
+    fn part(&self) -> FramePart {
+        FramePart::ScreenChars
+    }
+
+    fn encode(&self, transition: &Transition) -> Option<Commands> {
+        let mut screen_char_count = HashMap::<u8, usize>::new();
+        for screen_char in transition.to_screen_chars {
+            *screen_char_count.entry(*screen_char).or_default() += 1;
+        }
+        let (screen_char, _) = screen_char_count
+            .into_iter()
+            .max_by_key(|(screen_char, count)| (*count, u8::MAX - screen_char))?;
+        let cleared_screen_chars = [screen_char; 1000];
+        let cleared = Transition {
+            from_screen_chars: Some(&cleared_screen_chars),
+            ..*transition
+        };
+        let update = [
+            UnchangedScreenChars {}.encode(&cleared),
+            PartialScreenUpdate {}.encode(&cleared),
+            ScreenCharsRLEUpdate {}.encode(&cleared),
+        ]
+        .into_iter()
+        .flatten()
+        .min_by_key(|commands| commands.byte_size())?;
+
+        let mut result = vec![Command::ClearScreenChars(ClearScreenChars { screen_char })];
+        result.extend(update);
+        Some(result)
+    }
+}
+
+/// Update the screen chars that changed one at a time.
+pub struct PartialScreenUpdate {}
+
+impl Strategy for PartialScreenUpdate {
+    fn part(&self) -> FramePart {
+        FramePart::ScreenChars
+    }
+
+    fn encode(&self, transition: &Transition) -> Option<Commands> {
+        Some(vec![Command::PartialUpdateTextModeScreen(
+            PartialUpdateTextModeScreen::transition(transition.from_screen_chars?, transition.to_screen_chars),
+        )])
+    }
+}
+
+/// Update runs of changed screen chars with the smallest packet sequence, see [Command::update_screen_chars_rle].
+pub struct ScreenCharsRLEUpdate {}
+
+impl Strategy for ScreenCharsRLEUpdate {
+    fn part(&self) -> FramePart {
+        FramePart::ScreenChars
+    }
+
+    fn encode(&self, transition: &Transition) -> Option<Commands> {
+        Some(vec![Command::update_screen_chars_rle(
+            transition.from_screen_chars?,
+            transition.to_screen_chars,
+        )])
+    }
+}
+
+/// Update the screen chars with packets visiting the screen chars in another order than row-major, see
+/// [UpdateScreenCharsRLEExtended::transition].
+pub struct ScanOrderRLEUpdate {
+    pub scan_order: ScanOrder,
+}
+
+impl Strategy for ScanOrderRLEUpdate {
+    fn part(&self) -> FramePart {
+        FramePart::ScreenChars
+    }
+
+    fn encode(&self, transition: &Transition) -> Option<Commands> {
+        Some(vec![Command::UpdateScreenCharsRLEExtended(
+            UpdateScreenCharsRLEExtended::transition(
+                transition.from_screen_chars?,
+                transition.to_screen_chars,
+                self.scan_order,
+            ),
+        )])
+    }
+}
+
+/// No commands when the charset doesn't change.
+pub struct UnchangedCharset {}
+
+impl Strategy for UnchangedCharset {
+    fn part(&self) -> FramePart {
+        FramePart::Charset
+    }
+
+    fn encode(&self, transition: &Transition) -> Option<Commands> {
+        changed_chars(transition)?.is_empty().then(Commands::new)
+    }
+}
+
+/// Replace all chars of the charset.
+pub struct FullCharsetUpdate {}
+
+impl Strategy for FullCharsetUpdate {
+    fn part(&self) -> FramePart {
+        FramePart::Charset
+    }
+
+    fn encode(&self, transition: &Transition) -> Option<Commands> {
+        if transition.to_charset.is_empty() {
+            return Some(Commands::new());
+        }
+        Some(vec![Command::update_chars_ranged(0, transition.to_charset)])
+    }
+}
+
+/// Replace the range of chars from the first to the last changed char.
+pub struct RangedCharsetUpdate {}
+
+impl Strategy for RangedCharsetUpdate {
+    fn part(&self) -> FramePart {
+        FramePart::Charset
+    }
+
+    fn encode(&self, transition: &Transition) -> Option<Commands> {
+        let changed_chars = changed_chars(transition)?;
+        let (Some(first), Some(last)) = (changed_chars.first(), changed_chars.last()) else {
+            return Some(Commands::new());
+        };
+        Some(vec![Command::update_chars_ranged(
+            first.char,
+            &transition.to_charset[first.char as usize..=last.char as usize],
+        )])
+    }
+}
+
+/// Update the chars that changed one at a time.
+pub struct SparseCharsetUpdate {}
+
+impl Strategy for SparseCharsetUpdate {
+    fn part(&self) -> FramePart {
+        FramePart::Charset
+    }
+
+    fn encode(&self, transition: &Transition) -> Option<Commands> {
+        Some(Command::update_chars(&changed_chars(transition)?))
+    }
+}
+
+/// Chars of the target charset that differ from the previous charset, `None` when the previous charset is unknown.
+fn changed_chars(transition: &Transition) -> Option<Vec<UpdateChar>> {
+    let from_charset = transition.from_charset?;
+    Some(
+        transition
+            .to_charset
+            .iter()
+            .enumerate()
+            .filter(|(char, data)| from_charset.get(*char) != Some(*data))
+            .map(|(char, data)| UpdateChar {
+                char: char as u8,
+                data: *data,
+            })
+            .collect(),
+    )
+}
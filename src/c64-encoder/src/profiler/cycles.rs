@@ -0,0 +1,91 @@
+use mos6502::{
+    cpu::CPU,
+    instruction::Nmos6502,
+    memory::{Bus, Memory},
+};
+
+/// Number of cycles of each NMOS 6502 opcode, excluding page crossing and taken branch penalties.
+///
+/// Undocumented opcodes aren't executed by the emulator and are listed with the cycles of their documented
+/// counterparts.
+#[rustfmt::skip]
+const BASE_CYCLES: [u8; 256] = [
+    7, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4,
+    2, 6, 2, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5,
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4,
+    2, 5, 2, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4,
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+];
+
+/// Read instructions using (zero page),Y addressing that take an extra cycle when crossing a page.
+const INDIRECT_Y_READS: [u8; 7] = [0x11, 0x31, 0x51, 0x71, 0xB1, 0xD1, 0xF1];
+/// Read instructions using absolute,X addressing that take an extra cycle when crossing a page.
+const ABSOLUTE_X_READS: [u8; 8] = [0x1D, 0x3D, 0x5D, 0x7D, 0xBC, 0xBD, 0xDD, 0xFD];
+/// Read instructions using absolute,Y addressing that take an extra cycle when crossing a page.
+const ABSOLUTE_Y_READS: [u8; 8] = [0x19, 0x39, 0x59, 0x79, 0xB9, 0xBE, 0xD9, 0xF9];
+const BRANCHES: [u8; 8] = [0x10, 0x30, 0x50, 0x70, 0x90, 0xB0, 0xD0, 0xF0];
+
+/// Number of cycles of the instruction at the program counter, that only depend on the state before executing it.
+pub(crate) fn cycles_before_execution(cpu: &mut CPU<Memory, Nmos6502>) -> usize {
+    let program_counter = cpu.registers.program_counter;
+    let opcode = cpu.memory.get_byte(program_counter);
+    let mut cycles = BASE_CYCLES[opcode as usize] as usize;
+
+    let base_address = if INDIRECT_Y_READS.contains(&opcode) {
+        let zero_page = cpu.memory.get_byte(program_counter.wrapping_add(1));
+        Some((
+            read_u16(cpu, zero_page as u16, zero_page.wrapping_add(1) as u16),
+            cpu.registers.index_y,
+        ))
+    } else if ABSOLUTE_X_READS.contains(&opcode) {
+        let operand = program_counter.wrapping_add(1);
+        Some((read_u16(cpu, operand, operand.wrapping_add(1)), cpu.registers.index_x))
+    } else if ABSOLUTE_Y_READS.contains(&opcode) {
+        let operand = program_counter.wrapping_add(1);
+        Some((read_u16(cpu, operand, operand.wrapping_add(1)), cpu.registers.index_y))
+    } else {
+        None
+    };
+    if let Some((base_address, index)) = base_address {
+        if crosses_page(base_address, base_address.wrapping_add(index as u16)) {
+            cycles += 1;
+        }
+    }
+    cycles
+}
+
+/// Additional cycles of the executed instruction that depend on its outcome.
+///
+/// Taken branches need an additional cycle, and another one when the branch target is on a different page.
+pub(crate) fn cycles_after_execution(opcode: u8, program_counter_before: u16, program_counter_after: u16) -> usize {
+    if !BRANCHES.contains(&opcode) {
+        return 0;
+    }
+    let next_instruction = program_counter_before.wrapping_add(2);
+    if program_counter_after == next_instruction {
+        0
+    } else if crosses_page(next_instruction, program_counter_after) {
+        2
+    } else {
+        1
+    }
+}
+
+fn read_u16(cpu: &mut CPU<Memory, Nmos6502>, low_address: u16, high_address: u16) -> u16 {
+    u16::from_le_bytes([cpu.memory.get_byte(low_address), cpu.memory.get_byte(high_address)])
+}
+
+fn crosses_page(address_a: u16, address_b: u16) -> bool {
+    address_a & 0xFF00 != address_b & 0xFF00
+}
@@ -0,0 +1,277 @@
+//! Count the cycles the engine spends on each frame and command by running it in the `mos6502` emulator.
+//!
+//! Only available with the `profiler` feature.
+mod cycles;
+mod runner;
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+pub use runner::EngineRunner;
+
+use crate::{
+    builder::demo::DemoBuilder,
+    command::modules::EngineOptions,
+    decoder::reader::Reader,
+    encoder::Encoder,
+    evaluator::{evaluate, EvaluateResult},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum VideoStandard {
+    #[default]
+    PAL,
+    NTSC,
+}
+
+impl VideoStandard {
+    pub fn cycles_per_raster_line(&self) -> usize {
+        match self {
+            VideoStandard::PAL => 63,
+            VideoStandard::NTSC => 65,
+        }
+    }
+
+    pub fn raster_lines(&self) -> usize {
+        match self {
+            VideoStandard::PAL => 312,
+            VideoStandard::NTSC => 263,
+        }
+    }
+
+    pub fn cycles_per_frame(&self) -> usize {
+        self.cycles_per_raster_line() * self.raster_lines()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ProfileOptions {
+    /// Video standard used to advance the raster line.
+    pub video_standard: VideoStandard,
+    /// Cycles the engine may use per video frame. Defaults to a complete video frame; lower it to leave room for
+    /// music or bad lines.
+    pub cycles_per_frame: usize,
+}
+
+impl ProfileOptions {
+    pub fn new(video_standard: VideoStandard) -> Self {
+        Self {
+            video_standard,
+            cycles_per_frame: video_standard.cycles_per_frame(),
+        }
+    }
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        Self::new(VideoStandard::default())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandProfile {
+    /// Index of the command in the frame.
+    pub command: usize,
+    pub name: &'static str,
+    /// Cycles spent on the command, including dispatching it.
+    pub cycles: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameProfile {
+    /// Index of the played frame; frames played more than once due to loops are counted each time.
+    pub played_frame: usize,
+    /// Index of the demo frame.
+    pub frame: usize,
+    /// Number of video frames the frame is displayed.
+    pub duration: usize,
+    /// Cycles spent on the frame, including waiting for video frames.
+    pub cycles: usize,
+    /// Cycles spent on the frame that aren't part of a command.
+    pub overhead_cycles: usize,
+    pub commands: Vec<CommandProfile>,
+}
+
+impl FrameProfile {
+    /// Cycles available for the frame.
+    pub fn budget(&self, options: &ProfileOptions) -> usize {
+        self.duration * options.cycles_per_frame
+    }
+
+    pub fn is_over_budget(&self, options: &ProfileOptions) -> bool {
+        self.cycles > self.budget(options)
+    }
+}
+
+/// Cycles of all commands of the same type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandTypeProfile {
+    pub name: &'static str,
+    pub count: usize,
+    pub total_cycles: usize,
+    pub max_cycles: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub options: ProfileOptions,
+    pub frames: Vec<FrameProfile>,
+}
+
+impl Profile {
+    pub fn frames_over_budget(&self) -> impl Iterator<Item = &FrameProfile> {
+        self.frames.iter().filter(|frame| frame.is_over_budget(&self.options))
+    }
+
+    /// Cycles per command type, the most expensive command type first.
+    pub fn command_types(&self) -> Vec<CommandTypeProfile> {
+        let mut command_types = HashMap::<&'static str, CommandTypeProfile>::new();
+        for command in self.frames.iter().flat_map(|frame| &frame.commands) {
+            let command_type = command_types.entry(command.name).or_insert(CommandTypeProfile {
+                name: command.name,
+                count: 0,
+                total_cycles: 0,
+                max_cycles: 0,
+            });
+            command_type.count += 1;
+            command_type.total_cycles += command.cycles;
+            command_type.max_cycles = command_type.max_cycles.max(command.cycles);
+        }
+        let mut result = command_types.into_values().collect::<Vec<CommandTypeProfile>>();
+        result.sort_by(|a, b| b.total_cycles.cmp(&a.total_cycles).then(a.name.cmp(b.name)));
+        result
+    }
+
+    /// Write a human readable report with the cycles per frame and per command type.
+    pub fn write_table(&self, writer: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            writer,
+            "{:>6} {:>6} {:>9} {:>9} {:>6}",
+            "played", "frame", "cycles", "budget", "usage"
+        )?;
+        for frame in &self.frames {
+            let budget = frame.budget(&self.options);
+            writeln!(
+                writer,
+                "{:>6} {:>6} {:>9} {:>9} {:>5}%{}",
+                frame.played_frame,
+                frame.frame,
+                frame.cycles,
+                budget,
+                frame.cycles * 100 / budget,
+                if frame.is_over_budget(&self.options) {
+                    " OVER BUDGET"
+                } else {
+                    ""
+                }
+            )?;
+        }
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "{:<28} {:>6} {:>10} {:>9} {:>9}",
+            "command", "count", "total", "average", "max"
+        )?;
+        for command_type in self.command_types() {
+            writeln!(
+                writer,
+                "{:<28} {:>6} {:>10} {:>9} {:>9}",
+                command_type.name,
+                command_type.count,
+                command_type.total_cycles,
+                command_type.total_cycles / command_type.count,
+                command_type.max_cycles
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the cycles per frame as CSV.
+    pub fn write_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+        writeln!(writer, "played_frame,frame,cycles,overhead_cycles,budget,over_budget")?;
+        for frame in &self.frames {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                frame.played_frame,
+                frame.frame,
+                frame.cycles,
+                frame.overhead_cycles,
+                frame.budget(&self.options),
+                frame.is_over_budget(&self.options)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Play the engine data with the engine and count the cycles per frame and command.
+///
+/// Frames are played in the order of the evaluator; the engine and engine data must not overlap the memory used by
+/// the engine, see [crate::command::modules::EngineConfig::check_program].
+pub fn profile(engine_data: &[u8], options: ProfileOptions) -> EvaluateResult<Profile> {
+    let states = evaluate(engine_data)?;
+    let (demo, _) = engine_data.read::<DemoBuilder>().expect("evaluated demo should decode");
+    let mut runner = EngineRunner::with_options(
+        engine_data,
+        &states[0],
+        EngineOptions::default(),
+        &[],
+        options.video_standard,
+    )
+    .expect("engine should assemble");
+
+    let mut frames = vec![];
+    for (played_frame, state) in states.iter().enumerate().skip(1) {
+        let commands = &demo.frames[state.frame].commands;
+        // Offsets of the commands from the start of the engine data.
+        let mut command_offsets = vec![];
+        let mut offset = demo.frame_offset(state.frame) + size_of::<u16>();
+        for command in commands {
+            command_offsets.push(offset);
+            offset += command.byte_size();
+        }
+        let frame_range = demo.frame_offset(state.frame)..offset;
+
+        let mut command_cycles = vec![0; commands.len()];
+        let mut overhead_cycles = 0;
+        let mut last_command = None;
+        let engine_data_address = runner.engine_data_address as usize;
+        runner.run_frame_counting_cycles(|current_ptr, cycles| {
+            let offset = (current_ptr as usize).wrapping_sub(engine_data_address);
+            // The current pointer points to the command being processed. Commands that jump to another frame move it
+            // outside the frame, the remaining cycles are attributed to that command.
+            if frame_range.contains(&offset) {
+                last_command = command_offsets
+                    .iter()
+                    .rposition(|command_offset| *command_offset <= offset);
+            }
+            match last_command {
+                Some(command) => command_cycles[command] += cycles,
+                None => overhead_cycles += cycles,
+            }
+        });
+
+        frames.push(FrameProfile {
+            played_frame,
+            frame: state.frame,
+            duration: state.duration,
+            cycles: command_cycles.iter().sum::<usize>() + overhead_cycles,
+            overhead_cycles,
+            commands: commands
+                .iter()
+                .zip(command_cycles)
+                .enumerate()
+                .map(|(command_index, (command, cycles))| CommandProfile {
+                    command: command_index,
+                    name: command.name(),
+                    cycles,
+                })
+                .collect(),
+        });
+    }
+
+    Ok(Profile { options, frames })
+}
@@ -0,0 +1,213 @@
+use c64_assembler::{
+    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    generator::{Generator, ProgramGenerator},
+    validator::{AssemblerResult, Validator},
+    Module,
+};
+use mos6502::{
+    cpu::CPU,
+    instruction::Nmos6502,
+    memory::{Bus, Memory},
+};
+
+use crate::{
+    command::modules::{EngineBuilder, EngineConfig, EngineOptions},
+    evaluator::state::State,
+};
+
+use super::{
+    cycles::{cycles_after_execution, cycles_before_execution},
+    VideoStandard,
+};
+
+const PROGRAM_ADDRESS: u16 = 0x0800;
+/// Address of the stop instruction after `jsr engine__init`.
+const INIT_RETURN_ADDRESS: u16 = PROGRAM_ADDRESS + 3;
+/// Address of `jsr engine__frame__process`, after `jsr engine__init` and the stop instruction.
+const FRAME_PROCESS_ADDRESS: u16 = PROGRAM_ADDRESS + 4;
+/// Address of `jsr engine__deinit`, after `jsr engine__frame__process` and the stop instruction.
+const DEINIT_ADDRESS: u16 = PROGRAM_ADDRESS + 8;
+const KERNAL_IRQ_VECTOR_ADDRESS: u16 = 0x0314;
+/// KERNAL IRQ handler, the value of the IRQ vector at power on.
+const KERNAL_IRQ_HANDLER: u16 = 0xEA31;
+/// KERNAL code that restores the registers pushed by the KERNAL IRQ entry and returns from the interrupt.
+const KERNAL_IRQ_RETURN_ADDRESS: u16 = 0xEA81;
+const KERNAL_IRQ_RETURN: [u8; 6] = [0x68, 0xA8, 0x68, 0xAA, 0x68, 0x40];
+const RASTER_LINE_ADDRESS: u16 = 0xD012;
+const VIC2_MEMORY_SETUP_ADDRESS: u16 = 0xD018;
+
+/// Runs the generated engine with engine data in the `mos6502` emulator, one frame at a time.
+///
+/// The raster line advances with the cycles of the executed instructions.
+pub struct EngineRunner {
+    pub cpu: CPU<Memory, Nmos6502>,
+    /// Address where the engine data is loaded.
+    pub engine_data_address: u16,
+    config: EngineConfig,
+    video_standard: VideoStandard,
+    total_cycles: usize,
+}
+
+impl EngineRunner {
+    /// Assemble the engine built with the config together with the engine data and initialize it. Screen and
+    /// charset RAM are filled from the given state so that untouched memory matches the evaluator.
+    pub fn new(engine_data: &[u8], initial_state: &State, config: EngineConfig) -> AssemblerResult<Self> {
+        let options = EngineOptions {
+            config,
+            ..EngineOptions::default()
+        };
+        Self::with_options(engine_data, initial_state, options, &[], VideoStandard::default())
+    }
+
+    /// Like [EngineRunner::new], but builds the engine with the given options together with additional modules and
+    /// advances the raster line using the given video standard.
+    pub fn with_options(
+        engine_data: &[u8],
+        initial_state: &State,
+        options: EngineOptions,
+        modules: &[Module],
+        video_standard: VideoStandard,
+    ) -> AssemblerResult<Self> {
+        let config = options.config;
+        let mut application_builder = ApplicationBuilder::default();
+        application_builder
+            .include_vic2_defines()
+            .module(
+                ModuleBuilder::default()
+                    .instructions(
+                        InstructionBuilder::default()
+                            .jsr_addr("engine__init")
+                            .raw(&[0xFF])
+                            .jsr_addr("engine__frame__process")
+                            .raw(&[0xFF])
+                            .jsr_addr("engine__deinit")
+                            .raw(&[0xFF])
+                            .build(),
+                    )
+                    .build(),
+            )
+            .add_engine_with_options(options)
+            .module(
+                ModuleBuilder::default()
+                    .name("engine_data")
+                    .instructions(
+                        InstructionBuilder::default()
+                            .label("engine_data")
+                            .raw(engine_data)
+                            .build(),
+                    )
+                    .build(),
+            );
+        for module in modules {
+            application_builder.module(module.clone());
+        }
+        let application = application_builder.build()?;
+        application.validate()?;
+        let bytes = ProgramGenerator::default().generate(application)?;
+        let program = &bytes[2..];
+        if let Err(error) = config.check_program(PROGRAM_ADDRESS, program.len()) {
+            panic!("engine and engine data overlap memory used by the engine: {error:?}");
+        }
+
+        let mut cpu = CPU::new(Memory::new(), Nmos6502);
+        cpu.memory.set_bytes(PROGRAM_ADDRESS, program);
+        cpu.memory
+            .set_bytes(KERNAL_IRQ_VECTOR_ADDRESS, &KERNAL_IRQ_HANDLER.to_le_bytes());
+        cpu.memory.set_bytes(KERNAL_IRQ_RETURN_ADDRESS, &KERNAL_IRQ_RETURN);
+        cpu.memory
+            .set_bytes(config.screen_chars_address(), &initial_state.text_screen.screen_chars);
+        if let Some(back_buffer) = &initial_state.back_buffer {
+            cpu.memory.set_bytes(
+                config.back_screen_chars_address(),
+                &back_buffer.text_screen.screen_chars,
+            );
+        }
+        for char in 0..=255 {
+            cpu.memory.set_bytes(
+                config.charset_address() + char as u16 * 8,
+                &initial_state.charset.char(char).to_be_bytes(),
+            );
+        }
+
+        let mut runner = EngineRunner {
+            cpu,
+            engine_data_address: PROGRAM_ADDRESS + (program.len() - engine_data.len()) as u16,
+            config,
+            video_standard,
+            total_cycles: 0,
+        };
+        runner.run_from(PROGRAM_ADDRESS, |_, _| {});
+        Ok(runner)
+    }
+
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// Process a single frame.
+    pub fn run_frame(&mut self) {
+        self.run_from(FRAME_PROCESS_ADDRESS, |_, _| {});
+    }
+
+    /// Process a single frame. Calls `count` with the current pointer and the cycles of each executed instruction.
+    pub fn run_frame_counting_cycles(&mut self, count: impl FnMut(u16, usize)) {
+        self.run_from(FRAME_PROCESS_ADDRESS, count);
+    }
+
+    /// Raise an interrupt and run until the handler returns, like the KERNAL IRQ entry that pushes the registers and
+    /// jumps to the IRQ vector.
+    pub fn run_interrupt(&mut self) {
+        for byte in INIT_RETURN_ADDRESS.to_be_bytes().into_iter().chain([
+            0x20,
+            self.cpu.registers.accumulator,
+            self.cpu.registers.index_x,
+            self.cpu.registers.index_y,
+        ]) {
+            let stack_pointer = self.cpu.registers.stack_pointer.0;
+            self.cpu.memory.set_byte(0x0100 + stack_pointer as u16, byte);
+            self.cpu.registers.stack_pointer.0 = stack_pointer.wrapping_sub(1);
+        }
+        let irq_vector = self.cpu.memory.get_byte(KERNAL_IRQ_VECTOR_ADDRESS) as u16
+            | (self.cpu.memory.get_byte(KERNAL_IRQ_VECTOR_ADDRESS + 1) as u16) << 8;
+        self.run_from(irq_vector, |_, _| {});
+    }
+
+    /// Run `engine__deinit`.
+    pub fn deinit(&mut self) {
+        self.run_from(DEINIT_ADDRESS, |_, _| {});
+    }
+
+    /// Address of the screen chars the VIC-II shows according to `VIC2_MEMORY_SETUP`.
+    pub fn shown_screen_chars_address(&mut self) -> u16 {
+        let memory_setup = self.cpu.memory.get_byte(VIC2_MEMORY_SETUP_ADDRESS);
+        self.config.vic_bank_address() + (memory_setup >> 4) as u16 * 0x400
+    }
+
+    /// Run until the stop instruction, advancing the raster line with the cycles of each executed instruction.
+    fn run_from(&mut self, address: u16, mut count: impl FnMut(u16, usize)) {
+        let current_ptr_address = self.config.current_ptr() as u16;
+        self.cpu.registers.program_counter = address;
+        loop {
+            let program_counter = self.cpu.registers.program_counter;
+            let opcode = self.cpu.memory.get_byte(program_counter);
+            let current_ptr = u16::from_le_bytes([
+                self.cpu.memory.get_byte(current_ptr_address),
+                self.cpu.memory.get_byte(current_ptr_address + 1),
+            ]);
+            let mut cycles = cycles_before_execution(&mut self.cpu);
+            let Some(decoded_instr) = self.cpu.fetch_next_and_decode() else {
+                break;
+            };
+            self.cpu.execute_instruction(decoded_instr);
+            cycles += cycles_after_execution(opcode, program_counter, self.cpu.registers.program_counter);
+
+            count(current_ptr, cycles);
+            self.total_cycles += cycles;
+            let raster_line =
+                self.total_cycles / self.video_standard.cycles_per_raster_line() % self.video_standard.raster_lines();
+            self.cpu
+                .memory
+                .set_byte(RASTER_LINE_ADDRESS, (raster_line & 0xFF) as u8);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Harness that plays a complete demo with the generated 6502 engine and checks it against the evaluator.
+
+use std::fmt::{self, Display};
+
+use c64_assembler::validator::AssemblerResult;
+use c64_encoder::{
+    builder::demo::DemoBuilder,
+    command::{modules::EngineConfig, Command},
+    decoder::reader::Reader,
+    evaluator::{evaluate_with_options, state::State, EvaluateOptions},
+    profiler::EngineRunner,
+};
+use mos6502::memory::Bus;
+
+/// First memory location where the engine and the evaluator disagree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the played frame; frames played more than once due to loops are counted each time.
+    pub played_frame: usize,
+    /// Index of the demo frame.
+    pub frame: usize,
+    /// Index of the last command in the frame that changed the location according to the evaluator. `None` when no
+    /// command in the frame should have changed it.
+    pub command: Option<usize>,
+    pub address: u16,
+    pub expected: u8,
+    pub actual: u8,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "engine diverges from evaluator at played frame {} (frame {}, command {}): ${:04X} is ${:02X}, expected ${:02X}",
+            self.played_frame,
+            self.frame,
+            self.command.map_or("-".to_string(), |command| command.to_string()),
+            self.address,
+            self.actual,
+            self.expected
+        )
+    }
+}
+
+/// Find the first screen or charset location that differs from the given state.
+pub fn compare(runner: &mut EngineRunner, state: &State) -> Option<(u16, u8, u8)> {
+    let shown_screen_chars_address = runner.shown_screen_chars_address();
+    let config = *runner.config();
+    expected_memory(state, &config, shown_screen_chars_address).find_map(|(address, expected)| {
+        let actual = runner.cpu.memory.get_byte(address);
+        (actual != expected).then_some((address, expected, actual))
+    })
+}
+
+/// Screen and charset RAM of the state. When double buffered the back buffer is expected in the screen chars that
+/// aren't shown.
+fn expected_memory<'a>(
+    state: &'a State,
+    config: &EngineConfig,
+    shown_screen_chars_address: u16,
+) -> impl Iterator<Item = (u16, u8)> + 'a {
+    let hidden_screen_chars_address = if shown_screen_chars_address == config.screen_chars_address() {
+        config.back_screen_chars_address()
+    } else {
+        config.screen_chars_address()
+    };
+    let charset_address = config.charset_address();
+    let screen = (0..1000).map(move |offset| {
+        (
+            shown_screen_chars_address + offset as u16,
+            state.text_screen.screen_chars[offset],
+        )
+    });
+    let back_screen = state.back_buffer.iter().flat_map(move |back_buffer| {
+        (0..1000).map(move |offset| {
+            (
+                hidden_screen_chars_address + offset as u16,
+                back_buffer.text_screen.screen_chars[offset],
+            )
+        })
+    });
+    let charset = (0..=255_u8).flat_map(move |char| {
+        let bytes = state.charset.char(char).to_be_bytes();
+        (0..8).map(move |row| (charset_address + char as u16 * 8 + row as u16, bytes[row]))
+    });
+    screen.chain(back_screen).chain(charset)
+}
+
+fn expected_byte(state: &State, config: &EngineConfig, shown_screen_chars_address: u16, address: u16) -> u8 {
+    expected_memory(state, config, shown_screen_chars_address)
+        .find(|(expected_address, _)| *expected_address == address)
+        .map(|(_, byte)| byte)
+        .unwrap()
+}
+
+/// Find the last command of the frame that changed the location according to the evaluator.
+fn find_command(
+    demo: &DemoBuilder,
+    config: &EngineConfig,
+    previous_state: &State,
+    frame: usize,
+    shown_screen_chars_address: u16,
+    address: u16,
+) -> Option<usize> {
+    let mut state = previous_state.clone();
+    state.swap_screen_buffers();
+    let mut result = None;
+    for (command_index, command) in demo.frames[frame].commands.iter().enumerate() {
+        if matches!(
+            command,
+            Command::GotoFrame(_) | Command::LoopStart(_) | Command::LoopEnd(_)
+        ) {
+            continue;
+        }
+        let before = expected_byte(&state, config, shown_screen_chars_address, address);
+        state.apply(command);
+        if expected_byte(&state, config, shown_screen_chars_address, address) != before {
+            result = Some(command_index);
+        }
+    }
+    result
+}
+
+/// Play all frames of the engine data with the engine and compare screen and charset RAM with the evaluator after
+/// each frame.
+///
+/// Returns the number of played frames, or the first divergence.
+pub fn cross_check(engine_data: &[u8]) -> AssemblerResult<Result<usize, Divergence>> {
+    cross_check_with(engine_data, |_| {})
+}
+
+/// Like [cross_check], but allows changing the runner before the frames are played.
+pub fn cross_check_with(
+    engine_data: &[u8],
+    prepare: impl FnOnce(&mut EngineRunner),
+) -> AssemblerResult<Result<usize, Divergence>> {
+    run_cross_check(engine_data, EngineConfig::default(), prepare)
+}
+
+/// Like [cross_check], but builds the engine with the given config.
+pub fn cross_check_with_config(engine_data: &[u8], config: EngineConfig) -> AssemblerResult<Result<usize, Divergence>> {
+    run_cross_check(engine_data, config, |_| {})
+}
+
+fn run_cross_check(
+    engine_data: &[u8],
+    config: EngineConfig,
+    prepare: impl FnOnce(&mut EngineRunner),
+) -> AssemblerResult<Result<usize, Divergence>> {
+    let options = EvaluateOptions {
+        double_buffered: config.is_double_buffered(),
+    };
+    let states = evaluate_with_options(engine_data, options, |_| {}).expect("engine data should be valid");
+    let (demo, _) = engine_data.read::<DemoBuilder>().unwrap();
+    let mut runner = EngineRunner::new(engine_data, &states[0], config)?;
+    prepare(&mut runner);
+
+    for played_frame in 1..states.len() {
+        runner.run_frame();
+        let state = &states[played_frame];
+        if let Some((address, expected, actual)) = compare(&mut runner, state) {
+            let shown_screen_chars_address = runner.shown_screen_chars_address();
+            return Ok(Err(Divergence {
+                played_frame,
+                frame: state.frame,
+                command: find_command(
+                    &demo,
+                    &config,
+                    &states[played_frame - 1],
+                    state.frame,
+                    shown_screen_chars_address,
+                    address,
+                ),
+                address,
+                expected,
+                actual,
+            }));
+        }
+    }
+    Ok(Ok(states.len() - 1))
+}
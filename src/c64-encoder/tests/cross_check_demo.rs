@@ -0,0 +1,568 @@
+mod common;
+
+use c64_assembler::{
+    builder::{ApplicationBuilder, FunctionBuilder, InstructionBuilder, ModuleBuilder},
+    validator::AssemblerResult,
+};
+use c64_encoder::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder, screen_buffers::ScreenBuffers},
+    command::{
+        modules::{EngineBuilder, EngineConfig, EngineOptions, IrqPlayerOptions},
+        partial_update_text_mode::PartialUpdateTextModeScreen,
+        scan_order::ScanOrder,
+        set_sprite_pointers::SetSpritePointers,
+        update_chars::UpdateChar,
+        update_screen_chars_rle::UpdateScreenCharsRLE,
+        update_screen_chars_rle_extended::UpdateScreenCharsRLEExtended,
+        update_text_mode_screen::UpdateTextModeScreen,
+        Command,
+    },
+    encoder::Encoder,
+    evaluator::{
+        evaluate_with_options,
+        state::{ScreenBuffer, State},
+        EvaluateOptions,
+    },
+    optimizer::FrameOptimizer,
+    profiler::{EngineRunner, VideoStandard},
+};
+use common::{cross_check, cross_check_with, cross_check_with_config, Divergence};
+use mos6502::memory::Bus;
+
+fn screen(char: impl Fn(usize) -> u8) -> [u8; 1000] {
+    let mut result = [0; 1000];
+    for (offset, screen_char) in result.iter_mut().enumerate() {
+        *screen_char = char(offset);
+    }
+    result
+}
+
+#[test]
+fn cross_check_all_screen_and_charset_commands() -> AssemblerResult<()> {
+    let screen1 = screen(|offset| (offset % 256) as u8);
+    let mut screen2 = screen1;
+    screen2[10] = 0x80;
+    screen2[500] = 0x81;
+    let screen3 = screen(|offset| (offset / 40) as u8);
+
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .clear_screen_chars(0x20)
+            .update_chars(&[
+                UpdateChar {
+                    char: 0,
+                    data: 0xFF818181818181FF,
+                },
+                UpdateChar {
+                    char: 0x20,
+                    data: 0x0123456789ABCDEF,
+                },
+            ])
+            .build(),
+    )
+    .frame(
+        FrameBuilder::default()
+            .update_text_mode_screen(UpdateTextModeScreen { chars: screen1 })
+            .push(Command::update_chars_ranged(
+                0x80,
+                &[0x00FF00FF00FF00FF, 0x1122334455667788],
+            ))
+            .build(),
+    )
+    .loop_start()
+    .frame(
+        FrameBuilder::default()
+            .push(Command::PartialUpdateTextModeScreen(
+                PartialUpdateTextModeScreen::transition(&screen1, &screen2),
+            ))
+            .build(),
+    )
+    .frame(
+        FrameBuilder::default()
+            .push(Command::UpdateScreenCharsRLE(UpdateScreenCharsRLE::transition(
+                &screen2, &screen3,
+            )))
+            .build(),
+    )
+    .frame(
+        FrameBuilder::default()
+            .update_text_mode_screen(UpdateTextModeScreen { chars: screen1 })
+            .build(),
+    )
+    .loop_end(2)
+    .frame(FrameBuilder::default().clear_screen_chars(0x81).build());
+
+    let num_played_frames = cross_check(&demo.build())?.unwrap_or_else(|divergence| panic!("{divergence}"));
+    assert_eq!(9, num_played_frames);
+    Ok(())
+}
+
+#[test]
+fn cross_check_reports_diverging_command() -> AssemblerResult<()> {
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .update_chars(&[UpdateChar { char: 1, data: 0 }])
+            .update_text_mode_screen(UpdateTextModeScreen::filled(1))
+            .build(),
+    );
+    let engine_data = demo.build();
+
+    // Change the 10th screen char of the update text mode screen command in the engine data.
+    let update_text_mode_screen_offset = demo.frame_offset(0) + 2 + demo.frames[0].commands[0].byte_size() + 1;
+    let divergence = cross_check_with(&engine_data, |runner| {
+        let address = runner.engine_data_address + (update_text_mode_screen_offset + 10) as u16;
+        runner.cpu.memory.set_byte(address, 0x42);
+    })?;
+    assert_eq!(
+        Err(Divergence {
+            played_frame: 1,
+            frame: 0,
+            command: Some(1),
+            address: 0xC00A,
+            expected: 1,
+            actual: 0x42,
+        }),
+        divergence
+    );
+
+    // Screen RAM isn't changed by the frame, the engine is blamed.
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .update_chars(&[UpdateChar { char: 1, data: 0 }])
+            .build(),
+    );
+    let divergence = cross_check_with(&demo.build(), |runner| runner.cpu.memory.set_byte(0xC005, 0x42))?;
+    assert_eq!(
+        Err(Divergence {
+            played_frame: 1,
+            frame: 0,
+            command: None,
+            address: 0xC005,
+            expected: 0,
+            actual: 0x42,
+        }),
+        divergence
+    );
+    Ok(())
+}
+
+#[test]
+fn cross_check_packed_frames() -> AssemblerResult<()> {
+    let screen1 = screen(|offset| (offset % 40) as u8);
+    let screen2 = screen(|offset| if offset % 80 < 40 { 0x20 } else { 0x81 });
+
+    let mut demo = DemoBuilder::default();
+    demo.pack_frames(true)
+        .frame(
+            FrameBuilder::default()
+                .update_text_mode_screen(UpdateTextModeScreen { chars: screen1 })
+                .update_chars(&[UpdateChar {
+                    char: 0x81,
+                    data: 0xFF818181818181FF,
+                }])
+                .build(),
+        )
+        .loop_start()
+        .frame(
+            FrameBuilder::default()
+                .push(Command::UpdateScreenCharsRLE(UpdateScreenCharsRLE::transition(
+                    &screen1, &screen2,
+                )))
+                .build(),
+        )
+        .frame(
+            FrameBuilder::default()
+                .update_text_mode_screen(UpdateTextModeScreen { chars: screen1 })
+                .build(),
+        )
+        .loop_end(2)
+        .frame(FrameBuilder::default().clear_screen_chars(0x20).build())
+        .goto_frame(1);
+    assert!(demo.frames[..3]
+        .iter()
+        .all(|frame| matches!(frame.commands[0], Command::PackedCommands(_))));
+
+    let num_played_frames = cross_check(&demo.build())?.unwrap_or_else(|divergence| panic!("{divergence}"));
+    assert_eq!(7, num_played_frames);
+    Ok(())
+}
+
+#[test]
+fn cross_check_engine_config() -> AssemblerResult<()> {
+    let config = EngineConfig {
+        vic_bank: 1,
+        screen_slot: 2,
+        back_screen_slot: None,
+        charset_slot: 3,
+        zero_page_base: 0x40,
+        pointer_zero_page_base: 0x50,
+        depack_buffer_address: 0x6000,
+    };
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .clear_screen_chars(0x20)
+            .update_chars(&[UpdateChar {
+                char: 0x20,
+                data: 0x0123456789ABCDEF,
+            }])
+            .build(),
+    )
+    .frame(
+        FrameBuilder::default()
+            .update_text_mode_screen(UpdateTextModeScreen {
+                chars: screen(|offset| (offset % 256) as u8),
+            })
+            .push(Command::update_chars_ranged(0x80, &[0x00FF00FF00FF00FF]))
+            .pack()
+            .build(),
+    );
+    let engine_data = demo.build();
+
+    let mut runner = EngineRunner::new(&engine_data, &State::default(), config)?;
+    assert_eq!(0x02, runner.cpu.memory.get_byte(0xDD00));
+    assert_eq!(0b00100110, runner.cpu.memory.get_byte(0xD018));
+    let current_ptr = runner.cpu.memory.get_byte(0x53) as u16 | (runner.cpu.memory.get_byte(0x54) as u16) << 8;
+    assert_eq!(runner.engine_data_address + 2, current_ptr);
+
+    let num_played_frames =
+        cross_check_with_config(&engine_data, config)?.unwrap_or_else(|divergence| panic!("{divergence}"));
+    assert_eq!(2, num_played_frames);
+    Ok(())
+}
+
+#[test]
+fn cross_check_double_buffered() -> AssemblerResult<()> {
+    let config = EngineConfig {
+        back_screen_slot: Some(1),
+        ..EngineConfig::default()
+    };
+    let screens = [
+        screen(|offset| (offset % 256) as u8),
+        screen(|offset| (offset / 40) as u8),
+        screen(|offset| (offset % 40) as u8),
+        screen(|offset| if offset < 500 { 0x20 } else { 0x81 }),
+    ];
+    let mut screen_buffers = ScreenBuffers::for_config(&config, [0; 1000]);
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .update_text_mode_screen(UpdateTextModeScreen { chars: screens[0] })
+            .push(Command::SetSpritePointers(SetSpritePointers { pointers: [1; 8] }))
+            .build(),
+    );
+    screen_buffers.next_frame(&screens[0]);
+    demo.frame(
+        FrameBuilder::default()
+            .push(screen_buffers.transition(&screens[1]))
+            .push(Command::SetSpritePointers(SetSpritePointers { pointers: [2; 8] }))
+            .build(),
+    );
+    demo.frame(
+        FrameBuilder::default()
+            .clear_screen_chars(0x20)
+            .push(Command::PartialUpdateTextModeScreen(
+                PartialUpdateTextModeScreen::transition(&[0x20; 1000], &screens[2]),
+            ))
+            .build(),
+    );
+    screen_buffers.next_frame(&screens[2]);
+    demo.frame(
+        FrameBuilder::default()
+            .push(screen_buffers.transition(&screens[3]))
+            .build(),
+    );
+    assert_eq!(&screens[2], screen_buffers.back_screen_chars());
+    assert_eq!(&screens[3], screen_buffers.shown_screen_chars());
+    let engine_data = demo.build();
+
+    let options = EvaluateOptions { double_buffered: true };
+    let states = evaluate_with_options(&engine_data, options, |_| {}).unwrap();
+    for (state, expected_screen) in states[1..].iter().zip(&screens) {
+        assert_eq!(expected_screen, &state.text_screen.screen_chars);
+    }
+    assert_eq!([1; 8], states[3].sprites.pointers);
+    assert!(matches!(
+        &states[3].back_buffer,
+        Some(ScreenBuffer { sprite_pointers, .. }) if *sprite_pointers == [2; 8]
+    ));
+
+    let mut runner = EngineRunner::new(&engine_data, &states[0], config)?;
+    assert_eq!(0b00000010, runner.cpu.memory.get_byte(0xD018));
+    runner.run_frame();
+    assert_eq!(0b00010010, runner.cpu.memory.get_byte(0xD018));
+    assert_eq!(1, runner.cpu.memory.get_byte(0xC7F8));
+    runner.run_frame();
+    assert_eq!(0b00000010, runner.cpu.memory.get_byte(0xD018));
+    assert_eq!(2, runner.cpu.memory.get_byte(0xC3F8));
+    assert_eq!(1, runner.cpu.memory.get_byte(0xC7F8));
+
+    let num_played_frames =
+        cross_check_with_config(&engine_data, config)?.unwrap_or_else(|divergence| panic!("{divergence}"));
+    assert_eq!(4, num_played_frames);
+    Ok(())
+}
+
+#[test]
+fn cross_check_double_buffered_transitions() -> AssemblerResult<()> {
+    let config = EngineConfig {
+        back_screen_slot: Some(1),
+        ..EngineConfig::default()
+    };
+    // Alternating chars need more run-length packets than fit in a byte.
+    let screens = [
+        screen(|offset| (offset % 2) as u8),
+        screen(|offset| (offset % 3) as u8),
+        screen(|offset| (offset / 40) as u8),
+        screen(|offset| (offset % 2) as u8 + 1),
+    ];
+    let charset = [0x0123456789ABCDEF; 32];
+    let optimizer = FrameOptimizer::default();
+    let mut screen_buffers = ScreenBuffers::for_config(&config, [0; 1000]);
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .push(screen_buffers.transition(&screens[0]))
+            .build(),
+    );
+    demo.frame(
+        FrameBuilder::default()
+            .push(screen_buffers.transition(&screens[1]))
+            .build(),
+    );
+    for (index, to_screen_chars) in screens.iter().enumerate().skip(2) {
+        let from_charset = (index > 2).then_some(charset.as_slice());
+        let mut frame = FrameBuilder::default();
+        let transition = screen_buffers.frame_transition(to_screen_chars, from_charset, &charset);
+        frame.extend(&optimizer.optimize(&transition).unwrap());
+        demo.frame(frame.build());
+        screen_buffers.next_frame(to_screen_chars);
+    }
+    let engine_data = demo.build();
+
+    let options = EvaluateOptions { double_buffered: true };
+    let states = evaluate_with_options(&engine_data, options, |_| {}).unwrap();
+    for (state, expected_screen) in states[1..].iter().zip(&screens) {
+        assert_eq!(expected_screen, &state.text_screen.screen_chars);
+    }
+
+    let num_played_frames =
+        cross_check_with_config(&engine_data, config)?.unwrap_or_else(|divergence| panic!("{divergence}"));
+    assert_eq!(4, num_played_frames);
+    Ok(())
+}
+
+#[test]
+fn clear_screen_chars_keeps_sprite_pointers() -> AssemblerResult<()> {
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .push(Command::SetSpritePointers(SetSpritePointers {
+                pointers: [0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17],
+            }))
+            .build(),
+    )
+    .frame(FrameBuilder::default().clear_screen_chars(0x20).build());
+    let engine_data = demo.build();
+
+    let states = evaluate_with_options(&engine_data, EvaluateOptions::default(), |_| {}).unwrap();
+    let config = EngineConfig::default();
+    let mut runner = EngineRunner::new(&engine_data, &states[0], config)?;
+    for state in &states[1..] {
+        runner.run_frame();
+        for (sprite, pointer) in state.sprites.pointers.iter().enumerate() {
+            let address = config.sprite_pointers_address() + sprite as u16;
+            assert_eq!(*pointer, runner.cpu.memory.get_byte(address));
+        }
+    }
+    assert_eq!([0x20; 1000], states[2].text_screen.screen_chars);
+
+    let num_played_frames = cross_check(&engine_data)?.unwrap_or_else(|divergence| panic!("{divergence}"));
+    assert_eq!(2, num_played_frames);
+    Ok(())
+}
+
+#[test]
+fn irq_player() -> AssemblerResult<()> {
+    let mut demo = DemoBuilder::default();
+    demo.frame(FrameBuilder::default().clear_screen_chars(0x01).build())
+        .frame(FrameBuilder::default().clear_screen_chars(0x02).build());
+    let engine_data = demo.build();
+    let hook = ModuleBuilder::default()
+        .name("hook")
+        .function(
+            FunctionBuilder::default()
+                .name("hook")
+                .instructions(
+                    InstructionBuilder::default()
+                        .inc_addr("SCREEN_COLORS_PAGE0")
+                        .rts()
+                        .build(),
+                )
+                .build(),
+        )
+        .build();
+    let options = EngineOptions {
+        irq_player: Some(IrqPlayerOptions {
+            raster_line: 0x120,
+            interrupts_per_frame: 2,
+            hook: Some("hook"),
+        }),
+        ..EngineOptions::default()
+    };
+    let mut runner = EngineRunner::with_options(
+        &engine_data,
+        &State::default(),
+        options,
+        &[hook],
+        VideoStandard::default(),
+    )?;
+    let memory = |runner: &mut EngineRunner, address| runner.cpu.memory.get_byte(address);
+    assert_ne!([0x31, 0xEA], [memory(&mut runner, 0x0314), memory(&mut runner, 0x0315)]);
+    assert_eq!(0x01, memory(&mut runner, 0xD01A));
+    assert_eq!(0x80, memory(&mut runner, 0xD011) & 0x80);
+    assert_eq!(0x7F, memory(&mut runner, 0xDC0D));
+
+    let stack_pointer = runner.cpu.registers.stack_pointer.0;
+    for (expected_screen_char, expected_hook_calls) in [(0x01, 1), (0x01, 2), (0x02, 3)] {
+        runner.run_interrupt();
+        assert_eq!(expected_screen_char, memory(&mut runner, 0xC000));
+        assert_eq!(expected_hook_calls, memory(&mut runner, 0xD800));
+        assert_eq!(stack_pointer, runner.cpu.registers.stack_pointer.0);
+    }
+
+    runner.deinit();
+    assert_eq!([0x31, 0xEA], [memory(&mut runner, 0x0314), memory(&mut runner, 0x0315)]);
+    assert_eq!(0x00, memory(&mut runner, 0xD01A));
+    assert_eq!(0x81, memory(&mut runner, 0xDC0D));
+    runner.cpu.memory.set_byte(0xDC0D, 0x00);
+    runner.deinit();
+    assert_eq!(0x00, memory(&mut runner, 0xDC0D));
+    Ok(())
+}
+
+#[test]
+fn irq_player_calls_hook_during_wait() -> AssemblerResult<()> {
+    let mut demo = DemoBuilder::default();
+    demo.frame(FrameBuilder::default().clear_screen_chars(0x01).wait_frames(2).build())
+        .frame(FrameBuilder::default().clear_screen_chars(0x02).build());
+    let engine_data = demo.build();
+    let hook = ModuleBuilder::default()
+        .name("hook")
+        .function(
+            FunctionBuilder::default()
+                .name("hook")
+                .instructions(
+                    InstructionBuilder::default()
+                        .inc_addr("SCREEN_COLORS_PAGE0")
+                        .rts()
+                        .build(),
+                )
+                .build(),
+        )
+        .build();
+    let options = EngineOptions {
+        irq_player: Some(IrqPlayerOptions {
+            hook: Some("hook"),
+            ..IrqPlayerOptions::default()
+        }),
+        ..EngineOptions::default()
+    };
+    let mut runner = EngineRunner::with_options(
+        &engine_data,
+        &State::default(),
+        options,
+        &[hook],
+        VideoStandard::default(),
+    )?;
+    let memory = |runner: &mut EngineRunner, address| runner.cpu.memory.get_byte(address);
+
+    // The wait returns from the interrupt, the next 2 interrupts only call the hook.
+    let stack_pointer = runner.cpu.registers.stack_pointer.0;
+    for (expected_screen_char, expected_hook_calls) in [(0x01, 1), (0x01, 2), (0x01, 3), (0x02, 4)] {
+        runner.run_interrupt();
+        assert_eq!(expected_screen_char, memory(&mut runner, 0xC000));
+        assert_eq!(expected_hook_calls, memory(&mut runner, 0xD800));
+        assert_eq!(stack_pointer, runner.cpu.registers.stack_pointer.0);
+    }
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "cannot be combined with sync_raster_line")]
+fn irq_player_rejects_sync_raster_line() {
+    ApplicationBuilder::default().add_engine_with_options(EngineOptions {
+        sync_raster_line: Some(0),
+        irq_player: Some(IrqPlayerOptions::default()),
+        ..EngineOptions::default()
+    });
+}
+
+#[test]
+fn cross_check_extended_screen_chars_rle() -> AssemblerResult<()> {
+    let config = EngineConfig {
+        back_screen_slot: Some(1),
+        ..EngineConfig::default()
+    };
+    let screens = [
+        screen(|offset| (offset / 3 * 7 % 255 + 1) as u8),
+        screen(|offset| (offset / 2 * 5 % 255) as u8),
+        screen(|offset| if offset < 700 { 0x20 } else { 0x81 }),
+    ];
+    let mut screen_buffers = ScreenBuffers::for_config(&config, [0; 1000]);
+    let mut demo = DemoBuilder::default();
+    for to_screen_chars in &screens {
+        let command = Command::update_screen_chars_rle(screen_buffers.back_screen_chars(), to_screen_chars);
+        demo.frame(FrameBuilder::default().push(command).build());
+        screen_buffers.next_frame(to_screen_chars);
+    }
+    assert!(demo.frames[..2]
+        .iter()
+        .all(|frame| matches!(frame.commands[0], Command::UpdateScreenCharsRLEExtended(_))));
+    assert!(matches!(demo.frames[2].commands[0], Command::UpdateScreenCharsRLE(_)));
+
+    let num_played_frames =
+        cross_check_with_config(&demo.build(), config)?.unwrap_or_else(|divergence| panic!("{divergence}"));
+    assert_eq!(3, num_played_frames);
+    Ok(())
+}
+
+#[test]
+fn cross_check_scan_order_screen_chars_rle() -> AssemblerResult<()> {
+    let config = EngineConfig {
+        back_screen_slot: Some(1),
+        ..EngineConfig::default()
+    };
+    let frames = [
+        (ScanOrder::ColumnMajor, screen(|offset| (offset % 40 * 3) as u8)),
+        (
+            ScanOrder::Tiles8x8,
+            screen(|offset| ((offset / 320 * 5 + offset % 40 / 8) * 7) as u8),
+        ),
+        (ScanOrder::ColumnMajor, screen(|offset| (offset * offset / 13) as u8)),
+        (
+            ScanOrder::Tiles8x8,
+            screen(|offset| if offset % 40 < 20 { 0x20 } else { 0x81 }),
+        ),
+    ];
+    let mut screen_buffers = ScreenBuffers::for_config(&config, [0; 1000]);
+    let mut demo = DemoBuilder::default();
+    for (scan_order, to_screen_chars) in &frames {
+        let update =
+            UpdateScreenCharsRLEExtended::transition(screen_buffers.back_screen_chars(), to_screen_chars, *scan_order);
+        demo.frame(
+            FrameBuilder::default()
+                .push(Command::UpdateScreenCharsRLEExtended(update))
+                .build(),
+        );
+        screen_buffers.next_frame(to_screen_chars);
+    }
+
+    let num_played_frames =
+        cross_check_with_config(&demo.build(), config)?.unwrap_or_else(|divergence| panic!("{divergence}"));
+    assert_eq!(4, num_played_frames);
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use c64_assembler::{
+    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    generator::{Generator, ProgramGenerator},
+    validator::{AssemblerResult, Validator},
+};
+use c64_encoder::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder},
+    command::{modules::EngineBuilder, update_text_mode_screen::UpdateTextModeScreen},
+    evaluator::evaluate,
+};
+use mos6502::{
+    cpu::CPU,
+    instruction::Nmos6502,
+    memory::{Bus, Memory},
+};
+
+/// Run the engine for the given number of frames and return the first screen char after each frame.
+fn run_engine(engine_data: &[u8], num_frames: u8) -> AssemblerResult<Vec<u8>> {
+    let application = ApplicationBuilder::default()
+        .include_vic2_defines()
+        .define_address("FRAME_LOG", 0x0300)
+        .module(
+            ModuleBuilder::default()
+                .instructions(
+                    InstructionBuilder::default()
+                        .jsr_addr("engine__init")
+                        .ldx_imm(0)
+                        .label("next_frame")
+                        .txa()
+                        .pha()
+                        .jsr_addr("engine__frame__process")
+                        .pla()
+                        .tax()
+                        .lda_addr("SCREEN_CHARS_PAGE0")
+                        .sta_addr_x("FRAME_LOG")
+                        .inx()
+                        .cpx_imm(num_frames)
+                        .bne_addr("next_frame")
+                        .raw(&[0xFF])
+                        .build(),
+                )
+                .build(),
+        )
+        .add_engine()
+        .module(
+            ModuleBuilder::default()
+                .name("engine_data")
+                .instructions(
+                    InstructionBuilder::default()
+                        .label("engine_data")
+                        .raw(engine_data)
+                        .build(),
+                )
+                .build(),
+        )
+        .build()?;
+    application.validate()?;
+    let bytes = ProgramGenerator::default().generate(application)?;
+
+    let mut cpu = CPU::new(Memory::new(), Nmos6502);
+    cpu.memory.set_bytes(0x0800, &bytes[2..]);
+    cpu.registers.program_counter = 0x0800;
+
+    cpu.run();
+
+    Ok((0..num_frames as u16)
+        .map(|frame| cpu.memory.get_byte(0x0300 + frame))
+        .collect())
+}
+
+fn frame(screen_char: u8) -> FrameBuilder {
+    FrameBuilder::default().clear_screen_chars(screen_char).build()
+}
+
+#[test]
+fn decode_loop_and_goto_frame() -> AssemblerResult<()> {
+    let mut demo = DemoBuilder::default();
+    demo.frame(frame(1))
+        .loop_start()
+        .frame(frame(2))
+        .frame(frame(3))
+        .loop_end(3)
+        .frame(frame(4))
+        .goto_frame(1);
+    let engine_data = demo.build();
+
+    let expected = [1, 2, 3, 2, 3, 2, 3, 4, 2, 3, 2, 3, 2, 3, 4, 2];
+    let screen_chars = run_engine(&engine_data, expected.len() as u8)?;
+    assert_eq!(expected.to_vec(), screen_chars);
+
+    // The evaluator stops when a frame is reached with the same loop state as before, after the goto frame 2 was
+    // already displayed with the loop start recorded and no iterations left.
+    let states = evaluate(&engine_data).unwrap();
+    let evaluated_screen_chars = states[1..]
+        .iter()
+        .map(|state| state.text_screen.screen_chars[0])
+        .collect::<Vec<u8>>();
+    assert_eq!(expected[..9].to_vec(), evaluated_screen_chars);
+
+    Ok(())
+}
+
+#[test]
+fn evaluate_endless_goto_frame() {
+    let mut demo = DemoBuilder::default();
+    demo.frame(frame(1)).frame(frame(2)).goto_frame(0);
+    let states = evaluate(&demo.build()).unwrap();
+
+    assert_eq!(3, states.len());
+    assert_eq!(2, states[2].text_screen.screen_chars[0]);
+}
+
+#[test]
+#[should_panic(expected = "frame 70 starts at offset 70212, goto frame can't reach it")]
+fn goto_frame_beyond_u16_offset() {
+    let mut demo = DemoBuilder::default();
+    for frame_index in 0..=70 {
+        demo.frame(
+            FrameBuilder::default()
+                .update_text_mode_screen(UpdateTextModeScreen {
+                    chars: [frame_index; 1000],
+                })
+                .build(),
+        );
+    }
+    demo.goto_frame(70);
+}
@@ -0,0 +1,129 @@
+use c64_assembler::{
+    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    generator::{Generator, ProgramGenerator},
+    validator::{AssemblerResult, Validator},
+};
+use c64_encoder::{
+    builder::frame::FrameBuilder,
+    command::{
+        modules::CurrentPTR,
+        packed_commands::{PackedCommands, PackedCommandsEnd},
+        update_chars::UpdateChar,
+        update_text_mode_screen::UpdateTextModeScreen,
+        Command, DecoderModule, PACKED_COMMANDS, PACKED_COMMANDS_END,
+    },
+    encoder::Encoder,
+    lz::{pack, unpack},
+};
+use mos6502::{
+    cpu::CPU,
+    instruction::Nmos6502,
+    memory::{Bus, Memory},
+};
+
+/// Address of the buffer the commands are unpacked in.
+const DEPACK_BUFFER_ADDRESS: u16 = 0x8000;
+/// Address of `jsr packed_commands_end__process`, after `jsr packed_commands__process` and the stop instruction.
+const END_PROCESS_ADDRESS: u16 = 0x0804;
+
+fn build_program() -> AssemblerResult<Vec<u8>> {
+    let application = ApplicationBuilder::default()
+        .define_address("CURRENT_PTR", 0xFE)
+        .define_address("DEPACK_DST_PTR", 0x12)
+        .define_address("DEPACK_MATCH_PTR", 0x14)
+        .define_address("DEPACK_BUFFER", DEPACK_BUFFER_ADDRESS)
+        .module(
+            ModuleBuilder::default()
+                .instructions(
+                    InstructionBuilder::default()
+                        .jsr_addr("packed_commands__process")
+                        .raw(&[0xFF])
+                        .jsr_addr("packed_commands_end__process")
+                        .raw(&[0xFF])
+                        .build(),
+                )
+                .build(),
+        )
+        .module(PackedCommands::module())
+        .module(PackedCommandsEnd::module())
+        .module(CurrentPTR::module())
+        .build()?;
+
+    application.validate()?;
+
+    ProgramGenerator::default().generate(application)
+}
+
+fn current_ptr(cpu: &mut CPU<Memory, Nmos6502>) -> u16 {
+    cpu.memory.get_byte(0x00FE) as u16 | (cpu.memory.get_byte(0x00FF) as u16) << 8
+}
+
+/// Unpack the packed data with the depacker and return the content of the depack buffer.
+fn depack(packed_data: &[u8]) -> AssemblerResult<Vec<u8>> {
+    let (expected, _) = unpack(packed_data).unwrap();
+    let mut command = vec![PACKED_COMMANDS];
+    command.extend_from_slice(packed_data);
+    let bytes = build_program()?;
+
+    let mut cpu = CPU::new(Memory::new(), Nmos6502);
+    cpu.memory.set_bytes(0x00FE, &[0x00, 0x04]);
+    cpu.memory.set_bytes(0x0800, &bytes[2..]);
+    cpu.memory.set_bytes(0x0400, &command);
+    cpu.registers.program_counter = 0x0800;
+    cpu.run();
+    assert_eq!(DEPACK_BUFFER_ADDRESS, current_ptr(&mut cpu));
+
+    cpu.registers.program_counter = END_PROCESS_ADDRESS;
+    cpu.run();
+    assert_eq!(0x0400 + command.len() as u16, current_ptr(&mut cpu));
+
+    Ok((0..expected.len())
+        .map(|offset| cpu.memory.get_byte(DEPACK_BUFFER_ADDRESS + offset as u16))
+        .collect())
+}
+
+#[test]
+fn depack_literals() -> AssemblerResult<()> {
+    let data = (0..=255).collect::<Vec<u8>>();
+    assert_eq!(data, depack(&pack(&data))?);
+    Ok(())
+}
+
+#[test]
+fn depack_matches() -> AssemblerResult<()> {
+    let mut data = vec![0x20; 300];
+    data.extend_from_slice(b"HELLO WORLD, HELLO WORLD, HELLO C64");
+    data.extend((0..200).map(|index| (index % 7) as u8));
+    data.extend_from_slice(b"HELLO WORLD");
+    let packed_data = pack(&data);
+    assert!(packed_data.len() < data.len() / 4);
+    assert_eq!(data, depack(&packed_data)?);
+    Ok(())
+}
+
+#[test]
+fn depack_commands() -> AssemblerResult<()> {
+    let mut screen = [0x20; 1000];
+    for (offset, screen_char) in screen.iter_mut().enumerate().skip(400).take(200) {
+        *screen_char = (offset % 40) as u8;
+    }
+    let frame = FrameBuilder::default()
+        .update_text_mode_screen(UpdateTextModeScreen { chars: screen })
+        .update_chars(&[UpdateChar {
+            char: 1,
+            data: 0x0123456789ABCDEF,
+        }])
+        .set_border_color(c64_colors::colors::Color::Red)
+        .build();
+    let packed = Command::PackedCommands(PackedCommands::new(frame.commands.clone()));
+    let mut encoded_data = vec![0; packed.byte_size()];
+    packed.encode(&mut encoded_data);
+
+    let depacked = depack(&encoded_data[1..])?;
+
+    let mut expected = vec![0; frame.commands.byte_size()];
+    frame.commands.encode(&mut expected);
+    assert_eq!(expected, depacked[..expected.len()]);
+    assert_eq!(Some(&PACKED_COMMANDS_END), depacked.last());
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use c64_assembler::{
+    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    generator::{Generator, ProgramGenerator},
+    validator::{AssemblerResult, Validator},
+};
+use c64_encoder::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder},
+    charmap::encoding::is_u16_encodable,
+    command::{
+        modules::{CopyRawChar, CurrentPTR},
+        update_chars::UpdateChar,
+        update_chars_ranged::UpdateCharRanged,
+        update_chars_ranged_raw::UpdateCharsRangedRaw,
+        update_chars_raw::UpdateCharsRaw,
+        Command, DecoderModule,
+    },
+    encoder::Encoder,
+    evaluator::evaluate,
+};
+use mos6502::{
+    cpu::CPU,
+    instruction::Nmos6502,
+    memory::{Bus, Memory},
+};
+
+const LOSSY_CHAR: u64 = 0x8142241818244281;
+const LOSSLESS_CHAR: u64 = 0xF0F0F0F00F0F0F0F;
+
+fn build_program(process_function: &str) -> AssemblerResult<Vec<u8>> {
+    let application = ApplicationBuilder::default()
+        .define_address("CHAR_DECODE_SRC_PTR", 0xFE)
+        .define_address("CHAR_DECODE_DST_PTR", 0xFC)
+        .define_address("CURRENT_PTR", 0xFE)
+        .define_address("CHARSET_PTR_PAGE0", 0x2000)
+        .module(
+            ModuleBuilder::default()
+                .instructions(
+                    InstructionBuilder::default()
+                        .jsr_addr(process_function)
+                        .raw(&[0xFF])
+                        .build(),
+                )
+                .build(),
+        )
+        .module(CurrentPTR::module())
+        .module(UpdateCharsRaw::module())
+        .module(UpdateCharsRangedRaw::module())
+        .module(CopyRawChar::module())
+        .build()?;
+
+    application.validate()?;
+
+    ProgramGenerator::default().generate(application)
+}
+
+fn run(process_function: &str, encoder: &impl Encoder) -> AssemblerResult<CPU<Memory, Nmos6502>> {
+    let mut command = vec![0; encoder.byte_size() + 1];
+    encoder.encode(&mut command[1..]);
+    let bytes = build_program(process_function)?;
+
+    let mut cpu = CPU::new(Memory::new(), Nmos6502);
+    cpu.memory.set_bytes(0x00FE, &[0x00, 0x04]);
+    cpu.memory.set_bytes(0x0800, &bytes[2..]);
+    cpu.memory.set_bytes(0x0400, &command);
+    cpu.registers.program_counter = 0x0800;
+
+    cpu.run();
+
+    assert_eq!(command.len() as u8, cpu.memory.get_byte(0x00FE));
+    assert_eq!(0x04, cpu.memory.get_byte(0x00FF));
+
+    Ok(cpu)
+}
+
+fn assert_char(cpu: &mut CPU<Memory, Nmos6502>, char: u8, expected: u64) {
+    let address = 0x2000 + char as u16 * 8;
+    for (row, expected) in expected.to_be_bytes().iter().enumerate() {
+        assert_eq!(
+            *expected,
+            cpu.memory.get_byte(address + row as u16),
+            "char={char:02X}, row={row}"
+        );
+    }
+}
+
+#[test]
+fn decode_update_chars_raw() -> AssemblerResult<()> {
+    let update_chars = UpdateCharsRaw {
+        chars: vec![
+            UpdateChar {
+                char: 0x02,
+                data: LOSSY_CHAR,
+            },
+            UpdateChar {
+                char: 0xF1,
+                data: 0x0123456789ABCDEF,
+            },
+        ],
+    };
+
+    let mut cpu = run("update_chars_raw__process", &update_chars)?;
+
+    assert_char(&mut cpu, 0x02, LOSSY_CHAR);
+    assert_char(&mut cpu, 0xF1, 0x0123456789ABCDEF);
+    assert_char(&mut cpu, 0x03, 0x0000000000000000);
+
+    Ok(())
+}
+
+#[test]
+fn decode_update_chars_ranged_raw() -> AssemblerResult<()> {
+    let chars = [LOSSY_CHAR, 0x0123456789ABCDEF, LOSSLESS_CHAR, 0xFEDCBA9876543210];
+    let update_chars_ranged = UpdateCharsRangedRaw {
+        offset: 30,
+        chars: chars.iter().map(|data| UpdateCharRanged { data: *data }).collect(),
+    };
+
+    let mut cpu = run("update_chars_ranged_raw__process", &update_chars_ranged)?;
+
+    for (index, expected) in chars.iter().enumerate() {
+        assert_char(&mut cpu, 30 + index as u8, *expected);
+    }
+    assert_char(&mut cpu, 29, 0x0000000000000000);
+    assert_char(&mut cpu, 34, 0x0000000000000000);
+
+    Ok(())
+}
+
+#[test]
+fn update_chars_selects_raw_for_lossy_chars() {
+    assert!(!is_u16_encodable(LOSSY_CHAR));
+    assert!(is_u16_encodable(LOSSLESS_CHAR));
+
+    let chars = [
+        UpdateChar {
+            char: 1,
+            data: LOSSLESS_CHAR,
+        },
+        UpdateChar {
+            char: 2,
+            data: LOSSY_CHAR,
+        },
+    ];
+    let commands = Command::update_chars(&chars);
+    assert_eq!(2, commands.len());
+    assert!(matches!(&commands[0], Command::UpdateCharsU16Encoded(update_chars) if update_chars.chars.len() == 1));
+    assert!(matches!(&commands[1], Command::UpdateCharsRaw(update_chars) if update_chars.chars.len() == 1));
+
+    assert!(matches!(
+        Command::update_chars_ranged(0, &[LOSSLESS_CHAR, LOSSLESS_CHAR]),
+        Command::UpdateCharsRangedU16Encoded(_)
+    ));
+    assert!(matches!(
+        Command::update_chars_ranged(0, &[LOSSLESS_CHAR, LOSSY_CHAR]),
+        Command::UpdateCharsRangedRaw(_)
+    ));
+
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .update_chars(&chars)
+            .push(Command::update_chars_ranged(254, &[LOSSY_CHAR, LOSSLESS_CHAR]))
+            .build(),
+    );
+    let states = evaluate(&demo.build()).unwrap();
+    let charset = &states[1].charset;
+    assert_eq!(LOSSLESS_CHAR, charset.char(1));
+    assert_eq!(LOSSY_CHAR, charset.char(2));
+    assert_eq!(LOSSY_CHAR, charset.char(254));
+    assert_eq!(LOSSLESS_CHAR, charset.char(255));
+}
@@ -0,0 +1,210 @@
+use c64_assembler::{
+    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    generator::{Generator, ProgramGenerator},
+    validator::{AssemblerResult, Validator},
+};
+use c64_encoder::{
+    command::{
+        modules::{CurrentPTR, ScreenCharPTR},
+        scan_order::ScanOrder,
+        update_screen_chars_rle::{
+            RLE_MASK_AUTO_INCREMENT, RLE_MASK_SKIP_VALUES, RLE_MASK_UPDATE_VALUES, RLE_MASK_UPDATE_WITH_SINGLE_VALUE,
+        },
+        update_screen_chars_rle_extended::{
+            UpdateScreenCharsRLEExtended, RLE_EXTENDED_NUM_SCREEN_CHARS, RLE_TERMINATOR,
+        },
+        DecoderModule,
+    },
+    encoder::Encoder,
+};
+use mos6502::{
+    cpu::CPU,
+    instruction::Nmos6502,
+    memory::{Bus, Memory},
+};
+
+fn build_program(scan_order: ScanOrder) -> AssemblerResult<Vec<u8>> {
+    let module = UpdateScreenCharsRLEExtended::scan_order_module(scan_order);
+    let process = format!("{}__process", module.name);
+    let application = ApplicationBuilder::default()
+        .define_address("CURRENT_PTR", 0xFE)
+        .define_address("SCREEN_CHAR_PTR", 0xFC)
+        .define_address("SCREEN_CHARS_PAGE0", 0xC000)
+        .define_address("SCRATCH_SPACE_00", 0xFB)
+        .include_vic2_defines()
+        .module(
+            ModuleBuilder::default()
+                .instructions(InstructionBuilder::default().jsr_addr(&process).raw(&[0xFF]).build())
+                .build(),
+        )
+        .module(module)
+        .module(CurrentPTR::module())
+        .module(ScreenCharPTR::module())
+        .build()?;
+
+    application.validate()?;
+
+    ProgramGenerator::default().generate(application)
+}
+
+/// Decode the stream that follows the command type at $0400 into the screen chars at $C000, which are filled with
+/// $DE.
+fn decode(decode_stream: &[u8]) -> AssemblerResult<CPU<Memory, Nmos6502>> {
+    decode_scan_order(ScanOrder::RowMajor, decode_stream)
+}
+
+/// Decode the stream with the module for the scan order, see [decode].
+fn decode_scan_order(scan_order: ScanOrder, decode_stream: &[u8]) -> AssemblerResult<CPU<Memory, Nmos6502>> {
+    let program = build_program(scan_order)?;
+
+    let mut cpu = CPU::new(Memory::new(), Nmos6502);
+    cpu.memory.set_bytes(0x00FE, &[0x00, 0x04]);
+    cpu.memory.set_bytes(0x0800, &program[2..]);
+    cpu.registers.program_counter = 0x0800;
+
+    cpu.memory.set_bytes(0x0401, decode_stream);
+    cpu.memory.set_bytes(0xC000, &vec![0xDE; 1024]);
+
+    while let Some(decoded_instr) = cpu.fetch_next_and_decode() {
+        cpu.execute_instruction(decoded_instr);
+    }
+    Ok(cpu)
+}
+
+fn screen_chars(cpu: &mut CPU<Memory, Nmos6502>, len: u16) -> Vec<u8> {
+    (0xC000..0xC000 + len)
+        .map(|address| cpu.memory.get_byte(address))
+        .collect()
+}
+
+fn current_ptr(cpu: &mut CPU<Memory, Nmos6502>) -> u16 {
+    u16::from_le_bytes([cpu.memory.get_byte(0x00FE), cpu.memory.get_byte(0x00FF)])
+}
+
+fn screen_char_ptr(cpu: &mut CPU<Memory, Nmos6502>) -> u16 {
+    u16::from_le_bytes([cpu.memory.get_byte(0x00FC), cpu.memory.get_byte(0x00FD)])
+}
+
+#[test]
+fn screen_char_rle_extended_single_value() -> AssemblerResult<()> {
+    let decode_stream = [
+        // Packet: Update 200 values with 0xAD
+        RLE_MASK_UPDATE_WITH_SINGLE_VALUE | RLE_EXTENDED_NUM_SCREEN_CHARS,
+        200,
+        0xAD,
+        RLE_TERMINATOR,
+    ];
+
+    let mut cpu = decode(&decode_stream)?;
+
+    assert_eq!(0xC0C8, screen_char_ptr(&mut cpu));
+    assert_eq!(0x0405, current_ptr(&mut cpu));
+    let mut expected = vec![0xAD; 200];
+    expected.push(0xDE);
+    assert_eq!(expected, screen_chars(&mut cpu, 201));
+    Ok(())
+}
+
+#[test]
+fn screen_char_rle_extended_skip_and_auto_increment() -> AssemblerResult<()> {
+    let decode_stream = [
+        RLE_MASK_SKIP_VALUES | 0x05,
+        // Packet: 255 values starting at 0x00
+        RLE_MASK_AUTO_INCREMENT | RLE_EXTENDED_NUM_SCREEN_CHARS,
+        255,
+        0x00,
+        RLE_MASK_UPDATE_WITH_SINGLE_VALUE | 0x02,
+        0xAD,
+        RLE_TERMINATOR,
+    ];
+
+    let mut cpu = decode(&decode_stream)?;
+
+    assert_eq!(0xC106, screen_char_ptr(&mut cpu));
+    assert_eq!(0x0408, current_ptr(&mut cpu));
+    let mut expected = vec![0xDE; 5];
+    expected.extend(0..255);
+    expected.extend([0xAD, 0xAD, 0xDE]);
+    assert_eq!(expected, screen_chars(&mut cpu, 263));
+    Ok(())
+}
+
+#[test]
+fn screen_char_rle_extended_values() -> AssemblerResult<()> {
+    let values = (0..100).map(|value| value * 2).collect::<Vec<u8>>();
+    let mut decode_stream = vec![RLE_MASK_UPDATE_VALUES | RLE_EXTENDED_NUM_SCREEN_CHARS, 100];
+    decode_stream.extend(&values);
+    decode_stream.extend([RLE_MASK_UPDATE_VALUES | 0x02, 0x11, 0x22, RLE_TERMINATOR]);
+
+    let mut cpu = decode(&decode_stream)?;
+
+    assert_eq!(0xC066, screen_char_ptr(&mut cpu));
+    assert_eq!(0x0401 + decode_stream.len() as u16, current_ptr(&mut cpu));
+    let mut expected = values;
+    expected.extend([0x11, 0x22, 0xDE]);
+    assert_eq!(expected, screen_chars(&mut cpu, 103));
+    Ok(())
+}
+
+#[test]
+fn screen_char_rle_extended_many_packets() -> AssemblerResult<()> {
+    let to_screen_chars = std::array::from_fn::<u8, 1000, _>(|offset| (offset / 3 * 7 % 255 + 1) as u8);
+    let update = UpdateScreenCharsRLEExtended::transition(&[0xDE; 1000], &to_screen_chars, ScanOrder::RowMajor);
+    assert!(update.rle_packets.len() > 255);
+    let mut decode_stream = vec![0; update.byte_size()];
+    update.encode(&mut decode_stream);
+
+    let mut cpu = decode(&decode_stream)?;
+
+    assert_eq!(0x0401 + decode_stream.len() as u16, current_ptr(&mut cpu));
+    assert_eq!(to_screen_chars.to_vec(), screen_chars(&mut cpu, 1000));
+    Ok(())
+}
+
+/// Decode the transition from a screen filled with $DE in the given scan order and check the decoded screen chars.
+fn check_scan_order_transition(scan_order: ScanOrder, to_screen_chars: &[u8; 1000]) -> AssemblerResult<()> {
+    let update = UpdateScreenCharsRLEExtended::transition(&[0xDE; 1000], to_screen_chars, scan_order);
+    let mut decode_stream = vec![0; update.byte_size()];
+    update.encode(&mut decode_stream);
+
+    let mut cpu = decode_scan_order(scan_order, &decode_stream)?;
+
+    assert_eq!(0x0401 + decode_stream.len() as u16, current_ptr(&mut cpu));
+    assert_eq!(to_screen_chars.to_vec(), screen_chars(&mut cpu, 1000));
+    assert_eq!(0xDE, cpu.memory.get_byte(0xC000 + 1000));
+    Ok(())
+}
+
+/// Screen with columns of the same screen char, a different one for each column.
+fn vertical_stripes() -> [u8; 1000] {
+    std::array::from_fn(|offset| (offset % 40 * 3) as u8)
+}
+
+/// Screen with tiles of 8x8 of the same screen char, a different one for each tile.
+fn tiles() -> [u8; 1000] {
+    std::array::from_fn(|offset| ((offset / 320 * 5 + offset % 40 / 8) * 7) as u8)
+}
+
+/// Screen with unchanged screen chars, runs and random screen chars, so all packet types are used.
+fn mixed() -> [u8; 1000] {
+    std::array::from_fn(|offset| match offset / 7 % 4 {
+        0 => 0xDE,
+        1 => 0x20,
+        2 => (offset / 7) as u8,
+        _ => (offset * offset / 13) as u8,
+    })
+}
+
+#[test]
+fn screen_char_rle_columns() -> AssemblerResult<()> {
+    check_scan_order_transition(ScanOrder::ColumnMajor, &vertical_stripes())?;
+    check_scan_order_transition(ScanOrder::ColumnMajor, &tiles())?;
+    check_scan_order_transition(ScanOrder::ColumnMajor, &mixed())
+}
+
+#[test]
+fn screen_char_rle_tiles() -> AssemblerResult<()> {
+    check_scan_order_transition(ScanOrder::Tiles8x8, &vertical_stripes())?;
+    check_scan_order_transition(ScanOrder::Tiles8x8, &tiles())?;
+    check_scan_order_transition(ScanOrder::Tiles8x8, &mixed())
+}
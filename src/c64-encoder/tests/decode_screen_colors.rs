@@ -0,0 +1,196 @@
+use c64::image_container::Image;
+use c64_assembler::{
+    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    generator::{Generator, ProgramGenerator},
+    validator::{AssemblerResult, Validator},
+};
+use c64_colors::colors::{Color, SRGB};
+use c64_encoder::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder},
+    command::{
+        clear_screen_colors::ClearScreenColors,
+        modules::{CurrentPTR, ScreenCharPTR},
+        update_chars::UpdateChar,
+        update_chars_raw::UpdateCharsRaw,
+        update_screen_colors::UpdateScreenColors,
+        update_screen_colors_rle::UpdateScreenColorsRLE,
+        Command, DecoderModule,
+    },
+    encoder::Encoder,
+    evaluator::{
+        evaluate,
+        state::{State, BORDER_HEIGHT, BORDER_WIDTH},
+    },
+};
+use mos6502::{
+    cpu::CPU,
+    instruction::Nmos6502,
+    memory::{Bus, Memory},
+};
+
+fn build_program(process_function: &str) -> AssemblerResult<Vec<u8>> {
+    let application = ApplicationBuilder::default()
+        .define_address("CURRENT_PTR", 0xFE)
+        .define_address("SCREEN_CHAR_PTR", 0xFC)
+        .define_address("SCRATCH_SPACE_00", 0xFB)
+        .define_address("SCREEN_CHARS_PAGE0", 0xC000)
+        .define_address("SCREEN_COLORS_PAGE0", 0xD800)
+        .define_address("SCREEN_COLORS_PAGE1", 0xD900)
+        .define_address("SCREEN_COLORS_PAGE2", 0xDA00)
+        .define_address("SCREEN_COLORS_PAGE3", 0xDB00)
+        .module(
+            ModuleBuilder::default()
+                .instructions(
+                    InstructionBuilder::default()
+                        .jsr_addr(process_function)
+                        .raw(&[0xFF])
+                        .build(),
+                )
+                .build(),
+        )
+        .module(ClearScreenColors::module())
+        .module(UpdateScreenColors::module())
+        .module(UpdateScreenColorsRLE::module())
+        .module(CurrentPTR::module())
+        .module(ScreenCharPTR::module())
+        .build()?;
+
+    application.validate()?;
+
+    ProgramGenerator::default().generate(application)
+}
+
+fn run(process_function: &str, encoder: &impl Encoder, colors: &[u8; 1000]) -> AssemblerResult<[u8; 1000]> {
+    let mut command = vec![0; encoder.byte_size() + 1];
+    encoder.encode(&mut command[1..]);
+    let bytes = build_program(process_function)?;
+
+    let mut cpu = CPU::new(Memory::new(), Nmos6502);
+    cpu.memory.set_bytes(0x00FE, &[0x00, 0x04]);
+    cpu.memory.set_bytes(0x0800, &bytes[2..]);
+    cpu.memory.set_bytes(0x0400, &command);
+    cpu.memory.set_bytes(0xD800, colors);
+    cpu.registers.program_counter = 0x0800;
+
+    cpu.run();
+
+    let current_ptr = cpu.memory.get_byte(0x00FE) as u16 | (cpu.memory.get_byte(0x00FF) as u16) << 8;
+    assert_eq!(0x0400 + command.len() as u16, current_ptr);
+
+    let mut result = [0; 1000];
+    for (offset, color) in result.iter_mut().enumerate() {
+        *color = cpu.memory.get_byte(0xD800 + offset as u16);
+    }
+    Ok(result)
+}
+
+#[test]
+fn clear_screen_colors() -> AssemblerResult<()> {
+    let clear_screen_colors = ClearScreenColors { color: Color::Yellow };
+
+    let colors = run("clear_screen_colors__process", &clear_screen_colors, &[0; 1000])?;
+
+    assert_eq!([7; 1000], colors);
+    Ok(())
+}
+
+#[test]
+fn update_screen_colors() -> AssemblerResult<()> {
+    let mut update_screen_colors = UpdateScreenColors::default();
+    for (offset, color) in update_screen_colors.colors.iter_mut().enumerate() {
+        *color = (offset % 13) as u8;
+    }
+
+    let colors = run("update_screen_colors__process", &update_screen_colors, &[0x0F; 1000])?;
+
+    assert_eq!(update_screen_colors.colors, colors);
+    Ok(())
+}
+
+#[test]
+fn update_screen_colors_rle() -> AssemblerResult<()> {
+    let from_colors = [Color::LightBlue.into(); 1000];
+    let mut to_colors = from_colors;
+    to_colors[0..40].fill(Color::Red.into());
+    for (offset, color) in to_colors[300..316].iter_mut().enumerate() {
+        *color = offset as u8;
+    }
+    to_colors[520] = Color::White.into();
+    to_colors[990..1000].fill(Color::Black.into());
+    let update_screen_colors_rle = UpdateScreenColorsRLE::transition(&from_colors, &to_colors);
+
+    let colors = run(
+        "update_screen_colors_rle__process",
+        &update_screen_colors_rle,
+        &from_colors,
+    )?;
+
+    assert_eq!(to_colors, colors);
+    Ok(())
+}
+
+#[test]
+fn evaluate_screen_colors() {
+    let mut to_colors = [Color::Green.into(); 1000];
+    to_colors[500..600].fill(Color::Purple.into());
+
+    let mut demo = DemoBuilder::default();
+    demo.frame(FrameBuilder::default().clear_screen_colors(Color::Blue).build());
+    demo.frame(
+        FrameBuilder::default()
+            .push(Command::UpdateScreenColors(UpdateScreenColors::filled(
+                Color::Green.into(),
+            )))
+            .build(),
+    );
+    demo.frame(
+        FrameBuilder::default()
+            .push(Command::UpdateScreenColorsRLE(UpdateScreenColorsRLE::transition(
+                &[Color::Green.into(); 1000],
+                &to_colors,
+            )))
+            .build(),
+    );
+    let states = evaluate(&demo.build()).unwrap();
+
+    assert_eq!([u8::from(Color::Blue); 1000], states[1].color_ram.colors);
+    assert_eq!([u8::from(Color::Green); 1000], states[2].color_ram.colors);
+    assert_eq!(to_colors, states[3].color_ram.colors);
+}
+
+#[test]
+fn evaluate_vic2_colors() {
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .set_palette4([Color::Black, Color::White, Color::Black, Color::Black])
+            .clear_screen_chars(0)
+            .push(Command::UpdateCharsRaw(UpdateCharsRaw {
+                chars: vec![UpdateChar {
+                    char: 0,
+                    data: 0xFF00000000000000,
+                }],
+            }))
+            .build(),
+    );
+    demo.frame(FrameBuilder::default().set_border_color(Color::Red).build());
+    let states = evaluate(&demo.build()).unwrap();
+
+    let assert_pixel = |state: &State, x: usize, y: usize, color: Color| {
+        let expected = SRGB::from(color);
+        let pixel = state.get_pixel_color(x, y);
+        assert_eq!((expected.r, expected.g, expected.b), (pixel.r, pixel.g, pixel.b));
+    };
+
+    assert_pixel(&states[0], 0, 0, Color::LightBlue);
+
+    assert_pixel(&states[1], 0, 0, Color::Black);
+    assert_pixel(&states[1], BORDER_WIDTH, BORDER_HEIGHT, Color::White);
+    assert_pixel(&states[1], BORDER_WIDTH, BORDER_HEIGHT + 1, Color::Black);
+
+    assert_eq!(u8::from(Color::Red), states[2].vic2_colors.border);
+    assert_eq!(u8::from(Color::Black), states[2].vic2_colors.background);
+    assert_pixel(&states[2], 0, 0, Color::Red);
+    assert_pixel(&states[2], BORDER_WIDTH + 319, BORDER_HEIGHT + 200, Color::Red);
+    assert_pixel(&states[2], BORDER_WIDTH + 319, BORDER_HEIGHT + 192, Color::White);
+}
@@ -0,0 +1,263 @@
+use c64::image_container::Image;
+use c64_assembler::{
+    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    generator::{Generator, ProgramGenerator},
+    validator::{AssemblerResult, Validator},
+};
+use c64_colors::colors::{Color, SRGB};
+use c64_encoder::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder},
+    command::{
+        modules::{CurrentPTR, EngineConfig},
+        set_sprite_colors::SetSpriteColors,
+        set_sprite_data::{SetSpriteData, SPRITE_DATA_SIZE},
+        set_sprite_enable::SetSpriteEnable,
+        set_sprite_pointers::SetSpritePointers,
+        set_sprite_positions::{SetSpritePositions, SpritePosition},
+        Command, DecoderModule,
+    },
+    encoder::Encoder,
+    evaluator::{
+        evaluate,
+        state::{BORDER_HEIGHT, BORDER_WIDTH},
+    },
+};
+use mos6502::{
+    cpu::CPU,
+    instruction::Nmos6502,
+    memory::{Bus, Memory},
+};
+
+fn build_program(process_function: &str) -> AssemblerResult<Vec<u8>> {
+    let application = ApplicationBuilder::default()
+        .define_address("CURRENT_PTR", 0xFE)
+        .define_address("VIC_BANK_START", 0xC000)
+        .define_address("SPRITE_DATA_DST_PTR", 0x10)
+        .define_address("SPRITE_POINTERS", 0xC3F8)
+        .define_address("SPRITE_0_X", 0xD000)
+        .define_address("SPRITE_ENABLE", 0xD015)
+        .define_address("SPRITE_0_COLOR", 0xD027)
+        .module(
+            ModuleBuilder::default()
+                .instructions(
+                    InstructionBuilder::default()
+                        .jsr_addr(process_function)
+                        .raw(&[0xFF])
+                        .build(),
+                )
+                .build(),
+        )
+        .module(SetSpriteData::module())
+        .module(SetSpritePointers::module())
+        .module(SetSpritePositions::module())
+        .module(SetSpriteColors::module())
+        .module(SetSpriteEnable::module())
+        .module(CurrentPTR::module())
+        .build()?;
+
+    application.validate()?;
+
+    ProgramGenerator::default().generate(application)
+}
+
+fn run(process_function: &str, encoder: &impl Encoder) -> AssemblerResult<CPU<Memory, Nmos6502>> {
+    let mut command = vec![0; encoder.byte_size() + 1];
+    encoder.encode(&mut command[1..]);
+    let bytes = build_program(process_function)?;
+
+    let mut cpu = CPU::new(Memory::new(), Nmos6502);
+    cpu.memory.set_bytes(0x00FE, &[0x00, 0x04]);
+    cpu.memory.set_bytes(0x0800, &bytes[2..]);
+    cpu.memory.set_bytes(0x0400, &command);
+    cpu.registers.program_counter = 0x0800;
+
+    cpu.run();
+
+    assert_eq!(command.len() as u8, cpu.memory.get_byte(0x00FE));
+    assert_eq!(0x04, cpu.memory.get_byte(0x00FF));
+
+    Ok(cpu)
+}
+
+fn sprite_data(seed: u8) -> [u8; SPRITE_DATA_SIZE] {
+    let mut data = [0; SPRITE_DATA_SIZE];
+    for (index, byte) in data.iter_mut().enumerate() {
+        *byte = seed.wrapping_add(index as u8 * 3);
+    }
+    data
+}
+
+#[test]
+fn decode_set_sprite_data() -> AssemblerResult<()> {
+    for pointer in [16, 19, 31, 128, 130, 255] {
+        let set_sprite_data = SetSpriteData {
+            pointer,
+            data: sprite_data(pointer),
+        };
+
+        let mut cpu = run("set_sprite_data__process", &set_sprite_data)?;
+
+        let address = 0xC000 + pointer as u16 * 64;
+        for (offset, expected) in set_sprite_data.data.iter().enumerate() {
+            assert_eq!(
+                *expected,
+                cpu.memory.get_byte(address + offset as u16),
+                "pointer={pointer}, offset={offset}"
+            );
+        }
+        assert_eq!(0x00, cpu.memory.get_byte(address + SPRITE_DATA_SIZE as u16));
+        assert_eq!(0x00, cpu.memory.get_byte(address - 1));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn valid_sprite_pointers() {
+    let config = EngineConfig::default();
+    assert!(!config.is_valid_sprite_pointer(0));
+    assert!(!config.is_valid_sprite_pointer(15));
+    assert!(config.is_valid_sprite_pointer(16));
+    assert!(config.is_valid_sprite_pointer(31));
+    assert!(!config.is_valid_sprite_pointer(32));
+    assert!(!config.is_valid_sprite_pointer(127));
+    assert!(config.is_valid_sprite_pointer(128));
+    assert!(config.is_valid_sprite_pointer(255));
+    assert_eq!(16 + 128, config.valid_sprite_pointers().len());
+
+    // Screen chars at $4400, back screen chars at $4800 and charset at $7800.
+    let config = EngineConfig {
+        vic_bank: 1,
+        screen_slot: 1,
+        back_screen_slot: Some(2),
+        charset_slot: 7,
+        ..EngineConfig::default()
+    };
+    assert_eq!(Ok(()), config.validate());
+    assert!(config.is_valid_sprite_pointer(0));
+    assert!(config.is_valid_sprite_pointer(15));
+    assert!(!config.is_valid_sprite_pointer(16));
+    assert!(!config.is_valid_sprite_pointer(47));
+    assert!(config.is_valid_sprite_pointer(48));
+    assert!(config.is_valid_sprite_pointer(223));
+    assert!(!config.is_valid_sprite_pointer(224));
+    assert!(!config.is_valid_sprite_pointer(255));
+}
+
+#[test]
+fn decode_set_sprite_pointers() -> AssemblerResult<()> {
+    let set_sprite_pointers = SetSpritePointers {
+        pointers: [16, 17, 18, 128, 129, 200, 254, 255],
+    };
+
+    let mut cpu = run("set_sprite_pointers__process", &set_sprite_pointers)?;
+
+    for (sprite, expected) in set_sprite_pointers.pointers.iter().enumerate() {
+        assert_eq!(*expected, cpu.memory.get_byte(0xC3F8 + sprite as u16));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn decode_set_sprite_positions() -> AssemblerResult<()> {
+    let mut set_sprite_positions = SetSpritePositions::default();
+    for (sprite, position) in set_sprite_positions.positions.iter_mut().enumerate() {
+        *position = SpritePosition {
+            x: sprite as u16 * 60 + 10,
+            y: sprite as u8 * 20 + 50,
+        };
+    }
+
+    let mut cpu = run("set_sprite_positions__process", &set_sprite_positions)?;
+
+    for (sprite, position) in set_sprite_positions.positions.iter().enumerate() {
+        assert_eq!(
+            (position.x & 0xFF) as u8,
+            cpu.memory.get_byte(0xD000 + sprite as u16 * 2)
+        );
+        assert_eq!(position.y, cpu.memory.get_byte(0xD001 + sprite as u16 * 2));
+    }
+    // Sprites 5, 6 and 7 are located at x=310, x=370 and x=430.
+    assert_eq!(0b11100000, cpu.memory.get_byte(0xD010));
+
+    Ok(())
+}
+
+#[test]
+fn decode_set_sprite_colors_and_enable() -> AssemblerResult<()> {
+    let set_sprite_colors = SetSpriteColors {
+        colors: [
+            Color::Purple,
+            Color::White,
+            Color::Red,
+            Color::Cyan,
+            Color::Green,
+            Color::Blue,
+            Color::Yellow,
+            Color::LightGrey,
+        ],
+    };
+    let mut cpu = run("set_sprite_colors__process", &set_sprite_colors)?;
+    for (sprite, expected) in set_sprite_colors.colors.iter().enumerate() {
+        assert_eq!(u8::from(*expected), cpu.memory.get_byte(0xD027 + sprite as u16));
+    }
+
+    let set_sprite_enable = SetSpriteEnable { mask: 0b10100101 };
+    let mut cpu = run("set_sprite_enable__process", &set_sprite_enable)?;
+    assert_eq!(0b10100101, cpu.memory.get_byte(0xD015));
+
+    Ok(())
+}
+
+#[test]
+fn evaluate_sprites() {
+    let mut data = [0; SPRITE_DATA_SIZE];
+    // Top left pixel and the bottom right pixel of the sprite are set.
+    data[0] = 0b10000000;
+    data[SPRITE_DATA_SIZE - 1] = 0b00000001;
+
+    let mut positions = [SpritePosition::default(); 8];
+    positions[0] = SpritePosition { x: 24, y: 50 };
+    positions[1] = SpritePosition {
+        x: 24 + 280,
+        y: 50 + 100,
+    };
+
+    let mut demo = DemoBuilder::default();
+    demo.frame(
+        FrameBuilder::default()
+            .clear_screen_chars(0)
+            .push(Command::SetSpriteData(SetSpriteData { pointer: 128, data }))
+            .push(Command::SetSpritePointers(SetSpritePointers { pointers: [128; 8] }))
+            .push(Command::SetSpritePositions(SetSpritePositions { positions }))
+            .push(Command::SetSpriteColors(SetSpriteColors {
+                colors: [
+                    Color::Purple,
+                    Color::Red,
+                    Color::Black,
+                    Color::Black,
+                    Color::Black,
+                    Color::Black,
+                    Color::Black,
+                    Color::Black,
+                ],
+            }))
+            .push(Command::SetSpriteEnable(SetSpriteEnable { mask: 0b00000011 }))
+            .build(),
+    );
+    let states = evaluate(&demo.build()).unwrap();
+    let state = &states[1];
+
+    assert_eq!(data, state.sprites.data[128]);
+    assert_eq!(positions, state.sprites.positions);
+
+    let purple = SRGB::from(Color::Purple);
+    let red = SRGB::from(Color::Red);
+    let pixel = state.get_pixel_color(BORDER_WIDTH, BORDER_HEIGHT);
+    assert_eq!((purple.r, purple.g, purple.b), (pixel.r, pixel.g, pixel.b));
+    let pixel = state.get_pixel_color(BORDER_WIDTH + 280 + 23, BORDER_HEIGHT + 100 + 20);
+    assert_eq!((red.r, red.g, red.b), (pixel.r, pixel.g, pixel.b));
+    let pixel = state.get_pixel_color(BORDER_WIDTH + 1, BORDER_HEIGHT);
+    assert_ne!((purple.r, purple.g, purple.b), (pixel.r, pixel.g, pixel.b));
+}
@@ -0,0 +1,150 @@
+use c64_assembler::{
+    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    generator::{Generator, ProgramGenerator},
+    validator::{AssemblerResult, Validator},
+};
+use c64_encoder::{
+    command::{
+        modules::{CurrentPTR, ScreenCharPTR},
+        partial_update_text_mode::{PartialUpdateTextModeScreen, UpdateSingleChar},
+        update_text_mode_screen::UpdateTextModeScreen,
+        DecoderModule,
+    },
+    encoder::Encoder,
+};
+use mos6502::{
+    cpu::CPU,
+    instruction::Nmos6502,
+    memory::{Bus, Memory},
+};
+
+fn build_program(process_function: &str) -> AssemblerResult<Vec<u8>> {
+    let application = ApplicationBuilder::default()
+        .define_address("CURRENT_PTR", 0xFE)
+        .define_address("SCREEN_CHAR_PTR", 0xFC)
+        .define_address("SCREEN_CHARS_PAGE0", 0xC000)
+        .define_address("SCREEN_CHARS_PAGE1", 0xC100)
+        .define_address("SCREEN_CHARS_PAGE2", 0xC200)
+        .define_address("SCREEN_CHARS_PAGE3", 0xC300)
+        .module(
+            ModuleBuilder::default()
+                .instructions(
+                    InstructionBuilder::default()
+                        .jsr_addr(process_function)
+                        .raw(&[0xFF])
+                        .build(),
+                )
+                .build(),
+        )
+        .module(UpdateTextModeScreen::module())
+        .module(PartialUpdateTextModeScreen::module())
+        .module(CurrentPTR::module())
+        .module(ScreenCharPTR::module())
+        .build()?;
+
+    application.validate()?;
+
+    ProgramGenerator::default().generate(application)
+}
+
+fn run(process_function: &str, command: &[u8], screen_chars: &[u8; 1000]) -> AssemblerResult<CPU<Memory, Nmos6502>> {
+    let bytes = build_program(process_function)?;
+
+    let mut cpu = CPU::new(Memory::new(), Nmos6502);
+    cpu.memory.set_bytes(0x00FE, &[0x00, 0x04]);
+    cpu.memory.set_bytes(0x0800, &bytes[2..]);
+    cpu.memory.set_bytes(0x0400, command);
+    cpu.memory.set_bytes(0xC000, screen_chars);
+    cpu.registers.program_counter = 0x0800;
+
+    cpu.run();
+
+    Ok(cpu)
+}
+
+fn encode_command(encoder: &impl Encoder) -> Vec<u8> {
+    let mut command = vec![0; encoder.byte_size() + 1];
+    encoder.encode(&mut command[1..]);
+    command
+}
+
+fn current_ptr(cpu: &mut CPU<Memory, Nmos6502>) -> u16 {
+    cpu.memory.get_byte(0x00FE) as u16 | (cpu.memory.get_byte(0x00FF) as u16) << 8
+}
+
+#[test]
+fn update_text_mode_screen() -> AssemblerResult<()> {
+    let mut update_text_mode_screen = UpdateTextModeScreen::default();
+    for (offset, screen_char) in update_text_mode_screen.chars.iter_mut().enumerate() {
+        *screen_char = (offset % 251) as u8;
+    }
+    let command = encode_command(&update_text_mode_screen);
+
+    let mut cpu = run("update_text_mode_screen__process", &command, &[0xAA; 1000])?;
+
+    assert_eq!(0x0400 + command.len() as u16, current_ptr(&mut cpu));
+    for (offset, expected) in update_text_mode_screen.chars.iter().enumerate() {
+        assert_eq!(
+            *expected,
+            cpu.memory.get_byte(0xC000 + offset as u16),
+            "offset={offset}"
+        );
+    }
+    assert_eq!(0x00, cpu.memory.get_byte(0xC3E8));
+
+    Ok(())
+}
+
+#[test]
+fn partial_update_text_mode_screen() -> AssemblerResult<()> {
+    let offsets = [0_u16, 1, 255, 256, 300, 511, 512, 767, 768, 999];
+    let partial_update = PartialUpdateTextModeScreen {
+        changes: offsets
+            .iter()
+            .enumerate()
+            .map(|(index, offset)| UpdateSingleChar {
+                offset: *offset,
+                char: index as u8 + 1,
+            })
+            .collect(),
+    };
+    let command = encode_command(&partial_update);
+
+    let mut cpu = run("partial_update_text_mode_screen__process", &command, &[0xAA; 1000])?;
+
+    assert_eq!(0x0400 + command.len() as u16, current_ptr(&mut cpu));
+    for offset in 0..1000 {
+        let expected = offsets
+            .iter()
+            .position(|changed_offset| *changed_offset == offset)
+            .map_or(0xAA, |index| index as u8 + 1);
+        assert_eq!(expected, cpu.memory.get_byte(0xC000 + offset), "offset={offset}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn partial_update_text_mode_screen_transition() -> AssemblerResult<()> {
+    let from_screen_chars = [0x20; 1000];
+    let mut to_screen_chars = from_screen_chars;
+    for offset in (0..1000).step_by(3) {
+        to_screen_chars[offset] = (offset / 3) as u8;
+    }
+    let partial_update = PartialUpdateTextModeScreen::transition(&from_screen_chars, &to_screen_chars);
+    assert!(partial_update.changes.len() > 256);
+    let command = encode_command(&partial_update);
+
+    let mut cpu = run("partial_update_text_mode_screen__process", &command, &from_screen_chars)?;
+
+    assert_eq!(0x0400 + command.len() as u16, current_ptr(&mut cpu));
+    for (offset, expected) in to_screen_chars.iter().enumerate() {
+        assert_eq!(
+            *expected,
+            cpu.memory.get_byte(0xC000 + offset as u16),
+            "offset={offset}"
+        );
+    }
+
+    Ok(())
+}
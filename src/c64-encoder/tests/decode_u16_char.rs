@@ -7,6 +7,7 @@ use c64_encoder::{
     charmap::encoding::{decode_char, encode_char},
     command::{
         modules::{CurrentPTR, DecodeU16Char},
+        update_chars::{UpdateChar, UpdateCharsU16Encoded},
         update_chars_ranged::{UpdateCharRanged, UpdateCharsRangedU16Encoded},
         DecoderModule,
     },
@@ -129,6 +130,121 @@ fn decode_update_chars_ranged_u16() -> AssemblerResult<()> {
     Ok(())
 }
 
+fn build_update_chars_u16_program() -> AssemblerResult<Vec<u8>> {
+    let application = ApplicationBuilder::default()
+        .define_address("CHAR_DECODE_SRC_PTR", 0xFE)
+        .define_address("CHAR_DECODE_DST_PTR", 0xFC)
+        .define_address("CURRENT_PTR", 0xFE)
+        .define_address("CHARSET_PTR_PAGE0", 0x2000)
+        .module(
+            ModuleBuilder::default()
+                .instructions(
+                    InstructionBuilder::default()
+                        .jsr_addr("update_chars_u16__process")
+                        .raw(&[0xFF])
+                        .build(),
+                )
+                .build(),
+        )
+        .module(CurrentPTR::module())
+        .module(UpdateCharsU16Encoded::module())
+        .module(DecodeU16Char::module())
+        .build()?;
+    ProgramGenerator::default().generate(application)
+}
+
+#[test]
+fn decode_update_chars_u16() -> AssemblerResult<()> {
+    let update_chars = UpdateCharsU16Encoded {
+        chars: vec![
+            UpdateChar {
+                char: 0x02,
+                data: 0b1100110011001100001100110011001111001100110011000011001100110011,
+            },
+            UpdateChar {
+                char: 0xF1,
+                data: 0b1111000011110000111100001111000000001111000011110000111100001111,
+            },
+        ],
+    };
+    let mut command = vec![0; update_chars.byte_size() + 1];
+    update_chars.encode(&mut command[1..]);
+    print_hexdump(&command);
+
+    let bytes = build_update_chars_u16_program()?;
+
+    let mut cpu = CPU::new(Memory::new(), Nmos6502);
+    cpu.memory.set_bytes(0x00FE, &[0x00, 0x04]);
+    cpu.memory.set_bytes(0x0800, &bytes[2..]);
+    cpu.memory.set_bytes(0x0400, &command);
+    cpu.registers.program_counter = 0x0800;
+
+    cpu.run();
+
+    assert_eq!(0x08, cpu.memory.get_byte(0x00FE));
+    assert_eq!(0x04, cpu.memory.get_byte(0x00FF));
+
+    assert_eq!(0b11001100, cpu.memory.get_byte(0x2010));
+    assert_eq!(0b11001100, cpu.memory.get_byte(0x2011));
+    assert_eq!(0b00110011, cpu.memory.get_byte(0x2012));
+    assert_eq!(0b00110011, cpu.memory.get_byte(0x2013));
+    assert_eq!(0b11001100, cpu.memory.get_byte(0x2014));
+    assert_eq!(0b11001100, cpu.memory.get_byte(0x2015));
+    assert_eq!(0b00110011, cpu.memory.get_byte(0x2016));
+    assert_eq!(0b00110011, cpu.memory.get_byte(0x2017));
+
+    assert_eq!(0b11110000, cpu.memory.get_byte(0x2788));
+    assert_eq!(0b11110000, cpu.memory.get_byte(0x2789));
+    assert_eq!(0b11110000, cpu.memory.get_byte(0x278A));
+    assert_eq!(0b11110000, cpu.memory.get_byte(0x278B));
+    assert_eq!(0b00001111, cpu.memory.get_byte(0x278C));
+    assert_eq!(0b00001111, cpu.memory.get_byte(0x278D));
+    assert_eq!(0b00001111, cpu.memory.get_byte(0x278E));
+    assert_eq!(0b00001111, cpu.memory.get_byte(0x278F));
+
+    Ok(())
+}
+
+#[test]
+fn decode_update_chars_u16_all_chars() -> AssemblerResult<()> {
+    let update_chars = UpdateCharsU16Encoded {
+        chars: (0..=255_u8)
+            .rev()
+            .map(|char| UpdateChar {
+                char,
+                data: decode_char(u16::from(char) * 257),
+            })
+            .collect(),
+    };
+    let mut command = vec![0; update_chars.byte_size() + 1];
+    update_chars.encode(&mut command[1..]);
+    assert_eq!(0, command[1]);
+
+    let bytes = build_update_chars_u16_program()?;
+
+    let mut cpu = CPU::new(Memory::new(), Nmos6502);
+    cpu.memory.set_bytes(0x00FE, &[0x00, 0x04]);
+    cpu.memory.set_bytes(0x0800, &bytes[2..]);
+    cpu.memory.set_bytes(0x0400, &command);
+    cpu.registers.program_counter = 0x0800;
+
+    cpu.run();
+
+    for update_char in &update_chars.chars {
+        let address = 0x2000 + u16::from(update_char.char) * 8;
+        for (row, expected) in update_char.data.to_be_bytes().iter().enumerate() {
+            assert_eq!(
+                *expected,
+                cpu.memory.get_byte(address + row as u16),
+                "char={:02X}, row={row}",
+                update_char.char
+            );
+        }
+    }
+
+    Ok(())
+}
+
 #[test]
 fn decode_u16_roundtrip() {
     for encoded in 0..=65535_u16 {
@@ -0,0 +1,158 @@
+use c64_assembler::{
+    builder::{ApplicationBuilder, InstructionBuilder, ModuleBuilder},
+    generator::{Generator, ProgramGenerator},
+    validator::{AssemblerResult, Validator},
+};
+use c64_encoder::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder},
+    command::{
+        modules::{CurrentPTR, EngineBuilder, EngineOptions, Raster, DEFAULT_RASTER_LINE, MIN_RASTER_LINE},
+        wait_frames::WaitFrames,
+        DecoderModule,
+    },
+    encoder::Encoder,
+    evaluator::evaluate,
+};
+use mos6502::{
+    cpu::CPU,
+    instruction::Nmos6502,
+    memory::{Bus, Memory},
+};
+
+const RASTER_LINES_PER_FRAME: u16 = 312;
+/// Number of instructions executed per raster line. Must be larger than the raster polling loop.
+const INSTRUCTIONS_PER_RASTER_LINE: usize = 4;
+
+/// Run the program and advance the raster line every few executed instructions.
+///
+/// Returns the raster lines in the order they were displayed.
+fn run_with_raster(cpu: &mut CPU<Memory, Nmos6502>) -> Vec<u16> {
+    let mut num_instructions = 0;
+    let mut raster_lines = vec![0];
+    while let Some(decoded_instr) = cpu.fetch_next_and_decode() {
+        cpu.execute_instruction(decoded_instr);
+        num_instructions += 1;
+        if num_instructions % INSTRUCTIONS_PER_RASTER_LINE == 0 {
+            let raster_line = (raster_lines.last().unwrap() + 1) % RASTER_LINES_PER_FRAME;
+            cpu.memory.set_byte(0xD012, (raster_line & 0xFF) as u8);
+            raster_lines.push(raster_line);
+        }
+    }
+    raster_lines
+}
+
+fn num_times_displayed(raster_lines: &[u16], raster_line: u8) -> usize {
+    raster_lines.iter().filter(|line| **line == raster_line as u16).count()
+}
+
+#[test]
+fn decode_wait_frames() -> AssemblerResult<()> {
+    let application = ApplicationBuilder::default()
+        .define_address("CURRENT_PTR", 0xFE)
+        .define_address("RASTER_LINE", 0xD012)
+        .module(
+            ModuleBuilder::default()
+                .instructions(
+                    InstructionBuilder::default()
+                        .jsr_addr("wait_frames__process")
+                        .raw(&[0xFF])
+                        .build(),
+                )
+                .build(),
+        )
+        .module(WaitFrames::module())
+        .module(Raster::module())
+        .module(CurrentPTR::module())
+        .build()?;
+    application.validate()?;
+    let bytes = ProgramGenerator::default().generate(application)?;
+
+    for num_frames in [0, 1, 3] {
+        let wait_frames = WaitFrames { num_frames };
+        let mut command = vec![0; wait_frames.byte_size() + 1];
+        wait_frames.encode(&mut command[1..]);
+
+        let mut cpu = CPU::new(Memory::new(), Nmos6502);
+        cpu.memory.set_bytes(0x00FE, &[0x00, 0x04]);
+        cpu.memory.set_bytes(0x0800, &bytes[2..]);
+        cpu.memory.set_bytes(0x0400, &command);
+        cpu.registers.program_counter = 0x0800;
+
+        let raster_lines = run_with_raster(&mut cpu);
+
+        assert_eq!(0x02, cpu.memory.get_byte(0x00FE));
+        assert_eq!(
+            num_frames as usize,
+            num_times_displayed(&raster_lines, DEFAULT_RASTER_LINE)
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn engine_sync_raster_line() -> AssemblerResult<()> {
+    let mut demo = DemoBuilder::default();
+    demo.frame(FrameBuilder::default().wait_frames(2).build());
+    let engine_data = demo.build();
+
+    let application = ApplicationBuilder::default()
+        .include_vic2_defines()
+        .module(
+            ModuleBuilder::default()
+                .instructions(
+                    InstructionBuilder::default()
+                        .jsr_addr("engine__init")
+                        .jsr_addr("engine__frame__process")
+                        .raw(&[0xFF])
+                        .build(),
+                )
+                .build(),
+        )
+        .add_engine_with_options(EngineOptions {
+            sync_raster_line: Some(100),
+            ..EngineOptions::default()
+        })
+        .module(
+            ModuleBuilder::default()
+                .name("engine_data")
+                .instructions(
+                    InstructionBuilder::default()
+                        .label("engine_data")
+                        .raw(&engine_data)
+                        .build(),
+                )
+                .build(),
+        )
+        .build()?;
+    application.validate()?;
+    let bytes = ProgramGenerator::default().generate(application)?;
+
+    let mut cpu = CPU::new(Memory::new(), Nmos6502);
+    cpu.memory.set_bytes(0x0800, &bytes[2..]);
+    cpu.registers.program_counter = 0x0800;
+
+    let raster_lines = run_with_raster(&mut cpu);
+
+    let first_line_100 = raster_lines.iter().position(|line| *line == 100).unwrap();
+    let first_line_251 = raster_lines
+        .iter()
+        .position(|line| *line == DEFAULT_RASTER_LINE as u16)
+        .unwrap();
+    assert!(first_line_100 < first_line_251, "frame should be synced before waiting");
+    assert_eq!(2, num_times_displayed(&raster_lines, DEFAULT_RASTER_LINE));
+
+    let states = evaluate(&engine_data).unwrap();
+    assert_eq!(3, states[1].duration);
+
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "sync_raster_line must be 56 or above")]
+fn engine_rejects_sync_raster_line_below_56() {
+    ApplicationBuilder::default().add_engine_with_options(EngineOptions {
+        sync_raster_line: Some(MIN_RASTER_LINE - 1),
+        ..EngineOptions::default()
+    });
+}
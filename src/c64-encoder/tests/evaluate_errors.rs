@@ -0,0 +1,131 @@
+use c64_encoder::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder},
+    command::{
+        goto_frame::GotoFrame,
+        partial_update_text_mode::{PartialUpdateTextModeScreen, UpdateSingleChar},
+        update_screen_chars_rle::{RLECommand, RLEPacket, UpdateScreenCharsRLE},
+        Command,
+    },
+    decoder::DecodeError,
+    evaluator::{evaluate, evaluate_with_trace, EvaluateError, EvaluateErrorReason},
+};
+
+fn demo() -> DemoBuilder {
+    let mut demo = DemoBuilder::default();
+    demo.frame(FrameBuilder::default().clear_screen_chars(1).build())
+        .frame(FrameBuilder::default().clear_screen_chars(2).build());
+    demo
+}
+
+#[test]
+fn evaluate_truncated_demo() {
+    let demo_bytes = demo().build();
+
+    assert_eq!(
+        Some(EvaluateError {
+            offset: 8,
+            frame: Some(1),
+            command: Some(0),
+            reason: EvaluateErrorReason::Decode(DecodeError::UnexpectedEnd),
+        }),
+        evaluate(&demo_bytes[..demo_bytes.len() - 1]).err()
+    );
+    assert_eq!(
+        Some(EvaluateError {
+            offset: 0,
+            frame: None,
+            command: None,
+            reason: EvaluateErrorReason::Decode(DecodeError::UnexpectedEnd),
+        }),
+        evaluate(&demo_bytes[..1]).err()
+    );
+}
+
+#[test]
+fn evaluate_unknown_command_type() {
+    let mut demo_bytes = demo().build();
+    demo_bytes[8] = 0xEE;
+
+    assert_eq!(
+        Some(EvaluateError {
+            offset: 8,
+            frame: Some(1),
+            command: Some(0),
+            reason: EvaluateErrorReason::Decode(DecodeError::UnknownCommandType(0xEE)),
+        }),
+        evaluate(&demo_bytes).err()
+    );
+}
+
+#[test]
+fn evaluate_unknown_frame_offset() {
+    let mut demo = demo();
+    demo.frames[1].push(Command::GotoFrame(GotoFrame { offset: 3 }));
+
+    assert_eq!(
+        Some(EvaluateError {
+            offset: 10,
+            frame: Some(1),
+            command: Some(1),
+            reason: EvaluateErrorReason::UnknownFrameOffset(3),
+        }),
+        evaluate(&demo.build()).err()
+    );
+}
+
+#[test]
+fn evaluate_partial_update_outside_screen() {
+    let mut demo = demo();
+    demo.frames[1].push(Command::PartialUpdateTextModeScreen(PartialUpdateTextModeScreen {
+        changes: vec![
+            UpdateSingleChar { offset: 999, char: 1 },
+            UpdateSingleChar { offset: 1000, char: 2 },
+        ],
+    }));
+
+    assert_eq!(
+        Some(EvaluateError {
+            offset: 10,
+            frame: Some(1),
+            command: Some(1),
+            reason: EvaluateErrorReason::ScreenOffsetOutOfRange(1000),
+        }),
+        evaluate(&demo.build()).err()
+    );
+}
+
+#[test]
+fn evaluate_rle_packets_outside_screen() {
+    let mut demo = demo();
+    // 16 packets of 63 screen chars cover 1008 screen chars.
+    demo.frames[1].push(Command::UpdateScreenCharsRLE(UpdateScreenCharsRLE {
+        rle_packets: vec![
+            RLEPacket {
+                num_screen_chars: 63,
+                command: RLECommand::UpdateWithSingleValue(0x20),
+            };
+            16
+        ],
+    }));
+
+    assert_eq!(
+        Some(EvaluateError {
+            offset: 10,
+            frame: Some(1),
+            command: Some(1),
+            reason: EvaluateErrorReason::ScreenOffsetOutOfRange(1007),
+        }),
+        evaluate(&demo.build()).err()
+    );
+}
+
+#[test]
+fn evaluate_trace() {
+    let demo_bytes = demo().build();
+    let mut lines = vec![];
+    let states = evaluate_with_trace(&demo_bytes, |line| lines.push(line.to_string())).unwrap();
+
+    assert_eq!(3, states.len());
+    assert_eq!("decoding 10 demo-bytes", lines[0]);
+    assert!(lines.contains(&"decoding command=2.1".to_string()));
+}
@@ -0,0 +1,90 @@
+use c64_encoder::{
+    builder::{demo::DemoBuilder, frame::FrameBuilder},
+    command::{update_screen_chars_rle::UpdateScreenCharsRLE, update_text_mode_screen::UpdateTextModeScreen, Command},
+    profiler::{profile, ProfileOptions, VideoStandard},
+};
+
+fn demo() -> Vec<u8> {
+    let mut to_screen = [0x20; 1000];
+    to_screen[100..200].fill(0x41);
+    let mut demo = DemoBuilder::default();
+    demo.frame(FrameBuilder::default().clear_screen_chars(0x20).build())
+        .frame(
+            FrameBuilder::default()
+                .update_text_mode_screen(UpdateTextModeScreen::filled(0x20))
+                .set_border_color(c64_colors::colors::Color::Red)
+                .build(),
+        )
+        .frame(
+            FrameBuilder::default()
+                .push(Command::UpdateScreenCharsRLE(UpdateScreenCharsRLE::transition(
+                    &[0x20; 1000],
+                    &to_screen,
+                )))
+                .wait_frames(1)
+                .build(),
+        );
+    demo.build()
+}
+
+#[test]
+fn profile_frames_and_commands() {
+    let profile = profile(&demo(), ProfileOptions::default()).unwrap();
+
+    assert_eq!(3, profile.frames.len());
+    for frame in &profile.frames {
+        let command_cycles = frame.commands.iter().map(|command| command.cycles).sum::<usize>();
+        assert_eq!(frame.cycles, command_cycles + frame.overhead_cycles);
+        assert!(frame.commands.iter().all(|command| command.cycles > 0));
+    }
+    assert_eq!(
+        vec!["UpdateTextModeScreen", "SetBorderColor"],
+        profile.frames[1]
+            .commands
+            .iter()
+            .map(|command| command.name)
+            .collect::<Vec<&str>>()
+    );
+    let update_text_mode_screen = &profile.frames[1].commands[0];
+    let set_border_color = &profile.frames[1].commands[1];
+    assert!(update_text_mode_screen.cycles > 1000 * set_border_color.cycles / 100);
+    assert_eq!(2, profile.frames[2].duration);
+
+    let command_types = profile.command_types();
+    assert_eq!(5, command_types.len());
+    assert!(command_types
+        .windows(2)
+        .all(|pair| pair[0].total_cycles >= pair[1].total_cycles));
+    assert_eq!(0, profile.frames_over_budget().count());
+}
+
+#[test]
+fn profile_budget() {
+    assert_eq!(19656, ProfileOptions::new(VideoStandard::PAL).cycles_per_frame);
+    assert_eq!(17095, ProfileOptions::new(VideoStandard::NTSC).cycles_per_frame);
+
+    let options = ProfileOptions {
+        cycles_per_frame: 2000,
+        ..ProfileOptions::new(VideoStandard::NTSC)
+    };
+    let profile = profile(&demo(), options).unwrap();
+    let over_budget = profile
+        .frames_over_budget()
+        .map(|frame| frame.played_frame)
+        .collect::<Vec<usize>>();
+    assert_eq!(vec![1, 2, 3], over_budget);
+
+    let mut csv = vec![];
+    profile.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines = csv.lines().collect::<Vec<&str>>();
+    assert_eq!("played_frame,frame,cycles,overhead_cycles,budget,over_budget", lines[0]);
+    assert_eq!(4, lines.len());
+    assert!(lines[1].starts_with("1,0,") && lines[1].ends_with(",2000,true"));
+
+    let mut table = vec![];
+    profile.write_table(&mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    assert_eq!(3, table.matches("OVER BUDGET").count());
+    assert!(table.contains("UpdateScreenCharsRLE"));
+}